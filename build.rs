@@ -0,0 +1,109 @@
+use std::{
+    env,
+    fmt::Write,
+    fs,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+/// Directories embedded with the `embedded-assets` feature, when they exist.
+const ASSET_DIRECTORIES: [&str; 2] = ["fonts", "textures"];
+
+/// Materials are always embedded, like the shaders they reference.
+const MATERIAL_DIRECTORY: &str = "materials";
+
+/// Compiles every shader in `shaders/src` and writes `assets.rs` to `OUT_DIR`, a table of the
+/// SPIR-V, materials and other assets embedded into the binary.
+fn main() {
+    let out_dir = PathBuf::from(env::var_os("OUT_DIR").unwrap());
+    let mut assets = Vec::new();
+    let mut prebuilt = false;
+
+    println!("cargo:rerun-if-changed=shaders/src");
+    for source in sorted_files(Path::new("shaders/src")) {
+        println!("cargo:rerun-if-changed={}", source.display());
+
+        // Named like `text.frag.glsl`, where the second extension is the stage
+        let stem = Path::new(source.file_stem().unwrap());
+        let stage = match stem.extension().and_then(|stage| stage.to_str()) {
+            Some("frag") => "fragment",
+            Some("vert") => "vertex",
+            _ => continue,
+        };
+
+        let name = format!("{}.spv", stem.display());
+        let output = out_dir.join(&name);
+        prebuilt |= !compile(&source, stage, &output, &Path::new("shaders").join(&name));
+        assets.push((format!("shaders/{}", name), output));
+    }
+
+    if prebuilt {
+        println!("cargo:warning=glslc not found, using the prebuilt SPIR-V in shaders/");
+    }
+
+    embed(MATERIAL_DIRECTORY, &mut assets);
+    if env::var_os("CARGO_FEATURE_EMBEDDED_ASSETS").is_some() {
+        for directory in ASSET_DIRECTORIES.iter() {
+            embed(directory, &mut assets);
+        }
+    }
+
+    let mut table = String::from("static EMBEDDED: &[(&str, &[u8])] = &[\n");
+    for (name, path) in assets {
+        writeln!(table, "    ({:?}, include_bytes!({:?})),", name, path).unwrap();
+    }
+    table.push_str("];\n");
+    fs::write(out_dir.join("assets.rs"), table).unwrap();
+}
+
+/// Compiles with `glslc`, falling back to the checked in SPIR-V at `fallback` and returning false
+/// when `glslc` isn't installed. Errors in the shader itself fail the build.
+fn compile(source: &Path, stage: &str, output: &Path, fallback: &Path) -> bool {
+    let result = Command::new("glslc")
+        .arg(format!("-fshader-stage={}", stage))
+        .arg(source)
+        .arg("-o")
+        .arg(output)
+        .output();
+    match result {
+        Ok(result) if result.status.success() => return true,
+        Ok(result) => panic!(
+            "Failed to compile {}:\n{}",
+            source.display(),
+            String::from_utf8_lossy(&result.stderr)
+        ),
+        Err(e) if e.kind() == ErrorKind::NotFound => {
+            println!("cargo:rerun-if-changed={}", fallback.display());
+            fs::copy(fallback, output).unwrap();
+            return false;
+        }
+        Err(e) => panic!("Failed to run glslc: {}", e),
+    }
+}
+
+/// Adds every file in `directory` to the embedded assets.
+fn embed(directory: &str, assets: &mut Vec<(String, PathBuf)>) {
+    println!("cargo:rerun-if-changed={}", directory);
+    for path in sorted_files(Path::new(directory)) {
+        let name = format!(
+            "{}/{}",
+            directory,
+            path.file_name().unwrap().to_string_lossy()
+        );
+        assets.push((name, fs::canonicalize(path).unwrap()));
+    }
+}
+
+/// Files directly in `directory`, in a stable order. Empty if it doesn't exist.
+fn sorted_files(directory: &Path) -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = match fs::read_dir(directory) {
+        Ok(entries) => entries
+            .filter_map(|entry| Some(entry.ok()?.path()))
+            .filter(|path| path.is_file())
+            .collect(),
+        Err(_) => Vec::new(),
+    };
+    files.sort();
+    return files;
+}
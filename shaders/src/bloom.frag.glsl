@@ -0,0 +1,35 @@
+#version 450 core
+
+layout(location = 0) in vec2 coordinates;
+layout(set = 0, binding = 0) uniform sampler2D scene;
+
+// Luminance threshold, intensity and blur radius in pixels
+layout(push_constant) uniform Parameters {
+    vec4 parameters;
+} post;
+
+// Outputs
+layout(location = 0) out vec4 colour;
+
+// The part of the colour brighter than the threshold
+vec3 bright(vec2 offset) {
+    vec3 rgb = texture(scene, coordinates + offset).rgb;
+    float luminance = dot(rgb, vec3(0.2126, 0.7152, 0.0722));
+    return rgb * max(luminance - post.parameters.x, 0.0) / max(luminance, 1.0e-4);
+}
+
+void main() {
+    vec2 texel = post.parameters.z / vec2(textureSize(scene, 0));
+    vec3 glow = vec3(0.0);
+    float total = 0.0;
+    for (int x = -3; x <= 3; x++) {
+        for (int y = -3; y <= 3; y++) {
+            float weight = exp(-float(x * x + y * y) / 8.0);
+            glow += bright(vec2(x, y) * texel) * weight;
+            total += weight;
+        }
+    }
+
+    vec4 original = texture(scene, coordinates);
+    colour = vec4(original.rgb + glow / total * post.parameters.y, original.a);
+}
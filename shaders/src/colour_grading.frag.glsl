@@ -0,0 +1,20 @@
+#version 450 core
+
+layout(location = 0) in vec2 coordinates;
+layout(set = 0, binding = 0) uniform sampler2D scene;
+
+// Contrast, saturation and brightness, where 1 leaves the image unchanged
+layout(push_constant) uniform Parameters {
+    vec4 parameters;
+} post;
+
+// Outputs
+layout(location = 0) out vec4 colour;
+
+void main() {
+    vec4 original = texture(scene, coordinates);
+    vec3 rgb = (original.rgb - 0.5) * post.parameters.x + 0.5;
+    float luminance = dot(rgb, vec3(0.2126, 0.7152, 0.0722));
+    rgb = mix(vec3(luminance), rgb, post.parameters.y) * post.parameters.z;
+    colour = vec4(max(rgb, 0.0), original.a);
+}
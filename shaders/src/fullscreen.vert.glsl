@@ -0,0 +1,9 @@
+#version 450 core
+
+layout(location = 0) out vec2 coordinates;
+
+// A single triangle covering the screen, generated without a vertex buffer
+void main() {
+    coordinates = vec2((gl_VertexIndex << 1) & 2, gl_VertexIndex & 2);
+    gl_Position = vec4(coordinates * 2.0 - 1.0, 0.0, 1.0);
+}
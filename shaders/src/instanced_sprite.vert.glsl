@@ -0,0 +1,21 @@
+#version 450 core
+
+// Corner of the unit quad, from (0, 0) at the top-left to (1, 1) at the bottom-right
+layout(location = 0) in vec2 corner;
+
+// Per instance. The quad is mapped to clip space by an origin at its top-left corner and axes
+// along its top and left edges.
+layout(location = 1) in vec2 origin;
+layout(location = 2) in vec4 axes;
+layout(location = 3) in vec4 source;
+layout(location = 4) in vec4 tint;
+
+layout(location = 0) out vec2 vposition;
+layout(location = 1) out vec4 vtint;
+
+void main() {
+    vec2 position = origin + corner.x * axes.xy + corner.y * axes.zw;
+    gl_Position = vec4(position, 0.0, 1.0);
+    vposition = mix(source.xy, source.zw, corner);
+    vtint = tint;
+}
@@ -0,0 +1,11 @@
+#version 450 core
+
+layout(location = 0) in vec2 position;
+layout(location = 1) in vec4 vi_colour;
+
+layout(location = 0) out vec4 colour;
+
+void main() {
+    gl_Position = vec4(position, 0.0, 1.0);
+    colour = vi_colour;
+}
@@ -0,0 +1,19 @@
+#version 450 core
+
+layout(location = 0) in vec2 coordinates;
+layout(set = 0, binding = 0) uniform sampler2D scene;
+
+// Intensity and the distance between lines in pixels
+layout(push_constant) uniform Parameters {
+    vec4 parameters;
+} post;
+
+// Outputs
+layout(location = 0) out vec4 colour;
+
+void main() {
+    vec4 original = texture(scene, coordinates);
+    float phase = gl_FragCoord.y / max(post.parameters.y, 1.0) * 3.14159265;
+    float shade = post.parameters.x * (1.0 - abs(sin(phase)));
+    colour = vec4(original.rgb * (1.0 - shade), original.a);
+}
@@ -0,0 +1,42 @@
+#version 450 core
+
+layout(location = 0) in vec2 vs_Coordinates;
+layout(location = 1) in vec4 vs_Colour;
+layout(set = 0, binding = 0) uniform sampler2D glyph_distance_field;
+
+// Distances are in the units stored in the atlas, where 0.5 lies on the outline of the glyph
+layout(push_constant) uniform Style {
+    vec4 outline_colour;
+    vec4 shadow_colour;
+    vec2 shadow_offset;
+    float outline_width;
+} style;
+
+// Outputs
+layout(location = 0) out vec4 colour;
+
+// Composites `top` over `bottom`, both with straight alpha
+vec4 over(vec4 top, vec4 bottom) {
+    float alpha = top.a + bottom.a * (1.0 - top.a);
+    vec3 rgb = top.rgb * top.a + bottom.rgb * bottom.a * (1.0 - top.a);
+    return vec4(rgb / max(alpha, 1.0e-5), alpha);
+}
+
+void main() {
+    vec2 size = vec2(textureSize(glyph_distance_field, 0));
+    float distance = texture(glyph_distance_field, vs_Coordinates / size).r;
+    float shadow_distance =
+        texture(glyph_distance_field, (vs_Coordinates - style.shadow_offset) / size).r;
+
+    // Antialias over roughly one screen pixel, whatever the scale the text is drawn at
+    float smoothing = max(fwidth(distance), 1.0e-4);
+    float outline_edge = 0.5 - style.outline_width;
+    float fill = smoothstep(0.5 - smoothing, 0.5 + smoothing, distance);
+    float outline = smoothstep(outline_edge - smoothing, outline_edge + smoothing, distance);
+    float shadow = smoothstep(outline_edge - smoothing, outline_edge + smoothing, shadow_distance);
+
+    vec4 shadow_layer = vec4(style.shadow_colour.rgb, style.shadow_colour.a * shadow);
+    vec4 outline_layer = vec4(style.outline_colour.rgb, style.outline_colour.a * outline);
+    vec4 fill_layer = vec4(vs_Colour.rgb, vs_Colour.a * fill);
+    colour = over(fill_layer, over(outline_layer, shadow_layer));
+}
@@ -2,12 +2,14 @@
 
 layout(location = 0) in vec2 vs_Coordinates;
 layout(location = 1) in vec4 vs_Colour;
-layout(set = 0, binding = 0) uniform usampler2D glyph_coverage_map;
+layout(set = 0, binding = 0) uniform sampler2D glyph_coverage_map;
 
 // Outputs
 layout(location = 0) out vec4 colour;
 
 void main() {
-    float alpha = texture(glyph_coverage_map, vs_Coordinates).r;
-    colour = vec4(vs_Colour.xyz, alpha);
+    // Glyph coordinates are in atlas pixels, so they stay valid when the atlas grows
+    vec2 coordinates = vs_Coordinates / vec2(textureSize(glyph_coverage_map, 0));
+    float alpha = texture(glyph_coverage_map, coordinates).r;
+    colour = vec4(vs_Colour.rgb, vs_Colour.a * alpha);
 }
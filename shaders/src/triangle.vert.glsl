@@ -2,10 +2,13 @@
 
 layout(location = 0) in vec2 position;
 layout(location = 1) in vec2 vi_tc;
+layout(location = 2) in vec4 vi_tint;
 
 layout(location = 0) out vec2 vposition;
+layout(location = 1) out vec4 vtint;
 
 void main() {
     gl_Position = vec4(position, 0.0, 1.0);
     vposition = vi_tc;
+    vtint = vi_tint;
 }
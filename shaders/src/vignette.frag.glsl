@@ -0,0 +1,20 @@
+#version 450 core
+
+layout(location = 0) in vec2 coordinates;
+layout(set = 0, binding = 0) uniform sampler2D scene;
+
+// Intensity, the radius darkening starts at and the width it fades in over
+layout(push_constant) uniform Parameters {
+    vec4 parameters;
+} post;
+
+// Outputs
+layout(location = 0) out vec4 colour;
+
+void main() {
+    vec4 original = texture(scene, coordinates);
+    float distance = length(coordinates - 0.5) * 2.0;
+    float edge = post.parameters.y;
+    float shade = smoothstep(edge, edge + post.parameters.z, distance) * post.parameters.x;
+    colour = vec4(original.rgb * (1.0 - shade), original.a);
+}
@@ -0,0 +1,62 @@
+//! Shaders and materials, and with the `embedded-assets` feature any fonts and textures, are
+//! built into the binary so it runs from any directory. Files in the directory named by
+//! `THYROX_ASSETS` take precedence, so assets can be replaced without rebuilding.
+
+use std::{
+    borrow::Cow,
+    path::{Path, PathBuf},
+};
+
+// Generated by build.rs
+include!(concat!(env!("OUT_DIR"), "/assets.rs"));
+
+/// Used by `Renderer::load_font` when the requested font can't be read.
+pub const FALLBACK_FONT: &str = "fonts/DejaVuSans.ttf";
+
+/// The override directory, if one is set.
+pub fn directory() -> Option<PathBuf> {
+    return std::env::var_os("THYROX_ASSETS").map(PathBuf::from);
+}
+
+/// Looks for `path` in the override directory, then among the embedded assets, and finally on
+/// the filesystem as given.
+pub fn read<P>(path: P) -> Option<Cow<'static, [u8]>>
+where
+    P: AsRef<Path>,
+{
+    let path = path.as_ref();
+    if let Some(data) = directory().and_then(|directory| std::fs::read(directory.join(path)).ok()) {
+        return Some(Cow::Owned(data));
+    }
+
+    let embedded = EMBEDDED.iter().find(|(name, _)| Path::new(name) == path);
+    if let Some((_, data)) = embedded {
+        return Some(Cow::Borrowed(data));
+    }
+
+    return std::fs::read(path).ok().map(Cow::Owned);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn read_order() {
+        // Materials are always embedded and are also on the filesystem, relative to the crate
+        let material = "materials/sprite.material";
+        assert!(matches!(read(material), Some(Cow::Borrowed(_))));
+        assert!(matches!(read("Cargo.toml"), Some(Cow::Owned(_))));
+        assert!(read("materials/missing.material").is_none());
+
+        let directory = std::env::temp_dir().join(format!("thyrox-assets-{}", std::process::id()));
+        std::fs::create_dir_all(directory.join("materials")).unwrap();
+        std::fs::write(directory.join(material), "overridden").unwrap();
+        std::env::set_var("THYROX_ASSETS", &directory);
+        let overridden = read(material);
+        std::env::remove_var("THYROX_ASSETS");
+        std::fs::remove_dir_all(&directory).unwrap();
+
+        assert_eq!(overridden.as_deref(), Some(&b"overridden"[..]));
+    }
+}
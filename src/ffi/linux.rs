@@ -1,11 +1,21 @@
 use std::{
-    ffi::{c_void, CStr},
-    os::raw::c_char,
+    ffi::{c_void, CStr, CString, OsStr, OsString},
+    os::{raw::c_char, unix::ffi::OsStrExt},
+    path::Path,
     ptr,
 };
 
+pub const IN_CLOSE_WRITE: u32 = 0x00000008;
+pub const IN_MOVED_TO: u32 = 0x00000080;
+const IN_CLOEXEC: i32 = 0o2000000;
+const IN_NONBLOCK: i32 = 0o4000;
+
 extern "C" {
+    fn close(fd: i32) -> i32;
     pub fn free(ptr: *mut c_void);
+    fn inotify_add_watch(fd: i32, path_name: *const c_char, mask: u32) -> i32;
+    fn inotify_init1(flags: i32) -> i32;
+    fn read(fd: i32, buf: *mut c_void, count: usize) -> isize;
 }
 
 #[link(name = "dl")]
@@ -69,3 +79,83 @@ impl Drop for Library {
         }
     }
 }
+
+/// Header of each event read from an inotify instance, followed by `len` bytes of NUL-padded file
+/// name.
+#[repr(C)]
+struct InotifyEvent {
+    wd: i32,
+    mask: u32,
+    cookie: u32,
+    len: u32,
+}
+
+/// A non-blocking inotify instance.
+pub struct Inotify {
+    fd: i32,
+}
+
+impl Inotify {
+    /// Watches the directory or file at `path` for the events in `mask`, returning the watch
+    /// descriptor reported with its events.
+    pub fn add_watch(&self, path: &Path, mask: u32) -> Option<i32> {
+        let path = CString::new(path.as_os_str().as_bytes()).ok()?;
+        let wd = unsafe { inotify_add_watch(self.fd, path.as_ptr(), mask) };
+        if wd < 0 {
+            return None;
+        }
+
+        return Some(wd);
+    }
+
+    pub fn init() -> Option<Self> {
+        let fd = unsafe { inotify_init1(IN_NONBLOCK | IN_CLOEXEC) };
+        if fd < 0 {
+            return None;
+        }
+
+        return Some(Self { fd });
+    }
+
+    /// Returns the watch descriptor and file name of every event queued since the last call,
+    /// without blocking.
+    pub fn read_events(&self) -> Vec<(i32, OsString)> {
+        let header_size = std::mem::size_of::<InotifyEvent>();
+        // Aligned for the event headers, and large enough for any single event
+        let mut buffer = [0u64; 512];
+        let mut events = Vec::new();
+        loop {
+            let bytes = unsafe {
+                read(
+                    self.fd,
+                    buffer.as_mut_ptr() as *mut c_void,
+                    std::mem::size_of_val(&buffer),
+                )
+            };
+            if bytes <= 0 {
+                return events;
+            }
+
+            let data =
+                unsafe { std::slice::from_raw_parts(buffer.as_ptr() as *const u8, bytes as usize) };
+            let mut offset = 0;
+            while offset + header_size <= data.len() {
+                let event =
+                    unsafe { ptr::read_unaligned(data[offset..].as_ptr() as *const InotifyEvent) };
+                let name_start = offset + header_size;
+                let name = &data[name_start..name_start + event.len as usize];
+                let name = name.split(|&b| b == 0).next().unwrap_or(&[]);
+                events.push((event.wd, OsStr::from_bytes(name).to_os_string()));
+                offset = name_start + event.len as usize;
+            }
+        }
+    }
+}
+
+impl Drop for Inotify {
+    fn drop(&mut self) {
+        unsafe {
+            close(self.fd);
+        }
+    }
+}
@@ -24,7 +24,7 @@ macro_rules! define_handle {
 }
 
 #[cfg(target_os = "linux")]
-pub use linux::Library;
+pub use linux::{Inotify, Library};
 
 #[cfg(target_os = "linux")]
 pub mod linux;
@@ -14,11 +14,13 @@ pub const HEADER_VERSION_COMPLETE: u32 = make_version!(1, 2, HEADER_VERSION);
 // Constants
 pub const API_VERSION_1_0: u32 = make_version!(1, 0, 0);
 pub const API_VERSION_1_2: u32 = make_version!(1, 2, 0);
+pub const LOD_CLAMP_NONE: f32 = 1000.0;
 pub const MAX_DESCRIPTION_SIZE: usize = 256;
 pub const MAX_EXTENSION_NAME_SIZE: usize = 256;
 pub const MAX_MEMORY_HEAPS: usize = 16;
 pub const MAX_MEMORY_TYPES: usize = 32;
 pub const MAX_PHYSICAL_DEVICE_NAME_SIZE: usize = 256;
+pub const PIPELINE_CACHE_HEADER_VERSION_ONE: u32 = 1;
 pub const QUEUE_FAMILY_IGNORED: u32 = !0;
 pub const SUBPASS_EXTERNAL: u32 = !0;
 pub const UUID_SIZE: usize = 16;
@@ -37,12 +39,17 @@ pub type CommandBufferUsageFlags = Flags;
 pub type CommandPoolCreateFlags = Flags;
 pub type CommandPoolResetFlags = Flags;
 pub type CullModeFlags = Flags;
+pub type DebugUtilsMessageSeverityFlagsEXT = Flags;
+pub type DebugUtilsMessageTypeFlagsEXT = Flags;
+pub type DebugUtilsMessengerCallbackDataFlagsEXT = Flags;
+pub type DebugUtilsMessengerCreateFlagsEXT = Flags;
 pub type DescriptorPoolCreateFlags = Flags;
 pub type DescriptorPoolResetFlags = Flags;
 pub type DescriptorSetLayoutCreateFlags = Flags;
 pub type DeviceSize = u64;
 pub type FenceCreateFlags = Flags;
 pub type Flags = u32;
+pub type FormatFeatureFlags = Flags;
 pub type FramebufferCreateFlags = Flags;
 pub type ImageAspectFlags = Flags;
 pub type ImageCreateFlags = Flags;
@@ -378,7 +385,7 @@ pub enum ComponentSwizzle {
     A = 6,
 }
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
 #[repr(C)]
 pub enum CullModeBits {
     None = 0,
@@ -402,6 +409,23 @@ pub enum DependencyFlags {
     // DeviceGroup = 0x0000_0004,
 }
 
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[repr(C)]
+pub enum DebugUtilsMessageSeverityFlagBitsEXT {
+    Verbose = 0x0000_0001,
+    Info = 0x0000_0010,
+    Warning = 0x0000_0100,
+    Error = 0x0000_1000,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[repr(C)]
+pub enum DebugUtilsMessageTypeFlagBitsEXT {
+    General = 0x0000_0001,
+    Validation = 0x0000_0002,
+    Performance = 0x0000_0004,
+}
+
 #[derive(Clone, Copy, Debug)]
 #[repr(C)]
 pub enum DescriptorPoolCreateFlagBits {
@@ -416,7 +440,7 @@ pub enum DescriptorSetLayoutCreateFlagBits {
     UpdateAfterBindPoolEXT = 0x0000_0002,
 }
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
 #[repr(C)]
 pub enum DescriptorType {
     Sampler = 0,
@@ -476,14 +500,23 @@ pub enum FenceCreateFlagBits {
     Signaled = 0x0000_0001,
 }
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
 #[repr(C)]
 pub enum Filter {
-    Nerest = 0,
+    Nearest = 0,
     Linear = 1,
     CubicEXT = 1_000_015_000,
 }
 
+#[derive(Clone, Copy, Debug)]
+#[repr(C)]
+pub enum FormatFeatureFlagBits {
+    SampledImage = 0x0000_0001,
+    BlitSource = 0x0000_0400,
+    BlitDestination = 0x0000_0800,
+    SampledImageFilterLinear = 0x0000_1000,
+}
+
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
 #[repr(C)]
 pub enum Format {
@@ -1046,7 +1079,7 @@ pub enum PipelineStageFlagBits {
     // FragmentShadingRateAttachmentKHR = SHADING_RATE_IMAGE_NV,
 }
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
 #[repr(C)]
 pub enum PolygonMode {
     Fill = 0,
@@ -1066,7 +1099,7 @@ pub enum PresentModeKHR {
     SharedContinuousRefresh = 1000111001,
 }
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
 #[repr(C)]
 pub enum PrimitiveTopology {
     PointList = 0,
@@ -1165,7 +1198,7 @@ pub enum Result {
     ErrorInvalidOpaqueCaptureAddressKHR = -1_000_257_000,
 }
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
 #[repr(C)]
 pub enum SamplerAddressMode {
     Repeat = 0,
@@ -1242,6 +1275,7 @@ pub enum StructureType {
     ImageCreateInfo = 14,
     ImageViewCreateInfo = 15,
     ShaderModuleCreateInfo = 16,
+    PipelineCacheCreateInfo = 17,
     PipelineShaderStageCreateInfo = 18,
     PipelineVertexInputStateCreateInfo = 19,
     PipelineInputAssemblyStateCreateInfo = 20,
@@ -1287,6 +1321,8 @@ pub enum StructureType {
     SwapchainCreateInfoKHR = 1_000_001_000,
     PresentInfoKHR = 1_000_001_001,
     XCBSurfaceCreateInfoKHR = 1_000_005_000,
+    DebugUtilsMessengerCallbackDataEXT = 1_000_128_003,
+    DebugUtilsMessengerCreateInfoEXT = 1_000_128_004,
     WIN32SurfaceCreateInfoKHR = 1_000_009_000,
     PipelineInfoKHR = 1_000_269_001,
 }
@@ -1360,6 +1396,7 @@ define_handle!(
     BufferView,
     CommandBuffer,
     CommandPool,
+    DebugUtilsMessengerEXT,
     DescriptorPool,
     DescriptorSet,
     DescriptorSetLayout,
@@ -1641,6 +1678,36 @@ pub struct DescriptorSetLayoutBinding {
     pub immutable_samplers: *const *mut Sampler,
 }
 
+#[derive(Clone, Copy, Debug)]
+#[repr(C)]
+pub struct DebugUtilsMessengerCallbackDataEXT {
+    pub stype: StructureType,
+    pub next: *const c_void,
+    pub flags: DebugUtilsMessengerCallbackDataFlagsEXT,
+    pub message_id_name: *const c_char,
+    pub message_id_number: i32,
+    pub message: *const c_char,
+    // Labels and objects aren't used, so their structures aren't defined
+    pub queue_label_count: u32,
+    pub queue_labels: *const c_void,
+    pub cmd_buf_label_count: u32,
+    pub cmd_buf_labels: *const c_void,
+    pub object_count: u32,
+    pub objects: *const c_void,
+}
+
+#[derive(Clone, Copy)]
+#[repr(C)]
+pub struct DebugUtilsMessengerCreateInfoEXT {
+    pub stype: StructureType,
+    pub next: *const c_void,
+    pub flags: DebugUtilsMessengerCreateFlagsEXT,
+    pub message_severity: DebugUtilsMessageSeverityFlagsEXT,
+    pub message_type: DebugUtilsMessageTypeFlagsEXT,
+    pub user_callback: DebugUtilsMessengerCallbackEXT,
+    pub user_data: *mut c_void,
+}
+
 #[derive(Clone, Copy, Debug)]
 #[repr(C)]
 pub struct DescriptorSetLayoutCreateInfo {
@@ -1711,7 +1778,7 @@ pub struct ExtensionProperties {
     pub spec_version: u32,
 }
 
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
 #[repr(C)]
 pub struct Extent2D {
     pub width: u32,
@@ -1770,11 +1837,9 @@ pub struct FramebufferCreateInfo {
 #[derive(Clone, Copy, Debug)]
 #[repr(C)]
 pub struct FormatProperties {
-    pub max_extent: Extent3D,
-    pub max_mip_levels: u32,
-    pub max_array_layers: u32,
-    pub sample_counts: SampleCountFlags,
-    pub max_resource_size: DeviceSize,
+    pub linear_tiling_features: FormatFeatureFlags,
+    pub optimal_tiling_features: FormatFeatureFlags,
+    pub buffer_features: FormatFeatureFlags,
 }
 
 #[derive(Clone, Copy, Debug)]
@@ -1801,6 +1866,15 @@ pub struct GraphicsPipelineCreateInfo {
     pub base_pipeline_index: i32,
 }
 
+#[derive(Clone, Copy, Debug)]
+#[repr(C)]
+pub struct ImageBlit {
+    pub src_subresource: ImageSubresourceLayers,
+    pub src_offsets: [Offset3D; 2],
+    pub dst_subresource: ImageSubresourceLayers,
+    pub dst_offsets: [Offset3D; 2],
+}
+
 #[derive(Clone, Copy, Debug)]
 #[repr(C)]
 pub struct ImageCopy {
@@ -1909,6 +1983,15 @@ pub struct InstanceCreateInfo {
     pub enabled_extension_names: *const *const c_char,
 }
 
+#[derive(Clone, Copy, Debug)]
+#[repr(C)]
+pub struct LayerProperties {
+    pub layer_name: [c_char; MAX_EXTENSION_NAME_SIZE],
+    pub spec_version: u32,
+    pub implementation_version: u32,
+    pub description: [c_char; MAX_DESCRIPTION_SIZE],
+}
+
 #[derive(Clone, Copy, Debug)]
 #[repr(C)]
 pub struct MappedMemoryRange {
@@ -2739,6 +2822,17 @@ pub type CmdBindVertexBuffers = extern "system" fn(
     offsets: *const DeviceSize,
 );
 
+pub type CmdBlitImage = extern "system" fn(
+    command_buffer: *mut CommandBuffer,
+    src_image: *mut Image,
+    src_image_layout: ImageLayout,
+    dst_image: *mut Image,
+    dst_image_layout: ImageLayout,
+    region_count: u32,
+    regions: *const ImageBlit,
+    filter: Filter,
+);
+
 pub type CmdCopyBuffer = extern "system" fn(
     command_buffer: *mut CommandBuffer,
     src_buffer: *mut Buffer,
@@ -2885,6 +2979,13 @@ pub type CreateComputePipelines = extern "system" fn(
     pipelines: *mut *mut Pipeline,
 ) -> Result;
 
+pub type CreateDebugUtilsMessengerEXT = extern "system" fn(
+    instance: *mut Instance,
+    create_info: *const DebugUtilsMessengerCreateInfoEXT,
+    allocator: *const AllocationCallbacks,
+    messenger: *mut *mut DebugUtilsMessengerEXT,
+) -> Result;
+
 pub type CreateDescriptorPool = extern "system" fn(
     device: *mut Device,
     create_info: *const DescriptorPoolCreateInfo,
@@ -2949,6 +3050,13 @@ pub type CreateInstance = extern "system" fn(
     instance: *mut *mut Instance,
 ) -> Result;
 
+pub type CreatePipelineCache = extern "system" fn(
+    device: *mut Device,
+    create_info: *const PipelineCacheCreateInfo,
+    allocator: *const AllocationCallbacks,
+    pipeline_cache: *mut *mut PipelineCache,
+) -> Result;
+
 pub type CreatePipelineLayout = extern "system" fn(
     device: *mut Device,
     create_info: *const PipelineLayoutCreateInfo,
@@ -3017,6 +3125,19 @@ pub type DestroyCommandPool = extern "system" fn(
     allocator: *const AllocationCallbacks,
 );
 
+pub type DebugUtilsMessengerCallbackEXT = extern "system" fn(
+    message_severity: DebugUtilsMessageSeverityFlagsEXT,
+    message_types: DebugUtilsMessageTypeFlagsEXT,
+    callback_data: *const DebugUtilsMessengerCallbackDataEXT,
+    user_data: *mut c_void,
+) -> Bool32;
+
+pub type DestroyDebugUtilsMessengerEXT = extern "system" fn(
+    instance: *mut Instance,
+    messenger: *mut DebugUtilsMessengerEXT,
+    allocator: *const AllocationCallbacks,
+);
+
 pub type DestroyDescriptorPool = extern "system" fn(
     device: *mut Device,
     descriptor_pool: *mut DescriptorPool,
@@ -3065,6 +3186,12 @@ pub type DestroyPipeline = extern "system" fn(
     allocator: *const AllocationCallbacks,
 );
 
+pub type DestroyPipelineCache = extern "system" fn(
+    device: *mut Device,
+    pipeline_cache: *mut PipelineCache,
+    allocator: *const AllocationCallbacks,
+);
+
 pub type DestroyPipelineLayout = extern "system" fn(
     device: *mut Device,
     pipeline_layout: *mut PipelineLayout,
@@ -3117,6 +3244,9 @@ pub type EnumerateInstanceExtensionProperties = extern "system" fn(
     properties: *mut ExtensionProperties,
 ) -> Result;
 
+pub type EnumerateInstanceLayerProperties =
+    extern "system" fn(property_count: *mut u32, properties: *mut LayerProperties) -> Result;
+
 pub type EnumeratePhysicalDevices = extern "system" fn(
     instance: *mut Instance,
     count: *mut u32,
@@ -3179,9 +3309,18 @@ pub type GetImageMemoryRequirements = extern "system" fn(
     memory_requirements: *mut MemoryRequirements,
 );
 
+pub type GetFenceStatus = extern "system" fn(device: *mut Device, fence: *mut Fence) -> Result;
+
 pub type GetInstanceProcAddr =
     extern "system" fn(instance: *mut Instance, name: *const c_char) -> Option<Void>;
 
+pub type GetPipelineCacheData = extern "system" fn(
+    device: *mut Device,
+    pipeline_cache: *mut PipelineCache,
+    data_size: *mut usize,
+    data: *mut c_void,
+) -> Result;
+
 pub type GetPhysicalDeviceFeatures =
     extern "system" fn(physical_device: *mut PhysicalDevice, features: *mut PhysicalDeviceFeatures);
 
@@ -3230,6 +3369,13 @@ pub type GetPhysicalDeviceSurfaceFormatsKHR = extern "system" fn(
     surface_formats: *mut SurfaceFormatKHR,
 ) -> Result;
 
+pub type GetPhysicalDeviceSurfacePresentModesKHR = extern "system" fn(
+    physical_device: *mut PhysicalDevice,
+    surface: *mut SurfaceKHR,
+    present_mode_count: *mut u32,
+    present_modes: *mut PresentModeKHR,
+) -> Result;
+
 pub type GetPhysicalDeviceSurfaceSupportKHR = extern "system" fn(
     physical_device: *mut PhysicalDevice,
     queue_family_index: u32,
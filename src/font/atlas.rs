@@ -0,0 +1,161 @@
+/// A horizontal strip of the atlas. Glyphs are placed left to right along a shelf, and a shelf is
+/// only ever reclaimed as a whole.
+#[derive(Clone, Copy, Debug)]
+struct Shelf {
+    y: u32,
+    height: u32,
+    cursor: u32,
+    last_used: u64,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) struct Allocation {
+    pub(crate) shelf: usize,
+    pub(crate) x: u32,
+    pub(crate) y: u32,
+}
+
+/// Shelf packer for the glyph atlas. Rectangles are placed on the shelf with room left that wastes
+/// the least height, otherwise a new shelf is opened below the last one.
+#[derive(Debug)]
+pub(crate) struct ShelfPacker {
+    width: u32,
+    height: u32,
+    shelves: Vec<Shelf>,
+}
+
+impl ShelfPacker {
+    pub(crate) fn new(width: u32, height: u32) -> Self {
+        return Self {
+            width,
+            height,
+            shelves: Vec::new(),
+        };
+    }
+
+    pub(crate) fn allocate(&mut self, width: u32, height: u32, now: u64) -> Option<Allocation> {
+        if self.width < width {
+            return None;
+        }
+
+        // Prefer the shelf wasting the least vertical space
+        let best = self
+            .shelves
+            .iter()
+            .enumerate()
+            .filter(|(_, shelf)| height <= shelf.height && width <= self.width - shelf.cursor)
+            .min_by_key(|(_, shelf)| shelf.height - height)
+            .map(|(i, _)| i);
+
+        let index = match best {
+            Some(index) => index,
+            None => {
+                let y = self
+                    .shelves
+                    .last()
+                    .map_or(0, |shelf| shelf.y + shelf.height);
+                if self.height - y < height {
+                    return None;
+                }
+
+                self.shelves.push(Shelf {
+                    y,
+                    height,
+                    cursor: 0,
+                    last_used: now,
+                });
+                self.shelves.len() - 1
+            }
+        };
+
+        let shelf = &mut self.shelves[index];
+        let allocation = Allocation {
+            shelf: index,
+            x: shelf.cursor,
+            y: shelf.y,
+        };
+        shelf.cursor += width;
+        shelf.last_used = now;
+
+        return Some(allocation);
+    }
+
+    /// Resets the least-recently-used shelf that can hold a rectangle `height` pixels tall.
+    /// Shelves used at `now` are never evicted. Returns the index and vertical extent (y and
+    /// height) of the evicted shelf.
+    pub(crate) fn evict(&mut self, height: u32, now: u64) -> Option<(usize, u32, u32)> {
+        let (index, shelf) = self
+            .shelves
+            .iter_mut()
+            .enumerate()
+            .filter(|(_, shelf)| height <= shelf.height && shelf.last_used < now)
+            .min_by_key(|(_, shelf)| shelf.last_used)?;
+
+        shelf.cursor = 0;
+        return Some((index, shelf.y, shelf.height));
+    }
+
+    pub(crate) fn grow(&mut self, height: u32) {
+        debug_assert!(self.height <= height);
+        self.height = height;
+    }
+
+    pub(crate) fn touch(&mut self, shelf: usize, now: u64) {
+        self.shelves[shelf].last_used = now;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn packs_along_a_shelf() {
+        let mut packer = ShelfPacker::new(64, 64);
+        let a = packer.allocate(10, 16, 0).unwrap();
+        let b = packer.allocate(20, 12, 0).unwrap();
+
+        assert_eq!(
+            a,
+            Allocation {
+                shelf: 0,
+                x: 0,
+                y: 0
+            }
+        );
+        assert_eq!(
+            b,
+            Allocation {
+                shelf: 0,
+                x: 10,
+                y: 0
+            }
+        );
+    }
+
+    #[test]
+    fn opens_new_shelves_until_full() {
+        let mut packer = ShelfPacker::new(16, 32);
+        assert_eq!(packer.allocate(16, 16, 0).unwrap().y, 0);
+        assert_eq!(packer.allocate(16, 16, 0).unwrap().y, 16);
+        assert!(packer.allocate(16, 16, 0).is_none());
+
+        packer.grow(48);
+        assert_eq!(packer.allocate(16, 16, 0).unwrap().y, 32);
+    }
+
+    #[test]
+    fn evicts_least_recently_used_shelf() {
+        let mut packer = ShelfPacker::new(16, 32);
+        packer.allocate(16, 16, 0).unwrap();
+        packer.allocate(16, 16, 1).unwrap();
+        assert!(packer.allocate(16, 16, 2).is_none());
+
+        assert_eq!(packer.evict(16, 2), Some((0, 0, 16)));
+        assert_eq!(packer.allocate(16, 16, 2).unwrap().y, 0);
+
+        // Both shelves are now in use during frame 2
+        packer.touch(1, 2);
+        assert_eq!(packer.evict(16, 2), None);
+    }
+}
@@ -0,0 +1,324 @@
+use super::{Glyph, GlyphCache};
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum TextAlignment {
+    Left,
+    Centre,
+    Right,
+}
+
+/// Parameters for laying out a block of text. Widths are in pixels.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct LayoutOptions {
+    pub(crate) alignment: TextAlignment,
+    /// Lines are word-wrapped to this width. Words wider than a whole line are broken up.
+    pub(crate) max_width: Option<i32>,
+    /// Multiplier applied to the line height recommended by the face.
+    pub(crate) line_spacing: f32,
+}
+
+/// Everything the layout needs to know about a font.
+pub(crate) trait FontMetrics {
+    fn ascender(&self) -> i32;
+    fn descender(&self) -> i32;
+    fn glyph(&mut self, c: char) -> Option<Glyph>;
+    fn kerning(&mut self, left: char, right: char) -> i32;
+    fn line_height(&self) -> i32;
+}
+
+/// A glyph placed within a laid out block of text. `x` and `y` give the top-left corner of the
+/// glyph's bitmap, in pixels from the top-left corner of the block, with the y-axis pointing down.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct PositionedGlyph {
+    pub(crate) glyph: Glyph,
+    pub(crate) x: i32,
+    pub(crate) y: i32,
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct TextLayout {
+    pub(crate) glyphs: Vec<PositionedGlyph>,
+    /// Width of the widest line, in pixels
+    pub(crate) width: i32,
+    /// Distance from the ascender of the first line to the descender of the last, in pixels
+    pub(crate) height: i32,
+}
+
+struct Line {
+    glyphs: Vec<(Glyph, i32)>,
+    width: i32,
+}
+
+impl FontMetrics for GlyphCache {
+    fn ascender(&self) -> i32 {
+        return self.ascender;
+    }
+
+    fn descender(&self) -> i32 {
+        return self.descender;
+    }
+
+    fn glyph(&mut self, c: char) -> Option<Glyph> {
+        return GlyphCache::glyph(self, c);
+    }
+
+    fn kerning(&mut self, left: char, right: char) -> i32 {
+        return GlyphCache::kerning(self, left, right);
+    }
+
+    fn line_height(&self) -> i32 {
+        return self.line_height;
+    }
+}
+
+/// Breaks `text` into lines at newlines and, if a maximum width is given, between words. Kerning
+/// is applied between every pair of characters on a line.
+pub(crate) fn layout<F: FontMetrics>(
+    font: &mut F,
+    text: &str,
+    options: LayoutOptions,
+) -> TextLayout {
+    let mut lines = Vec::new();
+    for paragraph in text.split('\n') {
+        wrap_paragraph(font, paragraph, options.max_width, &mut lines);
+    }
+
+    let line_advance = (font.line_height() as f32 * options.line_spacing).round() as i32;
+    let width = lines.iter().map(|line| line.width).max().unwrap_or(0);
+    let height = (lines.len() as i32 - 1) * line_advance + font.ascender() - font.descender();
+
+    let mut glyphs = Vec::new();
+    for (i, line) in lines.iter().enumerate() {
+        let baseline = font.ascender() + i as i32 * line_advance;
+        let left = match options.alignment {
+            TextAlignment::Left => 0,
+            TextAlignment::Centre => (width - line.width) / 2,
+            TextAlignment::Right => width - line.width,
+        };
+
+        glyphs.extend(line.glyphs.iter().map(|&(glyph, pen)| PositionedGlyph {
+            glyph,
+            x: left + pen + glyph.bearing_x,
+            y: baseline - glyph.bearing_y,
+        }));
+    }
+
+    let layout = TextLayout {
+        glyphs,
+        width,
+        height,
+    };
+    return layout;
+}
+
+/// Appends the lines of a single paragraph, which contains no newlines, to `lines`.
+fn wrap_paragraph<F: FontMetrics>(
+    font: &mut F,
+    paragraph: &str,
+    max_width: Option<i32>,
+    lines: &mut Vec<Line>,
+) {
+    let max_width = max_width.unwrap_or(i32::MAX);
+    let mut line = Line {
+        glyphs: Vec::new(),
+        width: 0,
+    };
+    let mut previous: Option<char> = None;
+
+    for word in split_words(paragraph) {
+        let is_space = word.starts_with(char::is_whitespace);
+        let kerning = match (previous, word.chars().next()) {
+            (Some(left), Some(right)) => font.kerning(left, right),
+            _ => 0,
+        };
+
+        // Move the whole word to the next line if it doesn't fit, unless the line is empty
+        if !is_space && !line.glyphs.is_empty() {
+            let word_width = measure(font, word);
+            if max_width < line.width + kerning + word_width {
+                trim_trailing_space(&mut line);
+                lines.push(std::mem::replace(
+                    &mut line,
+                    Line {
+                        glyphs: Vec::new(),
+                        width: 0,
+                    },
+                ));
+                previous = None;
+            }
+        }
+
+        for c in word.chars() {
+            let glyph = match font.glyph(c) {
+                Some(glyph) => glyph,
+                None => continue,
+            };
+            let mut pen = line.width;
+            if let Some(left) = previous {
+                pen += font.kerning(left, c);
+            }
+
+            // Words wider than a whole line are broken up between characters
+            if !line.glyphs.is_empty() && max_width < pen + glyph.advance && !is_space {
+                lines.push(std::mem::replace(
+                    &mut line,
+                    Line {
+                        glyphs: Vec::new(),
+                        width: 0,
+                    },
+                ));
+                pen = 0;
+            }
+
+            line.glyphs.push((glyph, pen));
+            line.width = pen + glyph.advance;
+            previous = Some(c);
+        }
+    }
+
+    trim_trailing_space(&mut line);
+    lines.push(line);
+}
+
+/// Width of `word` in pixels, including kerning between its characters.
+fn measure<F: FontMetrics>(font: &mut F, word: &str) -> i32 {
+    let mut width = 0;
+    let mut previous = None;
+    for c in word.chars() {
+        if let Some(glyph) = font.glyph(c) {
+            if let Some(left) = previous {
+                width += font.kerning(left, c);
+            }
+            width += glyph.advance;
+            previous = Some(c);
+        }
+    }
+
+    return width;
+}
+
+/// Splits `text` into alternating runs of whitespace and non-whitespace characters.
+fn split_words(text: &str) -> impl Iterator<Item = &str> {
+    let mut rest = text;
+    return std::iter::from_fn(move || {
+        let first = rest.chars().next()?;
+        let end = rest
+            .char_indices()
+            .find(|&(_, c)| c.is_whitespace() != first.is_whitespace())
+            .map_or(rest.len(), |(i, _)| i);
+
+        let (word, remainder) = rest.split_at(end);
+        rest = remainder;
+        Some(word)
+    });
+}
+
+/// Trailing spaces don't count towards the width of a line, so alignment isn't thrown off.
+fn trim_trailing_space(line: &mut Line) {
+    while let Some(&(glyph, pen)) = line.glyphs.last() {
+        if glyph.width != 0 && glyph.height != 0 {
+            line.width = pen + glyph.advance;
+            return;
+        }
+
+        line.glyphs.pop();
+    }
+
+    line.width = 0;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Every character is 10 pixels wide, with "AV" kerned together by 3 pixels.
+    struct Monospace;
+
+    impl FontMetrics for Monospace {
+        fn ascender(&self) -> i32 {
+            return 16;
+        }
+
+        fn descender(&self) -> i32 {
+            return -4;
+        }
+
+        fn glyph(&mut self, c: char) -> Option<Glyph> {
+            let size = if c == ' ' { 0 } else { 10 };
+            let glyph = Glyph {
+                x: 0,
+                y: 0,
+                width: size,
+                height: size,
+                bearing_x: 0,
+                bearing_y: 10,
+                advance: 10,
+                shelf: None,
+            };
+            return Some(glyph);
+        }
+
+        fn kerning(&mut self, left: char, right: char) -> i32 {
+            return if (left, right) == ('A', 'V') { -3 } else { 0 };
+        }
+
+        fn line_height(&self) -> i32 {
+            return 24;
+        }
+    }
+
+    fn options(max_width: Option<i32>) -> LayoutOptions {
+        return LayoutOptions {
+            alignment: TextAlignment::Left,
+            max_width,
+            line_spacing: 1.0,
+        };
+    }
+
+    #[test]
+    fn applies_kerning() {
+        let layout = layout(&mut Monospace, "AVA", options(None));
+        let xs: Vec<i32> = layout.glyphs.iter().map(|g| g.x).collect();
+
+        assert_eq!(xs, [0, 7, 17]);
+        assert_eq!(layout.width, 27);
+    }
+
+    #[test]
+    fn breaks_lines_at_newlines() {
+        let layout = layout(&mut Monospace, "ab\ncde", options(None));
+
+        assert_eq!(layout.width, 30);
+        assert_eq!(layout.height, 24 + 16 + 4);
+        assert_eq!(layout.glyphs[2].y, 16 + 24 - 10);
+    }
+
+    #[test]
+    fn wraps_words_to_maximum_width() {
+        let layout = layout(&mut Monospace, "aaa bbb ccc", options(Some(75)));
+
+        assert_eq!(layout.height, 24 + 16 + 4);
+        assert_eq!(layout.width, 70);
+        // "ccc" starts the second line, without a leading space
+        assert_eq!(layout.glyphs[7].x, 0);
+        assert_eq!(layout.glyphs[7].y, 16 + 24 - 10);
+    }
+
+    #[test]
+    fn breaks_words_wider_than_a_line() {
+        let layout = layout(&mut Monospace, "abcdef", options(Some(40)));
+
+        assert_eq!(layout.height, 24 + 16 + 4);
+        assert_eq!(layout.width, 40);
+    }
+
+    #[test]
+    fn aligns_lines_within_the_block() {
+        let mut options = options(None);
+        options.alignment = TextAlignment::Right;
+        let layout = layout(&mut Monospace, "a\nbcd", options);
+
+        assert_eq!(layout.glyphs[0].x, 20);
+        assert_eq!(layout.glyphs[1].x, 0);
+    }
+}
@@ -0,0 +1,347 @@
+mod atlas;
+mod layout;
+mod sdf;
+
+use std::{borrow::Cow, collections::HashMap};
+
+use atlas::ShelfPacker;
+use freetype::freetype::{
+    FT_Done_Face, FT_Done_FreeType, FT_Face, FT_Get_Char_Index, FT_Get_Kerning, FT_Init_FreeType,
+    FT_Kerning_Mode, FT_Library, FT_Load_Char, FT_New_Memory_Face, FT_Set_Char_Size, FT_Vector,
+    FT_FACE_FLAG_KERNING, FT_LOAD_RENDER,
+};
+pub use layout::TextAlignment;
+pub(crate) use layout::{layout, LayoutOptions, TextLayout};
+
+/// Atlases have a fixed width and only ever grow downwards, so growing one never moves the glyphs
+/// already in it.
+const ATLAS_WIDTH: u32 = 512;
+const MAX_ATLAS_HEIGHT: u32 = 4096;
+/// Empty pixels kept to the right and below every glyph, so that linear filtering doesn't pull in
+/// coverage from a neighbouring glyph.
+const GLYPH_PADDING: u32 = 1;
+/// Distance in pixels a signed distance field extends past the edges of a glyph. Outlines and
+/// shadows can't reach further than this.
+pub(crate) const SDF_SPREAD: u32 = 4;
+
+/// What the atlas stores for every glyph.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum GlyphFormat {
+    /// Anti-aliased coverage, sharp only at the size the font was loaded at
+    Coverage,
+    /// Signed distance to the glyph's outline, which stays sharp when scaled
+    DistanceField,
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct CoverageMap {
+    pub(crate) data: Box<[u8]>,
+    pub(crate) width: u32,
+    pub(crate) height: u32,
+}
+
+/// A rectangle of the atlas, in pixels.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) struct Region {
+    pub(crate) x: u32,
+    pub(crate) y: u32,
+    pub(crate) width: u32,
+    pub(crate) height: u32,
+}
+
+/// Changes made to an atlas since it was last uploaded.
+#[derive(Debug)]
+pub(crate) enum AtlasUpdate {
+    None,
+    Regions(Vec<Region>),
+    /// The atlas has grown, the whole coverage map must be uploaded again.
+    Resized,
+}
+
+/// Placement of a glyph relative to the pen position on the baseline, along with its position in
+/// the atlas. Everything is in pixels, so entries stay valid when the atlas grows.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Glyph {
+    pub(crate) x: u32,
+    pub(crate) y: u32,
+    pub(crate) width: u32,
+    pub(crate) height: u32,
+    pub(crate) bearing_x: i32,
+    pub(crate) bearing_y: i32,
+    pub(crate) advance: i32,
+    /// Glyphs without any coverage, like the space character, don't occupy a shelf
+    shelf: Option<usize>,
+}
+
+/// Rasterizes code points on demand and packs them into a growable coverage map. When the atlas
+/// can no longer grow, glyphs are evicted least-recently-used first, one shelf at a time.
+pub(crate) struct GlyphCache {
+    library: FT_Library,
+    face: FT_Face,
+    /// The font file, which FreeType reads from for as long as the face exists
+    _data: Cow<'static, [u8]>,
+    atlas: CoverageMap,
+    packer: ShelfPacker,
+    glyphs: HashMap<char, Glyph>,
+    pub(crate) format: GlyphFormat,
+    dirty: Vec<Region>,
+    resized: bool,
+    clock: u64,
+    pub(crate) ascender: i32,
+    pub(crate) descender: i32,
+    /// Recommended distance between the baselines of consecutive lines
+    pub(crate) line_height: i32,
+    has_kerning: bool,
+}
+
+impl GlyphCache {
+    pub(crate) fn new(font: Cow<'static, [u8]>, height: u32, format: GlyphFormat) -> Option<Self> {
+        let (library, face) = unsafe {
+            let mut library = std::ptr::null_mut();
+            if FT_Init_FreeType(&mut library) != 0 {
+                return None;
+            }
+
+            let mut face = std::ptr::null_mut();
+            let size = font.len() as i64;
+            if FT_New_Memory_Face(library, font.as_ptr(), size, 0, &mut face) != 0 {
+                FT_Done_FreeType(library);
+                return None;
+            }
+            FT_Set_Char_Size(face, 0, height as i64 * 64, 192, 192);
+
+            (library, face)
+        };
+
+        let face_metrics = unsafe { (*(*face).size).metrics };
+        let line_height = (face_metrics.height / 64) as i32;
+        let initial_height = u32::min(4 * line_height as u32, MAX_ATLAS_HEIGHT);
+        let has_kerning = unsafe { (*face).face_flags } & FT_FACE_FLAG_KERNING as i64 != 0;
+
+        let cache = Self {
+            library,
+            face,
+            _data: font,
+            atlas: CoverageMap::new(ATLAS_WIDTH, initial_height),
+            packer: ShelfPacker::new(ATLAS_WIDTH, initial_height),
+            glyphs: HashMap::new(),
+            format,
+            dirty: Vec::new(),
+            resized: true,
+            clock: 0,
+            ascender: (face_metrics.ascender / 64) as i32,
+            descender: (face_metrics.descender / 64) as i32,
+            line_height,
+            has_kerning,
+        };
+        return Some(cache);
+    }
+
+    pub(crate) fn atlas(&self) -> &CoverageMap {
+        return &self.atlas;
+    }
+
+    /// Looks up `c`, rasterizing it into the atlas if it isn't already there. Returns `None` if
+    /// the glyph couldn't be loaded or there is no room left for it.
+    pub(crate) fn glyph(&mut self, c: char) -> Option<Glyph> {
+        if let Some(&glyph) = self.glyphs.get(&c) {
+            if let Some(shelf) = glyph.shelf {
+                self.packer.touch(shelf, self.clock);
+            }
+            return Some(glyph);
+        }
+
+        let glyph = self.rasterize(c)?;
+        self.glyphs.insert(c, glyph);
+        return Some(glyph);
+    }
+
+    /// Horizontal adjustment between `left` and `right` in pixels, usually negative.
+    pub(crate) fn kerning(&mut self, left: char, right: char) -> i32 {
+        if !self.has_kerning {
+            return 0;
+        }
+
+        let mut kerning = FT_Vector { x: 0, y: 0 };
+        unsafe {
+            let left = FT_Get_Char_Index(self.face, left as u64);
+            let right = FT_Get_Char_Index(self.face, right as u64);
+            let mode = FT_Kerning_Mode::FT_KERNING_DEFAULT as u32;
+            if FT_Get_Kerning(self.face, left, right, mode, &mut kerning) != 0 {
+                return 0;
+            }
+        }
+
+        return (kerning.x / 64) as i32;
+    }
+
+    /// Advances the clock used for least-recently-used eviction. Glyphs looked up since the last
+    /// tick are never evicted.
+    pub(crate) fn tick(&mut self) {
+        self.clock += 1;
+    }
+
+    pub(crate) fn take_update(&mut self) -> AtlasUpdate {
+        let update = if self.resized {
+            AtlasUpdate::Resized
+        } else if !self.dirty.is_empty() {
+            AtlasUpdate::Regions(std::mem::take(&mut self.dirty))
+        } else {
+            AtlasUpdate::None
+        };
+
+        self.resized = false;
+        self.dirty.clear();
+        return update;
+    }
+
+    fn allocate(&mut self, width: u32, height: u32) -> Option<atlas::Allocation> {
+        if self.atlas.width < width {
+            return None;
+        }
+
+        loop {
+            if let Some(allocation) = self.packer.allocate(width, height, self.clock) {
+                return Some(allocation);
+            }
+
+            if self.atlas.height < MAX_ATLAS_HEIGHT {
+                let height = u32::min(2 * self.atlas.height, MAX_ATLAS_HEIGHT);
+                self.atlas.grow(height);
+                self.packer.grow(height);
+                self.resized = true;
+                continue;
+            }
+
+            let (shelf, y, shelf_height) = self.packer.evict(height, self.clock)?;
+            self.glyphs.retain(|_, glyph| glyph.shelf != Some(shelf));
+            self.atlas.clear_rows(y, shelf_height);
+            self.dirty.push(Region {
+                x: 0,
+                y,
+                width: self.atlas.width,
+                height: shelf_height,
+            });
+        }
+    }
+
+    fn rasterize(&mut self, c: char) -> Option<Glyph> {
+        let slot = unsafe {
+            if FT_Load_Char(self.face, c as u64, FT_LOAD_RENDER as i32) != 0 {
+                return None;
+            }
+            *(*self.face).glyph
+        };
+
+        let bitmap = slot.bitmap;
+        let mut glyph = Glyph {
+            x: 0,
+            y: 0,
+            width: bitmap.width,
+            height: bitmap.rows,
+            bearing_x: slot.bitmap_left,
+            bearing_y: slot.bitmap_top,
+            advance: (slot.advance.x / 64) as i32,
+            shelf: None,
+        };
+        if glyph.width == 0 || glyph.height == 0 {
+            return Some(glyph);
+        }
+
+        let pitch = bitmap.pitch.unsigned_abs() as usize;
+        let coverage =
+            unsafe { std::slice::from_raw_parts(bitmap.buffer, pitch * glyph.height as usize) };
+        let (data, pitch) = match self.format {
+            GlyphFormat::Coverage => (None, pitch),
+            GlyphFormat::DistanceField => {
+                let field =
+                    sdf::distance_field(coverage, pitch, glyph.width, glyph.height, SDF_SPREAD);
+                glyph.width += 2 * SDF_SPREAD;
+                glyph.height += 2 * SDF_SPREAD;
+                glyph.bearing_x -= SDF_SPREAD as i32;
+                glyph.bearing_y += SDF_SPREAD as i32;
+                (Some(field), glyph.width as usize)
+            }
+        };
+        let data = data.as_deref().unwrap_or(coverage);
+
+        let allocation =
+            self.allocate(glyph.width + GLYPH_PADDING, glyph.height + GLYPH_PADDING)?;
+        glyph.x = allocation.x;
+        glyph.y = allocation.y;
+        glyph.shelf = Some(allocation.shelf);
+
+        for row in 0..glyph.height as usize {
+            let source = &data[row * pitch..row * pitch + glyph.width as usize];
+            let start = (glyph.y as usize + row) * self.atlas.width as usize + glyph.x as usize;
+            self.atlas.data[start..start + glyph.width as usize].copy_from_slice(source);
+        }
+
+        self.dirty.push(Region {
+            x: glyph.x,
+            y: glyph.y,
+            width: glyph.width,
+            height: glyph.height,
+        });
+        return Some(glyph);
+    }
+}
+
+impl Drop for GlyphCache {
+    fn drop(&mut self) {
+        unsafe {
+            FT_Done_Face(self.face);
+            FT_Done_FreeType(self.library);
+        }
+    }
+}
+
+impl CoverageMap {
+    fn clear_rows(&mut self, y: u32, height: u32) {
+        let start = (y * self.width) as usize;
+        let end = ((y + height) * self.width) as usize;
+        self.data[start..end].iter_mut().for_each(|c| *c = 0);
+    }
+
+    fn grow(&mut self, height: u32) {
+        let mut data = vec![0; (self.width * height) as usize].into_boxed_slice();
+        data[..self.data.len()].copy_from_slice(&self.data);
+
+        self.data = data;
+        self.height = height;
+    }
+
+    fn new(width: u32, height: u32) -> Self {
+        let data = vec![0; (width * height) as usize].into_boxed_slice();
+
+        let map = Self {
+            data,
+            width,
+            height,
+        };
+        return map;
+    }
+
+    /// Copies `region` out of the coverage map into a tightly packed buffer.
+    pub(crate) fn region_data(&self, region: Region) -> Box<[u8]> {
+        let mut data = Vec::with_capacity((region.width * region.height) as usize);
+        for row in region.y..region.y + region.height {
+            let start = (row * self.width + region.x) as usize;
+            data.extend_from_slice(&self.data[start..start + region.width as usize]);
+        }
+
+        return data.into_boxed_slice();
+    }
+
+    #[cfg(debug_assertions)]
+    pub fn print_pgm(&self) {
+        println!("P2\n{} {}\n255", self.width, self.height);
+        (0..self.height).for_each(|i| {
+            (0..self.width).for_each(|j| {
+                let offset = i * self.width + j;
+                print!("{} ", self.data[offset as usize]);
+            });
+            println!();
+        });
+    }
+}
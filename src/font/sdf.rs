@@ -0,0 +1,77 @@
+/// Converts a coverage bitmap into a signed distance field, `spread` pixels larger on every side.
+/// Each texel stores the distance to the nearest edge, with 128 on the edge, larger values inside
+/// the glyph and smaller values outside. Distances are clamped to `spread`.
+///
+/// `pitch` is the number of bytes between the starts of consecutive rows of `coverage`.
+pub(crate) fn distance_field(
+    coverage: &[u8],
+    pitch: usize,
+    width: u32,
+    height: u32,
+    spread: u32,
+) -> Box<[u8]> {
+    let (width, height, spread) = (width as i32, height as i32, spread as i32);
+    let inside = |x: i32, y: i32| {
+        if x < 0 || y < 0 || width <= x || height <= y {
+            return false;
+        }
+        return 128 <= coverage[y as usize * pitch + x as usize];
+    };
+
+    let field_width = width + 2 * spread;
+    let field_height = height + 2 * spread;
+    let mut field = Vec::with_capacity((field_width * field_height) as usize);
+    for field_y in 0..field_height {
+        for field_x in 0..field_width {
+            let (x, y) = (field_x - spread, field_y - spread);
+            let is_inside = inside(x, y);
+
+            // Brute force search for the nearest texel on the other side of the edge
+            let mut nearest = ((spread + 1) * (spread + 1)) as f32;
+            for dy in -spread..=spread {
+                for dx in -spread..=spread {
+                    let squared = (dx * dx + dy * dy) as f32;
+                    if squared < nearest && inside(x + dx, y + dy) != is_inside {
+                        nearest = squared;
+                    }
+                }
+            }
+
+            // The edge lies halfway between the two texel centres
+            let distance = f32::min(nearest.sqrt() - 0.5, spread as f32);
+            let signed = if is_inside { distance } else { -distance };
+            let value = 0.5 + 0.5 * signed / spread as f32;
+            field.push((255.0 * value).round().clamp(0.0, 255.0) as u8);
+        }
+    }
+
+    return field.into_boxed_slice();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pads_by_spread() {
+        let coverage = [255; 6];
+        let field = distance_field(&coverage, 3, 3, 2, 4);
+
+        assert_eq!(field.len(), (3 + 8) * (2 + 8));
+        // Corners are as far outside as the field can represent
+        assert_eq!(field[0], 0);
+    }
+
+    #[test]
+    fn edge_sits_at_half() {
+        // A single column of coverage, stored with a wider pitch than the bitmap
+        let coverage = [0, 255, 0, 9, 0, 255, 0, 9, 0, 255, 0, 9];
+        let field = distance_field(&coverage, 4, 3, 3, 2);
+        let row = &field[(2 + 1) * 7..(2 + 2) * 7];
+
+        // Texels on either side of an edge are equally far from it
+        assert_eq!(row[3] as i32 - 128, 128 - row[4] as i32 - 1);
+        assert_eq!(row[3], row[3].max(row[2]).max(row[4]));
+        assert!(row[0] < row[1] && row[1] < row[2]);
+    }
+}
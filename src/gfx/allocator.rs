@@ -0,0 +1,433 @@
+use super::{
+    find_memory_type,
+    loader::{DeviceTable, InstanceTable},
+};
+use crate::ffi::vk;
+use std::{
+    fmt,
+    mem::MaybeUninit,
+    ptr::{null, null_mut, NonNull},
+};
+
+/// Size of the blocks sub-allocations are carved from. Requests larger than half a block get a
+/// block of their own.
+const BLOCK_SIZE: vk::DeviceSize = 16 * 1024 * 1024;
+
+/// Whether a resource is laid out linearly in memory, like buffers and linearly tiled images, or
+/// in an implementation-defined way, like optimally tiled images. Linear and optimal resources
+/// may not share a `bufferImageGranularity`-sized page.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) enum AllocationKind {
+    Linear,
+    Optimal,
+}
+
+/// A region of device memory handed out by the `Allocator`. It must be returned with
+/// `Allocator::free` before the resource bound to it is destroyed or replaced.
+pub(crate) struct Allocation {
+    pub(crate) memory: *mut vk::DeviceMemory,
+    pub(crate) offset: vk::DeviceSize,
+    /// Start of the allocation in host address space, or null if the memory isn't host visible
+    pub(crate) mapped: *mut u8,
+    /// Bytes the resource required, excluding alignment padding
+    pub(crate) size: vk::DeviceSize,
+    block: usize,
+}
+
+/// Totals over every block the allocator currently holds.
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct MemoryStats {
+    pub(crate) blocks: usize,
+    pub(crate) allocations: usize,
+    /// Bytes allocated from the driver
+    pub(crate) reserved: vk::DeviceSize,
+    /// Bytes handed out to resources, including alignment padding
+    pub(crate) used: vk::DeviceSize,
+}
+
+impl fmt::Display for MemoryStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mib = |bytes: vk::DeviceSize| bytes as f64 / (1024.0 * 1024.0);
+        return write!(
+            f,
+            "{} allocations in {} blocks, {:.2} of {:.2} MiB used",
+            self.allocations,
+            self.blocks,
+            mib(self.used),
+            mib(self.reserved)
+        );
+    }
+}
+
+struct Block {
+    memory: NonNull<vk::DeviceMemory>,
+    memory_type: u32,
+    mapped: *mut u8,
+    /// Dedicated blocks hold a single large allocation and are freed along with it
+    dedicated: bool,
+    ranges: FreeList,
+}
+
+/// Sub-allocates buffers and images from a few large `DeviceMemory` blocks per memory type, rather
+/// than allocating memory for every resource. Host visible blocks stay mapped for their lifetime.
+pub(crate) struct Allocator {
+    device: *mut vk::Device,
+    physical_device: *mut vk::PhysicalDevice,
+    memory_properties: vk::PhysicalDeviceMemoryProperties,
+    buffer_image_granularity: vk::DeviceSize,
+    blocks: Vec<Option<Block>>,
+}
+
+impl Allocator {
+    pub(crate) fn new(
+        instance_table: &InstanceTable,
+        physical_device: *mut vk::PhysicalDevice,
+        device: *mut vk::Device,
+    ) -> Self {
+        let mut memory_properties = MaybeUninit::uninit();
+        (instance_table.get_physical_device_memory_properties)(
+            physical_device,
+            memory_properties.as_mut_ptr(),
+        );
+        let memory_properties = unsafe { memory_properties.assume_init() };
+
+        let mut properties = MaybeUninit::uninit();
+        (instance_table.get_physical_device_properties)(physical_device, properties.as_mut_ptr());
+        let properties = unsafe { properties.assume_init() };
+
+        return Self {
+            device,
+            physical_device,
+            memory_properties,
+            buffer_image_granularity: properties.limits.buffer_image_granularity.max(1),
+            blocks: Vec::new(),
+        };
+    }
+
+    pub(crate) fn allocate(
+        &mut self,
+        instance_table: &InstanceTable,
+        device_table: &DeviceTable,
+        requirements: vk::MemoryRequirements,
+        flags: vk::MemoryPropertyFlags,
+        kind: AllocationKind,
+    ) -> Option<Allocation> {
+        let memory_type = find_memory_type(
+            instance_table,
+            self.physical_device,
+            requirements.memory_type_bits,
+            flags,
+        )?;
+        let alignment = requirements.alignment.max(1);
+        let dedicated = BLOCK_SIZE / 2 < requirements.size;
+
+        if !dedicated {
+            for (index, block) in self.blocks.iter_mut().enumerate() {
+                let block = match block {
+                    Some(block) if block.memory_type == memory_type && !block.dedicated => block,
+                    _ => continue,
+                };
+                if let Some(offset) = block.ranges.allocate(requirements.size, alignment, kind) {
+                    return Some(block.allocation(index, offset, requirements.size));
+                }
+            }
+        }
+
+        let size = if dedicated {
+            requirements.size
+        } else {
+            BLOCK_SIZE
+        };
+        let mut block = self.create_block(device_table, memory_type, size, dedicated)?;
+        let offset = block
+            .ranges
+            .allocate(requirements.size, alignment, kind)
+            .expect("Allocation doesn't fit in a new block!");
+
+        let index = match self.blocks.iter().position(Option::is_none) {
+            Some(index) => index,
+            None => {
+                self.blocks.push(None);
+                self.blocks.len() - 1
+            }
+        };
+        let allocation = block.allocation(index, offset, requirements.size);
+        self.blocks[index] = Some(block);
+        return Some(allocation);
+    }
+
+    /// Frees every block, invalidating all outstanding allocations.
+    pub(crate) fn destroy(&mut self, device_table: &DeviceTable) {
+        for block in self.blocks.drain(..).flatten() {
+            (device_table.free_memory)(self.device, block.memory.as_ptr(), null());
+        }
+    }
+
+    pub(crate) fn free(&mut self, device_table: &DeviceTable, allocation: Allocation) {
+        let slot = &mut self.blocks[allocation.block];
+        let block = slot.as_mut().expect("Allocation freed twice!");
+        block.ranges.free(allocation.offset);
+
+        if block.dedicated && block.ranges.is_empty() {
+            (device_table.free_memory)(self.device, block.memory.as_ptr(), null());
+            *slot = None;
+        }
+    }
+
+    pub(crate) fn stats(&self) -> MemoryStats {
+        let mut stats = MemoryStats::default();
+        for block in self.blocks.iter().flatten() {
+            stats.blocks += 1;
+            stats.allocations += block.ranges.allocation_count();
+            stats.reserved += block.ranges.size();
+            stats.used += block.ranges.used();
+        }
+        return stats;
+    }
+
+    fn create_block(
+        &self,
+        device_table: &DeviceTable,
+        memory_type: u32,
+        size: vk::DeviceSize,
+        dedicated: bool,
+    ) -> Option<Block> {
+        let allocate_info = vk::MemoryAllocateInfo {
+            stype: vk::StructureType::MemoryAllocateInfo,
+            next: null(),
+            allocation_size: size,
+            memory_type_index: memory_type,
+        };
+        let mut memory = null_mut();
+        (device_table.allocate_memory)(self.device, &allocate_info, null(), &mut memory);
+        let memory = NonNull::new(memory)?;
+
+        let property_flags =
+            self.memory_properties.memory_types[memory_type as usize].property_flags;
+        let mut mapped = null_mut();
+        if property_flags & vk::MemoryPropertyFlagBits::HostVisible as u32 != 0 {
+            (device_table.map_memory)(
+                self.device,
+                memory.as_ptr(),
+                0,
+                vk::WHOLE_SIZE,
+                0,
+                &mut mapped,
+            );
+        }
+
+        debug!(
+            "Allocated a {} KiB block of memory type {}",
+            size / 1024,
+            memory_type
+        );
+        return Some(Block {
+            memory,
+            memory_type,
+            mapped: mapped as *mut u8,
+            dedicated,
+            ranges: FreeList::new(size, self.buffer_image_granularity),
+        });
+    }
+}
+
+impl Block {
+    fn allocation(&self, index: usize, offset: vk::DeviceSize, size: vk::DeviceSize) -> Allocation {
+        let mapped = if self.mapped.is_null() {
+            null_mut()
+        } else {
+            unsafe { self.mapped.add(offset as usize) }
+        };
+
+        return Allocation {
+            memory: self.memory.as_ptr(),
+            offset,
+            mapped,
+            size,
+            block: index,
+        };
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+struct Range {
+    offset: vk::DeviceSize,
+    size: vk::DeviceSize,
+    /// `None` if the range is free
+    kind: Option<AllocationKind>,
+}
+
+/// First-fit allocator over a single block. The ranges cover the whole block in order and no two
+/// free ranges are adjacent.
+struct FreeList {
+    ranges: Vec<Range>,
+    granularity: vk::DeviceSize,
+}
+
+impl FreeList {
+    fn new(size: vk::DeviceSize, granularity: vk::DeviceSize) -> Self {
+        let range = Range {
+            offset: 0,
+            size,
+            kind: None,
+        };
+        return Self {
+            ranges: vec![range],
+            granularity,
+        };
+    }
+
+    /// Returns the offset of the new allocation, or `None` if no free range can hold it.
+    fn allocate(
+        &mut self,
+        size: vk::DeviceSize,
+        alignment: vk::DeviceSize,
+        kind: AllocationKind,
+    ) -> Option<vk::DeviceSize> {
+        let granularity = self.granularity;
+        let conflicts = |other: Option<AllocationKind>| other.map_or(false, |other| other != kind);
+        let same_page = |a: vk::DeviceSize, b: vk::DeviceSize| a / granularity == b / granularity;
+
+        for i in 0..self.ranges.len() {
+            let range = self.ranges[i];
+            if range.kind.is_some() || range.size < size {
+                continue;
+            }
+
+            // Neighbours of a free range are always allocated, so only they need checking
+            let mut offset = align_up(range.offset, alignment);
+            if let Some(previous) = i.checked_sub(1).map(|i| self.ranges[i]) {
+                let previous_end = previous.offset + previous.size;
+                if conflicts(previous.kind) && same_page(previous_end - 1, offset) {
+                    offset = align_up(offset, granularity);
+                }
+            }
+
+            let end = offset + size;
+            let range_end = range.offset + range.size;
+            if range_end < end {
+                continue;
+            }
+            if let Some(next) = self.ranges.get(i + 1) {
+                if conflicts(next.kind) && same_page(end - 1, next.offset) {
+                    continue;
+                }
+            }
+
+            let mut split = Vec::with_capacity(3);
+            if range.offset < offset {
+                split.push(Range {
+                    offset: range.offset,
+                    size: offset - range.offset,
+                    kind: None,
+                });
+            }
+            split.push(Range {
+                offset,
+                size,
+                kind: Some(kind),
+            });
+            if end < range_end {
+                split.push(Range {
+                    offset: end,
+                    size: range_end - end,
+                    kind: None,
+                });
+            }
+            self.ranges.splice(i..=i, split);
+            return Some(offset);
+        }
+
+        return None;
+    }
+
+    fn allocation_count(&self) -> usize {
+        return self.ranges.iter().filter(|r| r.kind.is_some()).count();
+    }
+
+    fn free(&mut self, offset: vk::DeviceSize) {
+        let mut i = self
+            .ranges
+            .binary_search_by_key(&offset, |range| range.offset)
+            .expect("No allocation at offset!");
+        debug_assert!(self.ranges[i].kind.is_some());
+        self.ranges[i].kind = None;
+
+        // Merge with free neighbours to keep free ranges maximal
+        if self
+            .ranges
+            .get(i + 1)
+            .map_or(false, |next| next.kind.is_none())
+        {
+            self.ranges[i].size += self.ranges.remove(i + 1).size;
+        }
+        if 0 < i && self.ranges[i - 1].kind.is_none() {
+            let range = self.ranges.remove(i);
+            i -= 1;
+            self.ranges[i].size += range.size;
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        return self.allocation_count() == 0;
+    }
+
+    fn size(&self) -> vk::DeviceSize {
+        return self.ranges.iter().map(|r| r.size).sum();
+    }
+
+    fn used(&self) -> vk::DeviceSize {
+        return self
+            .ranges
+            .iter()
+            .filter(|r| r.kind.is_some())
+            .map(|r| r.size)
+            .sum();
+    }
+}
+
+fn align_up(value: vk::DeviceSize, alignment: vk::DeviceSize) -> vk::DeviceSize {
+    return (value + alignment - 1) / alignment * alignment;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn respects_alignment() {
+        let mut list = FreeList::new(1024, 1);
+        assert_eq!(list.allocate(10, 1, AllocationKind::Linear), Some(0));
+        assert_eq!(list.allocate(10, 256, AllocationKind::Linear), Some(256));
+        // The padding left behind is still usable
+        assert_eq!(list.allocate(100, 4, AllocationKind::Linear), Some(12));
+        assert_eq!(list.allocate(1024, 1, AllocationKind::Linear), None);
+    }
+
+    #[test]
+    fn separates_linear_and_optimal_pages() {
+        let mut list = FreeList::new(4096, 1024);
+        assert_eq!(list.allocate(100, 4, AllocationKind::Linear), Some(0));
+        assert_eq!(list.allocate(100, 4, AllocationKind::Optimal), Some(1024));
+        assert_eq!(list.allocate(100, 4, AllocationKind::Linear), Some(100));
+
+        // Would end on the page the optimal allocation starts on
+        assert_eq!(list.allocate(900, 4, AllocationKind::Linear), Some(2048));
+    }
+
+    #[test]
+    fn free_merges_ranges() {
+        let mut list = FreeList::new(300, 1);
+        let offsets: Vec<_> = (0..3)
+            .map(|_| list.allocate(100, 1, AllocationKind::Linear).unwrap())
+            .collect();
+        assert_eq!(list.used(), 300);
+
+        list.free(offsets[0]);
+        list.free(offsets[2]);
+        list.free(offsets[1]);
+        assert!(list.is_empty());
+        assert_eq!(list.ranges.len(), 1);
+        assert_eq!(list.allocate(300, 1, AllocationKind::Optimal), Some(0));
+    }
+}
@@ -0,0 +1,119 @@
+use crate::ffi::{linux, Inotify};
+use std::{
+    ffi::OsString,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+/// Material files, reported by `poll` along with shaders.
+const MATERIAL_DIRECTORY: &str = "materials";
+/// Compiled shaders, loaded by materials.
+const SHADER_DIRECTORY: &str = "shaders";
+/// GLSL sources, compiled into `SHADER_DIRECTORY` when they change.
+const SOURCE_DIRECTORY: &str = "shaders/src";
+
+/// Watches the shader directories in the asset override directory during development. Saving a
+/// GLSL source recompiles it with `glslc`, and the rewritten SPIR-V is then reported by `poll` so
+/// its materials can be rebuilt. Edited material files are reported too.
+pub(crate) struct ShaderWatcher {
+    directory: PathBuf,
+    inotify: Inotify,
+    materials: Option<i32>,
+    shaders: i32,
+    sources: Option<i32>,
+}
+
+impl ShaderWatcher {
+    pub(crate) fn new() -> Option<Self> {
+        // Embedded shaders can't change, so only overrides are watched
+        let directory = match crate::assets::directory() {
+            Some(directory) => directory,
+            None => {
+                warn!("Set THYROX_ASSETS to the directory containing shaders/ to hot reload");
+                return None;
+            }
+        };
+
+        // Editors often save by renaming a new file over the old one
+        let mask = linux::IN_CLOSE_WRITE | linux::IN_MOVED_TO;
+        let inotify = Inotify::init()?;
+        let shaders = inotify.add_watch(&directory.join(SHADER_DIRECTORY), mask)?;
+        let sources = inotify.add_watch(&directory.join(SOURCE_DIRECTORY), mask);
+        let materials = inotify.add_watch(&directory.join(MATERIAL_DIRECTORY), mask);
+        if sources.is_none() {
+            warn!(
+                "Not watching {}, only SPIR-V changes will be reloaded",
+                SOURCE_DIRECTORY
+            );
+        }
+
+        return Some(Self {
+            directory,
+            inotify,
+            materials,
+            shaders,
+            sources,
+        });
+    }
+
+    /// Returns the SPIR-V and material files rewritten since the last call, relative to the
+    /// override directory and without duplicates.
+    pub(crate) fn poll(&mut self) -> Vec<PathBuf> {
+        let mut changed = Vec::new();
+        for (wd, name) in self.inotify.read_events() {
+            let extension = Path::new(&name).extension();
+            let path = if wd == self.shaders && extension == Some("spv".as_ref()) {
+                Path::new(SHADER_DIRECTORY).join(name)
+            } else if Some(wd) == self.materials && extension == Some("material".as_ref()) {
+                Path::new(MATERIAL_DIRECTORY).join(name)
+            } else {
+                if Some(wd) == self.sources {
+                    compile(&self.directory, &name);
+                }
+                continue;
+            };
+
+            if !changed.contains(&path) {
+                changed.push(path);
+            }
+        }
+
+        return changed;
+    }
+}
+
+/// Compiles `shaders/src/<name>.<stage>.glsl` into `shaders/<name>.<stage>.spv` within
+/// `directory`, logging any errors. Other files are ignored.
+fn compile(directory: &Path, source: &OsString) {
+    let source = Path::new(source);
+    if source.extension() != Some("glsl".as_ref()) {
+        return;
+    }
+
+    // The stage is the second extension, as in `text.frag.glsl`
+    let stem = Path::new(source.file_stem().unwrap());
+    let stage = match stem.extension().and_then(|stage| stage.to_str()) {
+        Some("frag") => "fragment",
+        Some("vert") => "vertex",
+        _ => return,
+    };
+
+    let input = directory.join(SOURCE_DIRECTORY).join(source);
+    let mut output = directory.join(SHADER_DIRECTORY).join(stem).into_os_string();
+    output.push(".spv");
+    let result = Command::new("glslc")
+        .arg(format!("-fshader-stage={}", stage))
+        .arg(&input)
+        .arg("-o")
+        .arg(&output)
+        .output();
+    match result {
+        Ok(output) if output.status.success() => debug!("Compiled {:?}", input),
+        Ok(output) => error!(
+            "Failed to compile {:?}, keeping the old shader:\n{}",
+            input,
+            String::from_utf8_lossy(&output.stderr).trim_end()
+        ),
+        Err(e) => error!("Failed to run glslc: {}", e),
+    }
+}
@@ -14,6 +14,7 @@ pub struct DeviceTable {
     pub cmd_bind_index_buffer: vk::CmdBindIndexBuffer,
     pub cmd_bind_pipeline: vk::CmdBindPipeline,
     pub cmd_bind_vertex_buffers: vk::CmdBindVertexBuffers,
+    pub cmd_blit_image: vk::CmdBlitImage,
     pub cmd_copy_buffer: vk::CmdCopyBuffer,
     pub cmd_copy_buffer_to_image: vk::CmdCopyBufferToImage,
     pub cmd_copy_image: vk::CmdCopyImage,
@@ -40,6 +41,7 @@ pub struct DeviceTable {
     pub create_graphics_pipelines: vk::CreateGraphicsPipelines,
     pub create_image: vk::CreateImage,
     pub create_image_view: vk::CreateImageView,
+    pub create_pipeline_cache: vk::CreatePipelineCache,
     pub create_pipeline_layout: vk::CreatePipelineLayout,
     pub create_render_pass: vk::CreateRenderPass,
     pub create_sampler: vk::CreateSampler,
@@ -56,6 +58,7 @@ pub struct DeviceTable {
     pub destroy_image: vk::DestroyImage,
     pub destroy_image_view: vk::DestroyImageView,
     pub destroy_pipeline: vk::DestroyPipeline,
+    pub destroy_pipeline_cache: vk::DestroyPipelineCache,
     pub destroy_pipeline_layout: vk::DestroyPipelineLayout,
     pub destroy_render_pass: vk::DestroyRenderPass,
     pub destroy_sampler: vk::DestroySampler,
@@ -71,7 +74,9 @@ pub struct DeviceTable {
     pub get_buffer_memory_requirements: vk::GetBufferMemoryRequirements,
     pub get_device_memory_commitment: vk::GetDeviceMemoryCommitment,
     pub get_device_queue: vk::GetDeviceQueue,
+    pub get_fence_status: vk::GetFenceStatus,
     pub get_image_memory_requirements: vk::GetImageMemoryRequirements,
+    pub get_pipeline_cache_data: vk::GetPipelineCacheData,
     pub get_swapchain_images_khr: vk::GetSwapchainImagesKHR,
     pub invalidate_mapped_memory_ranges: vk::InvalidateMappedMemoryRanges,
     pub map_memory: vk::MapMemory,
@@ -87,6 +92,12 @@ pub struct DeviceTable {
     pub wait_for_fences: vk::WaitForFences,
 }
 
+/// Functions provided by `VK_EXT_debug_utils`, only present when the extension is enabled.
+pub struct DebugUtilsTable {
+    pub create_debug_utils_messenger_ext: vk::CreateDebugUtilsMessengerEXT,
+    pub destroy_debug_utils_messenger_ext: vk::DestroyDebugUtilsMessengerEXT,
+}
+
 pub struct InstanceTable {
     pub create_device: vk::CreateDevice,
     pub create_instance: vk::CreateInstance,
@@ -105,6 +116,7 @@ pub struct InstanceTable {
     pub get_physical_device_queue_family_properties: vk::GetPhysicalDeviceQueueFamilyProperties,
     pub get_physical_device_surface_capabilities_khr: vk::GetPhysicalDeviceSurfaceCapabilitiesKHR,
     pub get_physical_device_surface_formats_khr: vk::GetPhysicalDeviceSurfaceFormatsKHR,
+    pub get_physical_device_surface_present_modes_khr: vk::GetPhysicalDeviceSurfacePresentModesKHR,
     pub get_physical_device_surface_support_khr: vk::GetPhysicalDeviceSurfaceSupportKHR,
 }
 
@@ -181,6 +193,7 @@ impl Loader {
             cmd_bind_index_buffer: "vkCmdBindIndexBuffer\0",
             cmd_bind_pipeline: "vkCmdBindPipeline\0",
             cmd_bind_vertex_buffers: "vkCmdBindVertexBuffers\0",
+            cmd_blit_image: "vkCmdBlitImage\0",
             cmd_copy_buffer: "vkCmdCopyBuffer\0",
             cmd_copy_buffer_to_image: "vkCmdCopyBufferToImage\0",
             cmd_copy_image: "vkCmdCopyImage\0",
@@ -207,6 +220,7 @@ impl Loader {
             create_graphics_pipelines: "vkCreateGraphicsPipelines\0",
             create_image: "vkCreateImage\0",
             create_image_view: "vkCreateImageView\0",
+            create_pipeline_cache: "vkCreatePipelineCache\0",
             create_pipeline_layout: "vkCreatePipelineLayout\0",
             create_render_pass: "vkCreateRenderPass\0",
             create_sampler: "vkCreateSampler\0",
@@ -223,6 +237,7 @@ impl Loader {
             destroy_image: "vkDestroyImage\0",
             destroy_image_view: "vkDestroyImageView\0",
             destroy_pipeline: "vkDestroyPipeline\0",
+            destroy_pipeline_cache: "vkDestroyPipelineCache\0",
             destroy_pipeline_layout: "vkDestroyPipelineLayout\0",
             destroy_render_pass: "vkDestroyRenderPass\0",
             destroy_sampler: "vkDestroySampler\0",
@@ -238,7 +253,9 @@ impl Loader {
             get_buffer_memory_requirements: "vkGetBufferMemoryRequirements\0",
             get_device_memory_commitment: "vkGetDeviceMemoryCommitment\0",
             get_device_queue: "vkGetDeviceQueue\0",
+            get_fence_status: "vkGetFenceStatus\0",
             get_image_memory_requirements: "vkGetImageMemoryRequirements\0",
+            get_pipeline_cache_data: "vkGetPipelineCacheData\0",
             get_swapchain_images_khr: "vkGetSwapchainImagesKHR\0",
             invalidate_mapped_memory_ranges: "vkInvalidateMappedMemoryRanges\0",
             map_memory: "vkMapMemory\0",
@@ -292,12 +309,28 @@ impl Loader {
             get_physical_device_queue_family_properties: "vkGetPhysicalDeviceQueueFamilyProperties\0",
             get_physical_device_surface_capabilities_khr: "vkGetPhysicalDeviceSurfaceCapabilitiesKHR\0",
             get_physical_device_surface_formats_khr: "vkGetPhysicalDeviceSurfaceFormatsKHR\0",
+            get_physical_device_surface_present_modes_khr: "vkGetPhysicalDeviceSurfacePresentModesKHR\0",
             get_physical_device_surface_support_khr: "vkGetPhysicalDeviceSurfaceSupportKHR\0",
         );
 
         return table;
     }
 
+    pub fn load_debug_utils_functions(&self, instance: *mut vk::Instance) -> DebugUtilsTable {
+        let table = unsafe {
+            DebugUtilsTable {
+                create_debug_utils_messenger_ext: transmute(
+                    self.load_instance_symbol(instance, "vkCreateDebugUtilsMessengerEXT\0"),
+                ),
+                destroy_debug_utils_messenger_ext: transmute(
+                    self.load_instance_symbol(instance, "vkDestroyDebugUtilsMessengerEXT\0"),
+                ),
+            }
+        };
+
+        return table;
+    }
+
     pub fn load_vk_create_instance(&self) -> vk::CreateInstance {
         let symbol = "vkCreateInstance\0";
         let csymbol = CStr::from_bytes_with_nul(symbol.as_bytes()).unwrap();
@@ -308,6 +341,34 @@ impl Loader {
         return create_instance;
     }
 
+    pub fn load_vk_enumerate_instance_extension_properties(
+        &self,
+    ) -> vk::EnumerateInstanceExtensionProperties {
+        let symbol = "vkEnumerateInstanceExtensionProperties\0";
+        let csymbol = CStr::from_bytes_with_nul(symbol.as_bytes()).unwrap();
+        let void = (self.get_instance_proc_addr)(null_mut(), csymbol.as_ptr());
+        let enumerate_instance_extension_properties = unsafe {
+            transmute::<_, Option<_>>(void)
+                .expect("Failed to load vkEnumerateInstanceExtensionProperties")
+        };
+
+        return enumerate_instance_extension_properties;
+    }
+
+    pub fn load_vk_enumerate_instance_layer_properties(
+        &self,
+    ) -> vk::EnumerateInstanceLayerProperties {
+        let symbol = "vkEnumerateInstanceLayerProperties\0";
+        let csymbol = CStr::from_bytes_with_nul(symbol.as_bytes()).unwrap();
+        let void = (self.get_instance_proc_addr)(null_mut(), csymbol.as_ptr());
+        let enumerate_instance_layer_properties = unsafe {
+            transmute::<_, Option<_>>(void)
+                .expect("Failed to load vkEnumerateInstanceLayerProperties")
+        };
+
+        return enumerate_instance_layer_properties;
+    }
+
     pub fn take_library(self) -> Library {
         return self._vulkan;
     }
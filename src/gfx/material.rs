@@ -1,25 +1,130 @@
-use super::loader::DeviceTable;
+use super::{
+    loader::DeviceTable,
+    material_file::{DescriptionError, MaterialDescription},
+    reflect::{self, Reflection, ScalarKind},
+    ShaderModule,
+};
 use crate::ffi::vk;
 use std::{
-    path::Path,
+    path::{Path, PathBuf},
     ptr::{null, null_mut},
 };
 
-pub(crate) struct DescriptorSetLayoutBinding {
-    pub(crate) descriptor_type: vk::DescriptorType,
-    pub(crate) descriptor_count: u32,
-    pub(crate) stage_flags: vk::ShaderStageFlags,
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) enum BlendMode {
+    Opaque,
+    /// Straight alpha, the default
+    Alpha,
+    /// Adds the colour scaled by its alpha, for glows and other light
+    Additive,
+    /// Multiplies the destination by the colour, ignoring alpha, so white leaves it unchanged
+    Multiply,
+    /// For colours already multiplied by their alpha, which blend without dark fringes
+    PremultipliedAlpha,
+}
+
+/// Fixed-function state of a material's pipeline.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct PipelineState {
+    pub(crate) blend: BlendMode,
+    pub(crate) cull_mode: vk::CullModeBits,
+    pub(crate) topology: vk::PrimitiveTopology,
+    /// Anything but `Fill` requires `PipelineContext::non_solid_fill`
+    pub(crate) polygon_mode: vk::PolygonMode,
+}
+
+impl Default for PipelineState {
+    fn default() -> Self {
+        return Self {
+            blend: BlendMode::Alpha,
+            cull_mode: vk::CullModeBits::Back,
+            topology: vk::PrimitiveTopology::TriangleList,
+            polygon_mode: vk::PolygonMode::Fill,
+        };
+    }
+}
+
+/// Where a material's pipeline is used and what the device supports, shared by every material.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct PipelineContext {
+    pub(crate) render_pass: *mut vk::RenderPass,
+    pub(crate) pipeline_cache: *mut vk::PipelineCache,
+    pub(crate) samples: vk::SampleCountFlagBits,
+    /// Whether the device supports line and point polygon modes
+    pub(crate) non_solid_fill: bool,
 }
 
 #[derive(Clone, Copy, Debug)]
 pub(crate) enum MaterialError {
     DescriptorSet,
+    Pipeline,
     PipelineLayout,
+    ShaderModule,
+    /// The material file couldn't be read or isn't UTF-8
+    Read,
+    Description(DescriptionError),
+    /// The vertex and instance layouts in the material file differ from the vertex shader inputs,
+    /// or the inputs leave gaps between locations
+    VertexLayoutMismatch,
+    /// The binding is missing from the material file or the shaders, or its type differs
+    UndeclaredBinding(u32),
+    /// The material can't be drawn with the vertices and descriptors sprites provide
+    NotSpriteCompatible,
+    /// Line and point polygon modes aren't supported by the device
+    PolygonMode,
+    /// Both stages declare the binding, with different types or counts
+    BindingMismatch(u32),
+    /// A fragment shader input location with no vertex shader output of the same type
+    InterfaceMismatch(u32),
+    /// Materials have a single descriptor set, so any other set is unsupported
+    UnsupportedSet(u32),
+    /// Textures are all normalized or sRGB formats, so must be sampled as floats
+    IntegerSampler(u32),
+    /// A vertex input location that isn't a 32-bit scalar or vector
+    UnsupportedVertexInput(u32),
 }
 
 impl std::fmt::Display for MaterialError {
-    fn fmt(&self, _: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        return Ok(());
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        return match self {
+            Self::DescriptorSet => write!(f, "failed to create descriptor set layout"),
+            Self::Pipeline => write!(f, "failed to create pipeline"),
+            Self::PipelineLayout => write!(f, "failed to create pipeline layout"),
+            Self::ShaderModule => write!(f, "failed to load shader module"),
+            Self::Read => write!(f, "failed to read material file"),
+            Self::Description(e) => write!(f, "invalid material file, {}", e),
+            Self::VertexLayoutMismatch => {
+                write!(f, "vertex layout doesn't match the vertex shader inputs")
+            }
+            Self::UndeclaredBinding(binding) => write!(
+                f,
+                "binding {} differs between the material file and the shaders",
+                binding
+            ),
+            Self::NotSpriteCompatible => write!(f, "material can't be used to draw sprites"),
+            Self::PolygonMode => write!(f, "polygon mode isn't supported by the device"),
+            Self::BindingMismatch(binding) => write!(
+                f,
+                "binding {} differs between the vertex and fragment shaders",
+                binding
+            ),
+            Self::InterfaceMismatch(location) => write!(
+                f,
+                "fragment input at location {} doesn't match a vertex output",
+                location
+            ),
+            Self::UnsupportedSet(set) => {
+                write!(f, "descriptor set {} used, only set 0 is supported", set)
+            }
+            Self::IntegerSampler(binding) => write!(
+                f,
+                "binding {} samples integers, but textures are sampled as floats",
+                binding
+            ),
+            Self::UnsupportedVertexInput(location) => {
+                write!(f, "unsupported vertex input at location {}", location)
+            }
+        };
     }
 }
 
@@ -29,6 +134,9 @@ pub(crate) struct Material {
     pub(crate) set_layout: *mut vk::DescriptorSetLayout,
     pub(crate) pipeline_layout: *mut vk::PipelineLayout,
     pub(crate) pipeline: *mut vk::Pipeline,
+    pub(crate) description: MaterialDescription,
+    /// The material file, reread when the material is reloaded
+    path: PathBuf,
     device: *mut vk::Device,
     destroy_descriptor_set_layout: vk::DestroyDescriptorSetLayout,
     destroy_pipeline_layout: vk::DestroyPipelineLayout,
@@ -36,75 +144,93 @@ pub(crate) struct Material {
 }
 
 impl Material {
-    pub(crate) fn new<F, V>(
+    /// Reads a material file and builds its pipeline.
+    pub(crate) fn load<P>(
         device_table: &DeviceTable,
         device: *mut vk::Device,
-        bindings: &[DescriptorSetLayoutBinding],
-        push_constant_ranges: &[vk::PushConstantRange],
-        fragment: F,
-        vertex: V,
-        input_formats: &[vk::Format],
-        render_pass: *mut vk::RenderPass,
+        path: P,
+        context: &PipelineContext,
     ) -> Result<Self, MaterialError>
     where
-        F: AsRef<Path>,
-        V: AsRef<Path>,
+        P: AsRef<Path>,
     {
-        let fragment_module = super::ShaderModule::from_path(device_table, device, fragment);
-        let vertex_module = super::ShaderModule::from_path(device_table, device, vertex);
-
-        let bindings: Box<[vk::DescriptorSetLayoutBinding]> = bindings
-            .iter()
-            .scan(0, |index, binding| {
-                let binding = vk::DescriptorSetLayoutBinding {
-                    binding: *index,
-                    descriptor_type: binding.descriptor_type,
-                    descriptor_count: binding.descriptor_count,
-                    stage_flags: binding.stage_flags,
-                    immutable_samplers: null(),
-                };
+        let path = path.as_ref();
+        let file = crate::assets::read(path).ok_or(MaterialError::Read)?;
+        let text = std::str::from_utf8(&file).map_err(|_| MaterialError::Read)?;
+        let description = MaterialDescription::parse(text).map_err(MaterialError::Description)?;
+        return Self::new(device_table, device, path.to_owned(), description, context);
+    }
+
+    /// Push constants are read from the shaders, while the layouts and bindings given by the
+    /// description must match them. Vertex inputs are expected to be interleaved in the buffer
+    /// bound to binding 0 and instance inputs in the one bound to binding 1, in order of location
+    /// with no gaps between locations.
+    fn new(
+        device_table: &DeviceTable,
+        device: *mut vk::Device,
+        path: PathBuf,
+        description: MaterialDescription,
+        context: &PipelineContext,
+    ) -> Result<Self, MaterialError> {
+        if description.state.polygon_mode != vk::PolygonMode::Fill && !context.non_solid_fill {
+            return Err(MaterialError::PolygonMode);
+        }
+
+        let (fragment_module, fragment) = load_shader(device_table, device, &description.fragment)?;
+        let (vertex_module, vertex) = load_shader(device_table, device, &description.vertex)?;
+        let bindings = descriptor_set_layout_bindings(&vertex, &fragment)?;
+        let push_constant_ranges = push_constant_ranges(&vertex, &fragment);
+        let input_formats = vertex_input_formats(&vertex, &fragment)?;
+        check_declared_bindings(&description.bindings, &bindings)?;
+        let vertex_layout = &description.vertex_layout;
+        let instance_layout = &description.instance_layout;
+        let layout = vertex_layout.iter().chain(instance_layout);
+        if !input_formats.iter().eq(layout) {
+            return Err(MaterialError::VertexLayoutMismatch);
+        }
 
-                *index += 1;
-                Some(binding)
-            })
-            .collect();
         let set_layout = create_descriptor_set_layout(device_table, device, &bindings)
             .ok_or(MaterialError::DescriptorSet)?;
         let set_layouts = &[set_layout];
 
         let pipeline_layout =
-            create_pipeline_layout(device_table, device, set_layouts, push_constant_ranges)
+            create_pipeline_layout(device_table, device, set_layouts, &push_constant_ranges)
                 .ok_or(MaterialError::PipelineLayout)?;
 
-        let vertex_input_attributes: Box<[vk::VertexInputAttributeDescription]> = input_formats
-            .iter()
-            .enumerate()
-            .scan(0, |offset, (i, &format)| {
-                let attribute_description = vk::VertexInputAttributeDescription {
-                    location: i as u32,
-                    binding: 0,
+        let layouts = [
+            (vertex_layout, vk::VertexInputRate::Vertex),
+            (instance_layout, vk::VertexInputRate::Instance),
+        ];
+        let mut vertex_input_attributes = Vec::new();
+        let mut vertex_binding_descriptions = Vec::new();
+        for (binding, &(layout, input_rate)) in layouts.iter().enumerate() {
+            // Full-screen passes have no vertex buffer, and most materials no instance buffer
+            if layout.is_empty() {
+                continue;
+            }
+
+            let mut offset = 0;
+            for &format in layout.iter() {
+                vertex_input_attributes.push(vk::VertexInputAttributeDescription {
+                    location: vertex_input_attributes.len() as u32,
+                    binding: binding as u32,
                     format,
-                    offset: *offset,
-                };
-
-                *offset += format_length(format) as u32;
-                Some(attribute_description)
-            })
-            .collect();
-
-        let vertex_binding_description = vk::VertexInputBindingDescription {
-            binding: 0,
-            stride: input_formats
-                .iter()
-                .fold(0, |acc, &format| acc + format_length(format)) as u32,
-            input_rate: vk::VertexInputRate::Vertex,
-        };
+                    offset,
+                });
+                offset += format_length(format) as u32;
+            }
+            vertex_binding_descriptions.push(vk::VertexInputBindingDescription {
+                binding: binding as u32,
+                stride: offset,
+                input_rate,
+            });
+        }
         let vertex_input_state = vk::PipelineVertexInputStateCreateInfo {
             stype: vk::StructureType::PipelineVertexInputStateCreateInfo,
             next: null(),
             flags: 0,
-            vertex_binding_description_count: 1,
-            vertex_binding_descriptions: &vertex_binding_description,
+            vertex_binding_description_count: vertex_binding_descriptions.len() as u32,
+            vertex_binding_descriptions: vertex_binding_descriptions.as_ptr(),
             vertex_attribute_description_count: vertex_input_attributes.len() as u32,
             vertex_attribute_descriptions: vertex_input_attributes.as_ptr(),
         };
@@ -113,16 +239,24 @@ impl Material {
             device_table,
             device,
             pipeline_layout,
-            render_pass,
+            context,
             &vertex_input_state,
+            &description.state,
             *fragment_module,
             *vertex_module,
         );
+        if pipeline.is_null() {
+            (device_table.destroy_pipeline_layout)(device, pipeline_layout, null());
+            (device_table.destroy_descriptor_set_layout)(device, set_layout, null());
+            return Err(MaterialError::Pipeline);
+        }
 
         let material = Self {
             set_layout,
             pipeline_layout,
             pipeline,
+            description,
+            path,
             device,
             destroy_descriptor_set_layout: device_table.destroy_descriptor_set_layout,
             destroy_pipeline_layout: device_table.destroy_pipeline_layout,
@@ -132,70 +266,25 @@ impl Material {
         return Ok(material);
     }
 
-    pub(crate) fn sprite(
+    /// Builds the material again, rereading its file and shaders. On error the caller keeps the old
+    /// material.
+    pub(crate) fn reload(
+        &self,
         device_table: &DeviceTable,
-        device: *mut vk::Device,
-        render_pass: *mut vk::RenderPass,
+        context: &PipelineContext,
     ) -> Result<Self, MaterialError> {
-        let sprite = DescriptorSetLayoutBinding {
-            descriptor_type: vk::DescriptorType::CombinedImageSampler,
-            descriptor_count: 1,
-            stage_flags: vk::ShaderStageFlagBits::Fragment as u32,
-        };
-        let bindings = [sprite];
-        let push_constant_range = [];
-
-        let fragment = "shaders/triangle.frag.spv";
-        let vertex = "shaders/triangle.vert.spv";
-
-        let formats = [vk::Format::R32G32SFLOAT, vk::Format::R32G32SFLOAT];
-
-        let material = Self::new(
-            device_table,
-            device,
-            &bindings,
-            &push_constant_range,
-            fragment,
-            vertex,
-            &formats,
-            render_pass,
-        );
-        return material;
+        return Self::load(device_table, self.device, &self.path, context);
     }
 
-    pub(crate) fn text(
-        device_table: &DeviceTable,
-        device: *mut vk::Device,
-        render_pass: *mut vk::RenderPass,
-    ) -> Result<Self, MaterialError> {
-        let glyphs = DescriptorSetLayoutBinding {
-            descriptor_type: vk::DescriptorType::CombinedImageSampler,
-            descriptor_count: 1,
-            stage_flags: vk::ShaderStageFlagBits::Fragment as u32,
-        };
-        let bindings = [glyphs];
-        let push_constant_range = [];
-
-        let fragment = "shaders/text.frag.spv";
-        let vertex = "shaders/text.vert.spv";
-
-        let formats = [
-            vk::Format::R32G32SFLOAT,
-            vk::Format::R32G32SFLOAT,
-            vk::Format::R32G32B32A32SFLOAT,
-        ];
+    pub(crate) fn path(&self) -> &Path {
+        return &self.path;
+    }
 
-        let material = Self::new(
-            device_table,
-            device,
-            &bindings,
-            &push_constant_range,
-            fragment,
-            vertex,
-            &formats,
-            render_pass,
-        );
-        return material;
+    /// Whether `path` is the material file or one of its shaders.
+    pub(crate) fn uses_file(&self, path: &Path) -> bool {
+        return self.path == path
+            || self.description.vertex == path
+            || self.description.fragment == path;
     }
 }
 
@@ -207,6 +296,51 @@ impl Drop for Material {
     }
 }
 
+/// Every binding the shaders use must be declared with the same type, and nothing else.
+fn check_declared_bindings(
+    declared: &[(u32, vk::DescriptorType)],
+    bindings: &[vk::DescriptorSetLayoutBinding],
+) -> Result<(), MaterialError> {
+    for b in bindings.iter() {
+        if !declared.contains(&(b.binding, b.descriptor_type)) {
+            return Err(MaterialError::UndeclaredBinding(b.binding));
+        }
+    }
+    for &(binding, _) in declared.iter() {
+        if !bindings.iter().any(|b| b.binding == binding) {
+            return Err(MaterialError::UndeclaredBinding(binding));
+        }
+    }
+
+    return Ok(());
+}
+
+/// Blend factors for colour and alpha, or no blending for `Opaque`.
+fn color_blend_attachment_state(blend: BlendMode) -> vk::PipelineColorBlendAttachmentState {
+    use vk::BlendFactor::*;
+
+    let (src_color, dst_color, src_alpha, dst_alpha) = match blend {
+        BlendMode::Opaque | BlendMode::Alpha => (SourceAlpha, OneMinusSourceAlpha, One, Zero),
+        BlendMode::Additive => (SourceAlpha, One, Zero, One),
+        BlendMode::Multiply => (DestinationColor, Zero, Zero, One),
+        BlendMode::PremultipliedAlpha => (One, OneMinusSourceAlpha, One, OneMinusSourceAlpha),
+    };
+
+    return vk::PipelineColorBlendAttachmentState {
+        blend_enable: (blend != BlendMode::Opaque) as u32,
+        src_color_blend_factor: src_color,
+        dst_color_blend_factor: dst_color,
+        color_blend_op: vk::BlendOp::Add,
+        src_alpha_blend_factor: src_alpha,
+        dst_alpha_blend_factor: dst_alpha,
+        alpha_blend_op: vk::BlendOp::Add,
+        color_write_mask: vk::ColorComponentFlagBits::R as u32
+            | vk::ColorComponentFlagBits::G as u32
+            | vk::ColorComponentFlagBits::B as u32
+            | vk::ColorComponentFlagBits::A as u32,
+    };
+}
+
 fn create_descriptor_set_layout(
     device_table: &DeviceTable,
     device: *mut vk::Device,
@@ -235,8 +369,9 @@ fn create_graphics_pipeline(
     device_table: &DeviceTable,
     device: *mut vk::Device,
     layout: *mut vk::PipelineLayout,
-    render_pass: *mut vk::RenderPass,
+    context: &PipelineContext,
     vertex_input_state: *const vk::PipelineVertexInputStateCreateInfo,
+    state: &PipelineState,
     fragment: *mut vk::ShaderModule,
     vertex: *mut vk::ShaderModule,
 ) -> *mut vk::Pipeline {
@@ -265,7 +400,7 @@ fn create_graphics_pipeline(
         stype: vk::StructureType::PipelineInputAssemblyStateCreateInfo,
         next: null(),
         flags: 0,
-        topology: vk::PrimitiveTopology::TriangleList,
+        topology: state.topology,
         primitive_restart_enabled: false as u32,
     };
 
@@ -295,8 +430,8 @@ fn create_graphics_pipeline(
         flags: 0,
         depth_clamp_enable: false as u32,
         rasterizer_discard_enable: false as u32,
-        polygon_mode: vk::PolygonMode::Fill,
-        cull_mode: vk::CullModeBits::Back as u32,
+        polygon_mode: state.polygon_mode,
+        cull_mode: state.cull_mode as u32,
         front_face: vk::FrontFace::Clockwise,
         depth_bias_enable: false as u32,
         depth_bias_constant_factor: 0.0,
@@ -309,7 +444,7 @@ fn create_graphics_pipeline(
         stype: vk::StructureType::PipelineMultisampleStateCreateInfo,
         next: null(),
         flags: 0,
-        rasterization_samples: vk::SampleCountFlagBits::One,
+        rasterization_samples: context.samples,
         sample_shading_enabled: false as u32,
         min_sample_shading: 0.0,
         sample_mask: null(),
@@ -317,19 +452,7 @@ fn create_graphics_pipeline(
         alpha_to_one_enable: false as u32,
     };
 
-    let attachment_state = vk::PipelineColorBlendAttachmentState {
-        blend_enable: true as u32,
-        src_color_blend_factor: vk::BlendFactor::SourceAlpha,
-        dst_color_blend_factor: vk::BlendFactor::OneMinusSourceAlpha,
-        color_blend_op: vk::BlendOp::Add,
-        src_alpha_blend_factor: vk::BlendFactor::One,
-        dst_alpha_blend_factor: vk::BlendFactor::Zero,
-        alpha_blend_op: vk::BlendOp::Add,
-        color_write_mask: vk::ColorComponentFlagBits::R as u32
-            | vk::ColorComponentFlagBits::G as u32
-            | vk::ColorComponentFlagBits::B as u32
-            | vk::ColorComponentFlagBits::A as u32,
-    };
+    let attachment_state = color_blend_attachment_state(state.blend);
     let color_blend_state = vk::PipelineColorBlendStateCreateInfo {
         stype: vk::StructureType::PipelineColorBlendStateCreateInfo,
         next: null(),
@@ -366,13 +489,20 @@ fn create_graphics_pipeline(
         color_blend_state: &color_blend_state,
         dynamic_state: &dynamic_state,
         layout,
-        render_pass,
+        render_pass: context.render_pass,
         subpass: 0,
         base_pipeline_handle: null_mut(),
         base_pipeline_index: -1,
     };
     let mut pipeline = null_mut();
-    (device_table.create_graphics_pipelines)(device, null_mut(), 1, &info, null(), &mut pipeline);
+    (device_table.create_graphics_pipelines)(
+        device,
+        context.pipeline_cache,
+        1,
+        &info,
+        null(),
+        &mut pipeline,
+    );
     return pipeline;
 }
 
@@ -401,13 +531,130 @@ fn create_pipeline_layout(
     return Some(layout);
 }
 
+/// Merges the bindings of both stages, which must agree wherever they share a binding.
+fn descriptor_set_layout_bindings(
+    vertex: &Reflection,
+    fragment: &Reflection,
+) -> Result<Vec<vk::DescriptorSetLayoutBinding>, MaterialError> {
+    let mut bindings: Vec<vk::DescriptorSetLayoutBinding> = Vec::new();
+    for reflection in [vertex, fragment].iter() {
+        for binding in reflection.bindings.iter() {
+            if binding.set != 0 {
+                return Err(MaterialError::UnsupportedSet(binding.set));
+            }
+            if let Some(ScalarKind::Int) | Some(ScalarKind::Uint) = binding.sampled {
+                return Err(MaterialError::IntegerSampler(binding.binding));
+            }
+
+            let stage = reflection.stage as vk::ShaderStageFlags;
+            let shared = bindings.iter_mut().find(|b| b.binding == binding.binding);
+            match shared {
+                Some(b) if b.descriptor_type == binding.descriptor_type => {
+                    if b.descriptor_count != binding.count {
+                        return Err(MaterialError::BindingMismatch(binding.binding));
+                    }
+                    b.stage_flags |= stage;
+                }
+                Some(_) => return Err(MaterialError::BindingMismatch(binding.binding)),
+                None => bindings.push(vk::DescriptorSetLayoutBinding {
+                    binding: binding.binding,
+                    descriptor_type: binding.descriptor_type,
+                    descriptor_count: binding.count,
+                    stage_flags: stage,
+                    immutable_samplers: null(),
+                }),
+            }
+        }
+    }
+
+    return Ok(bindings);
+}
+
 fn format_length(format: vk::Format) -> usize {
     use vk::Format::*;
     let float_size = std::mem::size_of::<f32>();
 
     match format {
-        R32G32SFLOAT => 2 * float_size,
-        R32G32B32A32SFLOAT => 4 * float_size,
+        R32SFLOAT | R32SINT | R32UINT => float_size,
+        R32G32SFLOAT | R32G32SINT | R32G32UINT => 2 * float_size,
+        R32G32B32SFLOAT | R32G32B32SINT | R32G32B32UINT => 3 * float_size,
+        R32G32B32A32SFLOAT | R32G32B32A32SINT | R32G32B32A32UINT => 4 * float_size,
         _ => todo!("Unknown format {:?}", format),
     }
 }
+
+/// Reads and reflects a shader, logging why if it can't be used.
+fn load_shader(
+    device_table: &DeviceTable,
+    device: *mut vk::Device,
+    path: &Path,
+) -> Result<(ShaderModule, Reflection), MaterialError> {
+    let file = crate::assets::read(path).ok_or_else(|| {
+        error!("Failed to read shader {:?}", path);
+        MaterialError::ShaderModule
+    })?;
+    let reflected = reflect::words(&file).and_then(|code| {
+        let reflection = reflect::reflect(&code)?;
+        Ok((code, reflection))
+    });
+    let (code, reflection) = reflected.map_err(|e| {
+        error!("Failed to reflect shader {:?}: {}", path, e);
+        MaterialError::ShaderModule
+    })?;
+
+    let module = ShaderModule::new(device_table, device, &code).ok_or_else(|| {
+        error!("Failed to create shader module from {:?}", path);
+        MaterialError::ShaderModule
+    })?;
+    return Ok((module, reflection));
+}
+
+/// A single range covering the push constants of both stages.
+fn push_constant_ranges(vertex: &Reflection, fragment: &Reflection) -> Vec<vk::PushConstantRange> {
+    let mut stage_flags = 0;
+    let mut size = 0;
+    for reflection in [vertex, fragment].iter() {
+        if reflection.push_constant_size != 0 {
+            stage_flags |= reflection.stage as vk::ShaderStageFlags;
+            size = size.max(reflection.push_constant_size);
+        }
+    }
+
+    if size == 0 {
+        return Vec::new();
+    }
+
+    return vec![vk::PushConstantRange {
+        stage_flags,
+        offset: 0,
+        size,
+    }];
+}
+
+/// Formats of the vertex shader inputs, after checking the fragment shader only reads outputs
+/// the vertex shader writes. Attributes are given consecutive locations from 0, so the inputs
+/// must be at those locations too.
+fn vertex_input_formats(
+    vertex: &Reflection,
+    fragment: &Reflection,
+) -> Result<Vec<vk::Format>, MaterialError> {
+    for input in fragment.inputs.iter() {
+        if !vertex.outputs.contains(input) {
+            return Err(MaterialError::InterfaceMismatch(input.location));
+        }
+    }
+
+    return vertex
+        .inputs
+        .iter()
+        .enumerate()
+        .map(|(location, input)| {
+            if input.location != location as u32 {
+                return Err(MaterialError::VertexLayoutMismatch);
+            }
+
+            let error = MaterialError::UnsupportedVertexInput(input.location);
+            input.ty.format().ok_or(error)
+        })
+        .collect();
+}
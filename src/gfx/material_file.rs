@@ -0,0 +1,277 @@
+//! Materials are described by text files of `key = value` lines, where `#` starts a comment. An
+//! additive glow for sprites looks like:
+//!
+//! ```text
+//! vertex = shaders/triangle.vert.spv
+//! fragment = shaders/triangle.frag.spv
+//! vertex_layout = vec2 vec2 vec4
+//! binding.0 = combined_image_sampler
+//! blend = additive
+//! cull = none
+//! ```
+//!
+//! `blend`, `cull`, `topology` and `polygon_mode` default to `alpha`, `back`, `triangle_list` and
+//! `fill`. Instanced materials also give an `instance_layout`, for the inputs following the vertex
+//! layout that are read once per instance from a second buffer. The layouts and bindings must
+//! match the shaders, and are checked against their reflection.
+
+use super::material::{BlendMode, PipelineState};
+use crate::ffi::vk;
+use std::path::PathBuf;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum DescriptionError {
+    /// A line that is neither a comment nor `key = value`
+    Syntax(usize),
+    UnknownKey(usize),
+    InvalidValue(usize),
+    DuplicateKey(usize),
+    MissingKey(&'static str),
+}
+
+impl std::fmt::Display for DescriptionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        return match self {
+            Self::Syntax(line) => write!(f, "line {}: expected `key = value`", line),
+            Self::UnknownKey(line) => write!(f, "line {}: unknown key", line),
+            Self::InvalidValue(line) => write!(f, "line {}: invalid value", line),
+            Self::DuplicateKey(line) => write!(f, "line {}: key is already set", line),
+            Self::MissingKey(key) => write!(f, "`{}` is required", key),
+        };
+    }
+}
+
+impl std::error::Error for DescriptionError {}
+
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct MaterialDescription {
+    pub(crate) vertex: PathBuf,
+    pub(crate) fragment: PathBuf,
+    /// Formats of the interleaved vertex inputs, in order of location
+    pub(crate) vertex_layout: Vec<vk::Format>,
+    /// Formats of the interleaved per-instance inputs, at the locations after the vertex inputs
+    pub(crate) instance_layout: Vec<vk::Format>,
+    /// Descriptor types in set 0, sorted by binding
+    pub(crate) bindings: Vec<(u32, vk::DescriptorType)>,
+    pub(crate) state: PipelineState,
+}
+
+impl MaterialDescription {
+    pub(crate) fn parse(text: &str) -> Result<Self, DescriptionError> {
+        let mut vertex = None;
+        let mut fragment = None;
+        let mut vertex_layout = Vec::new();
+        let mut instance_layout = Vec::new();
+        let mut bindings = Vec::new();
+        let mut state = PipelineState::default();
+        let mut keys: Vec<&str> = Vec::new();
+
+        for (i, line) in text.lines().enumerate() {
+            let number = i + 1;
+            let line = line.split('#').next().unwrap().trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            let (key, value) = match line.find('=') {
+                Some(i) => (line[..i].trim(), line[i + 1..].trim()),
+                None => return Err(DescriptionError::Syntax(number)),
+            };
+            if keys.contains(&key) {
+                return Err(DescriptionError::DuplicateKey(number));
+            }
+            keys.push(key);
+
+            let invalid = DescriptionError::InvalidValue(number);
+            if value.is_empty() {
+                return Err(invalid);
+            }
+
+            match key {
+                "vertex" => vertex = Some(PathBuf::from(value)),
+                "fragment" => fragment = Some(PathBuf::from(value)),
+                "vertex_layout" => {
+                    let formats: Option<_> = value.split_whitespace().map(parse_format).collect();
+                    vertex_layout = formats.ok_or(invalid)?;
+                }
+                "instance_layout" => {
+                    let formats: Option<_> = value.split_whitespace().map(parse_format).collect();
+                    instance_layout = formats.ok_or(invalid)?;
+                }
+                "blend" => state.blend = parse_blend_mode(value).ok_or(invalid)?,
+                "cull" => state.cull_mode = parse_cull_mode(value).ok_or(invalid)?,
+                "topology" => state.topology = parse_topology(value).ok_or(invalid)?,
+                "polygon_mode" => state.polygon_mode = parse_polygon_mode(value).ok_or(invalid)?,
+                _ if key.starts_with("binding.") => {
+                    let binding = key["binding.".len()..]
+                        .parse()
+                        .map_err(|_| DescriptionError::UnknownKey(number))?;
+                    let descriptor_type = parse_descriptor_type(value).ok_or(invalid)?;
+                    bindings.push((binding, descriptor_type));
+                }
+                _ => return Err(DescriptionError::UnknownKey(number)),
+            }
+        }
+
+        bindings.sort_by_key(|&(binding, _)| binding);
+        let description = Self {
+            vertex: vertex.ok_or(DescriptionError::MissingKey("vertex"))?,
+            fragment: fragment.ok_or(DescriptionError::MissingKey("fragment"))?,
+            vertex_layout,
+            instance_layout,
+            bindings,
+            state,
+        };
+
+        return Ok(description);
+    }
+}
+
+fn parse_blend_mode(value: &str) -> Option<BlendMode> {
+    return match value {
+        "opaque" => Some(BlendMode::Opaque),
+        "alpha" => Some(BlendMode::Alpha),
+        "additive" => Some(BlendMode::Additive),
+        "multiply" => Some(BlendMode::Multiply),
+        "premultiplied_alpha" => Some(BlendMode::PremultipliedAlpha),
+        _ => None,
+    };
+}
+
+fn parse_cull_mode(value: &str) -> Option<vk::CullModeBits> {
+    return match value {
+        "none" => Some(vk::CullModeBits::None),
+        "front" => Some(vk::CullModeBits::Front),
+        "back" => Some(vk::CullModeBits::Back),
+        _ => None,
+    };
+}
+
+fn parse_descriptor_type(value: &str) -> Option<vk::DescriptorType> {
+    return match value {
+        "sampler" => Some(vk::DescriptorType::Sampler),
+        "combined_image_sampler" => Some(vk::DescriptorType::CombinedImageSampler),
+        "sampled_image" => Some(vk::DescriptorType::SamplerImage),
+        "storage_image" => Some(vk::DescriptorType::StorageImage),
+        "uniform_buffer" => Some(vk::DescriptorType::UniformBuffer),
+        "storage_buffer" => Some(vk::DescriptorType::StorageBuffer),
+        _ => None,
+    };
+}
+
+/// Vertex inputs are named by their GLSL types.
+fn parse_format(value: &str) -> Option<vk::Format> {
+    use vk::Format::*;
+
+    return match value {
+        "float" => Some(R32SFLOAT),
+        "vec2" => Some(R32G32SFLOAT),
+        "vec3" => Some(R32G32B32SFLOAT),
+        "vec4" => Some(R32G32B32A32SFLOAT),
+        "int" => Some(R32SINT),
+        "ivec2" => Some(R32G32SINT),
+        "ivec3" => Some(R32G32B32SINT),
+        "ivec4" => Some(R32G32B32A32SINT),
+        "uint" => Some(R32UINT),
+        "uvec2" => Some(R32G32UINT),
+        "uvec3" => Some(R32G32B32UINT),
+        "uvec4" => Some(R32G32B32A32UINT),
+        _ => None,
+    };
+}
+
+fn parse_polygon_mode(value: &str) -> Option<vk::PolygonMode> {
+    return match value {
+        "fill" => Some(vk::PolygonMode::Fill),
+        "line" => Some(vk::PolygonMode::Line),
+        "point" => Some(vk::PolygonMode::Point),
+        _ => None,
+    };
+}
+
+fn parse_topology(value: &str) -> Option<vk::PrimitiveTopology> {
+    return match value {
+        "point_list" => Some(vk::PrimitiveTopology::PointList),
+        "line_list" => Some(vk::PrimitiveTopology::LineList),
+        "line_strip" => Some(vk::PrimitiveTopology::LineStrip),
+        "triangle_list" => Some(vk::PrimitiveTopology::TriangleList),
+        "triangle_strip" => Some(vk::PrimitiveTopology::TriangleStrip),
+        _ => None,
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_description() {
+        let text = "
+            # Debug outline
+            vertex = shaders/triangle.vert.spv
+            fragment = shaders/triangle.frag.spv  # shared with sprites
+            vertex_layout = vec2 vec2 vec4
+            instance_layout = vec4 uint
+            binding.1 = uniform_buffer
+            binding.0 = combined_image_sampler
+            blend = premultiplied_alpha
+            cull = none
+            topology = line_strip
+            polygon_mode = line
+        ";
+
+        let expected = MaterialDescription {
+            vertex: PathBuf::from("shaders/triangle.vert.spv"),
+            fragment: PathBuf::from("shaders/triangle.frag.spv"),
+            vertex_layout: vec![
+                vk::Format::R32G32SFLOAT,
+                vk::Format::R32G32SFLOAT,
+                vk::Format::R32G32B32A32SFLOAT,
+            ],
+            instance_layout: vec![vk::Format::R32G32B32A32SFLOAT, vk::Format::R32UINT],
+            bindings: vec![
+                (0, vk::DescriptorType::CombinedImageSampler),
+                (1, vk::DescriptorType::UniformBuffer),
+            ],
+            state: PipelineState {
+                blend: BlendMode::PremultipliedAlpha,
+                cull_mode: vk::CullModeBits::None,
+                topology: vk::PrimitiveTopology::LineStrip,
+                polygon_mode: vk::PolygonMode::Line,
+            },
+        };
+        assert_eq!(MaterialDescription::parse(text), Ok(expected));
+    }
+
+    #[test]
+    fn parse_errors() {
+        let parse = |text: &str| MaterialDescription::parse(text).unwrap_err();
+        let shaders = "vertex = a.spv\nfragment = b.spv\n";
+
+        assert_eq!(parse("vertex a.spv"), DescriptionError::Syntax(1));
+        assert_eq!(
+            parse("fragment = b.spv"),
+            DescriptionError::MissingKey("vertex")
+        );
+        assert_eq!(
+            parse(&format!("{}colour = red", shaders)),
+            DescriptionError::UnknownKey(3)
+        );
+        assert_eq!(
+            parse(&format!("{}binding.x = sampler", shaders)),
+            DescriptionError::UnknownKey(3)
+        );
+        assert_eq!(
+            parse(&format!("{}blend = subtract", shaders)),
+            DescriptionError::InvalidValue(3)
+        );
+        assert_eq!(
+            parse(&format!("{}vertex_layout = vec2 mat4", shaders)),
+            DescriptionError::InvalidValue(3)
+        );
+        assert_eq!(
+            parse(&format!("{}vertex = c.spv", shaders)),
+            DescriptionError::DuplicateKey(3)
+        );
+    }
+}
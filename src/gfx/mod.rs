@@ -1,31 +1,74 @@
+mod allocator;
+mod hot_reload;
 mod loader;
 mod material;
+mod material_file;
+mod overlay;
+mod pipeline_cache;
+mod post;
+mod reflect;
 mod scene;
+mod shape;
 mod sprite;
-
-use material::Material;
-use scene::Scene;
-use sprite::Sprite;
+mod text;
+mod texture;
+mod upload;
+
+use allocator::{Allocation, AllocationKind, Allocator, MemoryStats};
+use hot_reload::ShaderWatcher;
+use material::{Material, MaterialError, PipelineContext};
+use overlay::{FrameStats, PerformanceOverlay};
+use pipeline_cache::PipelineCache;
+use post::PostProcess;
+use scene::{Draw, DrawKind, MaterialKey, Scene};
+use shape::{ShapeBuilder, PRIMITIVE_VERTEX_LENGTH};
+use sprite::{Sprite, SPRITE_INSTANCE_LENGTH, UNIT_QUAD};
+pub use sprite::{SpriteInstance, Transform};
+use text::{DistanceFieldStyle, Font, TEXT_VERTEX_LENGTH};
+use texture::{mip_levels, SamplerCache};
+use upload::UploadQueue;
 
 use crate::{
     ffi::vk,
-    math::Vector2,
+    font::{AtlasUpdate, GlyphCache, LayoutOptions, Region},
+    log::Level,
+    math::{Rectangle, Vector2, Vector4},
 };
 use std::{
-    ffi::CStr,
+    ffi::{c_void, CStr, CString},
     mem::MaybeUninit,
-    ops::Deref,
+    ops::{Deref, Range},
     path::Path,
     ptr::{copy_nonoverlapping, null, null_mut, NonNull},
+    time::Instant,
 };
 
+pub use crate::ffi::vk::PresentModeKHR as PresentMode;
+pub use crate::ffi::vk::SampleCountFlagBits as SampleCount;
+pub use crate::font::{GlyphFormat, TextAlignment};
 pub use loader::InstanceTable;
-use loader::{DeviceTable, Loader};
+use loader::{DebugUtilsTable, DeviceTable, Loader};
+pub use post::{PostEffect, PostPass};
+pub use shape::ShapeStyle;
+pub use text::{Outline, Shadow, TextStyle};
+pub use texture::{SamplerOptions, TextureOptions};
 
 const GRAPHICS: usize = 0;
 const PRESENTATION: usize = 1;
 const TRANSFER: usize = 2;
 
+/// Descriptor sets in each of a frame's descriptor pools. Another pool is added whenever a frame
+/// needs more.
+const DESCRIPTOR_POOL_SIZE: u32 = 64;
+
+/// Initial size of the vertex buffer, with room for a hundred thousand instanced sprites a frame.
+/// It grows when a frame needs more.
+const VERTEX_BUFFER_SIZE: vk::DeviceSize = 8 * 1024 * 1024;
+
+/// Larger batches of instances are split into several draws, each fitting the initial buffer.
+const MAX_INSTANCES_PER_DRAW: usize =
+    VERTEX_BUFFER_SIZE as usize / (SPRITE_INSTANCE_LENGTH * std::mem::size_of::<f32>());
+
 struct Device {
     queue_family_indices: [u32; 3],
     queues: Queues,
@@ -40,7 +83,8 @@ impl Device {
         physical_device: *mut vk::PhysicalDevice,
         surface: *mut vk::SurfaceKHR,
     ) -> (Self, DeviceTable) {
-        let queue_family_indices = select_queue_family_indices(table, surface, physical_device);
+        let queue_family_indices = select_queue_family_indices(table, surface, physical_device)
+            .expect("Physical device lacks the required queues!");
         let vk_device = create_device(table, physical_device, queue_family_indices);
 
         let device_table = loader.load_device_functions(vk_device);
@@ -69,6 +113,18 @@ impl Drop for Device {
     }
 }
 
+struct DebugUtilsMessengerEXT {
+    instance: *mut vk::Instance,
+    destroy_debug_utils_messenger_ext: vk::DestroyDebugUtilsMessengerEXT,
+    messenger: *mut vk::DebugUtilsMessengerEXT,
+}
+
+impl Drop for DebugUtilsMessengerEXT {
+    fn drop(&mut self) {
+        (self.destroy_debug_utils_messenger_ext)(self.instance, self.messenger, null());
+    }
+}
+
 struct Instance {
     destroy_instance: vk::DestroyInstance,
     instance: *mut vk::Instance,
@@ -95,27 +151,26 @@ struct ShaderModule {
 }
 
 impl ShaderModule {
-    fn from_path<P>(table: &DeviceTable, device: *mut vk::Device, path: P) -> Self
-    where
-        P: AsRef<Path>,
-    {
-        let file = std::fs::read(path).unwrap();
+    fn new(table: &DeviceTable, device: *mut vk::Device, code: &[u32]) -> Option<Self> {
         let info = vk::ShaderModuleCreateInfo {
             stype: vk::StructureType::ShaderModuleCreateInfo,
             next: null(),
             flags: 0,
-            code_size: file.len(),
-            code: file.as_ptr() as *const u32,
+            code_size: 4 * code.len(),
+            code: code.as_ptr(),
         };
 
         let mut shader_module = null_mut();
-        (table.create_shader_module)(device, &info, null(), &mut shader_module);
+        let result = (table.create_shader_module)(device, &info, null(), &mut shader_module);
+        if result != vk::Result::Success || shader_module.is_null() {
+            return None;
+        }
 
-        return Self {
+        return Some(Self {
             shader_module,
             device,
             destroy_shader_module: table.destroy_shader_module,
-        };
+        });
     }
 }
 
@@ -156,7 +211,7 @@ impl Drop for SurfaceKHR {
 /// A `DeviceMemory`-backed vulkan `Buffer`, Memory-Backed Buffer (MBB).
 struct MBB {
     buffer: *mut vk::Buffer,
-    memory: NonNull<vk::DeviceMemory>,
+    allocation: Allocation,
     size: vk::DeviceSize,
     flags: vk::MemoryPropertyFlags,
 }
@@ -165,7 +220,7 @@ impl MBB {
     fn create(
         instance_table: &InstanceTable,
         device_table: &DeviceTable,
-        physical_device: *mut vk::PhysicalDevice,
+        allocator: &mut Allocator,
         device: *mut vk::Device,
         size: vk::DeviceSize,
         usage: vk::BufferUsageFlags,
@@ -188,88 +243,90 @@ impl MBB {
         (device_table.get_buffer_memory_requirements)(device, buffer, requirements.as_mut_ptr());
         let requirements = unsafe { requirements.assume_init() };
 
-        let memory = allocate_memory(
-            instance_table,
-            device_table,
-            physical_device,
-            device,
-            requirements,
-            flags,
-        )
-        .expect("Failed to allocate memory!");
+        let allocation = allocator
+            .allocate(
+                instance_table,
+                device_table,
+                requirements,
+                flags,
+                AllocationKind::Linear,
+            )
+            .expect("Failed to allocate memory!");
 
-        (device_table.bind_buffer_memory)(device, buffer, memory.as_ptr(), 0);
+        (device_table.bind_buffer_memory)(device, buffer, allocation.memory, allocation.offset);
 
         let mbb = Self {
             buffer,
-            memory,
+            allocation,
             size,
             flags,
         };
         return mbb;
     }
 
-    fn fill(&self, table: &DeviceTable, device: *mut vk::Device, data: *const u8) {
-        debug_assert!(
-            ((self.flags & vk::MemoryPropertyFlagBits::HostCoherent as u32) != 0)
-                && ((self.flags & vk::MemoryPropertyFlagBits::HostVisible as u32) != 0)
-        );
-
-        let mut p = null_mut();
-        (table.map_memory)(device, self.memory.as_ptr(), 0, self.size, 0, &mut p);
-        unsafe {
-            copy_nonoverlapping(data, p as *mut u8, self.size as usize);
-        }
-        (table.unmap_memory)(device, self.memory.as_ptr());
+    fn fill(&self, data: *const u8) {
+        self.write_region(0, self.size, data);
     }
 
-    fn destroy(self, table: &DeviceTable, device: *mut vk::Device) {
-        (table.free_memory)(device, self.memory.as_ptr(), null());
+    fn destroy(self, table: &DeviceTable, device: *mut vk::Device, allocator: &mut Allocator) {
         (table.destroy_buffer)(device, self.buffer, null());
+        allocator.free(table, self.allocation);
     }
 
-    fn write_region(
-        &self,
-        table: &DeviceTable,
-        device: *mut vk::Device,
-        offset: vk::DeviceSize,
-        size: vk::DeviceSize,
-        data: *const u8,
-    ) {
+    /// Host visible memory stays mapped, so writes are plain copies.
+    fn write_region(&self, offset: vk::DeviceSize, size: vk::DeviceSize, data: *const u8) {
         debug_assert!(
             ((self.flags & vk::MemoryPropertyFlagBits::HostCoherent as u32) != 0)
                 && ((self.flags & vk::MemoryPropertyFlagBits::HostVisible as u32) != 0)
         );
+        // Small buffers share blocks, so writing past the end would corrupt their neighbours
+        assert!(
+            offset + size <= self.size,
+            "Writing {} bytes at offset {} overflows a buffer of {} bytes",
+            size,
+            offset,
+            self.size
+        );
 
-        let mut address = null_mut();
-        (table.map_memory)(device, self.memory.as_ptr(), offset, size, 0, &mut address);
         unsafe {
-            copy_nonoverlapping(data, address as *mut u8, size as usize);
+            let address = self.allocation.mapped.add(offset as usize);
+            copy_nonoverlapping(data, address, size as usize);
         }
-        (table.unmap_memory)(device, self.memory.as_ptr());
     }
 }
 
 struct MBI {
     image: NonNull<vk::Image>,
-    memory: NonNull<vk::DeviceMemory>,
+    allocation: Allocation,
 }
 
 impl MBI {
     fn create(
         instance_table: &InstanceTable,
         device_table: &DeviceTable,
-        physical_device: *mut vk::PhysicalDevice,
+        allocator: &mut Allocator,
         device: *mut vk::Device,
         format: vk::Format,
         extent: vk::Extent2D,
+        mip_levels: u32,
+        samples: vk::SampleCountFlagBits,
         tiling: vk::ImageTiling,
         usage: vk::ImageUsageFlags,
         layout: vk::ImageLayout,
         flags: vk::MemoryPropertyFlags,
     ) -> Self {
-        let image = create_image(device_table, device, format, extent, tiling, usage, layout)
-            .expect("Failed to create image!");
+        let image = create_image(
+            device_table,
+            device,
+            format,
+            extent,
+            mip_levels,
+            samples,
+            tiling,
+            usage,
+            layout,
+        )
+        .expect("Failed to create image!");
 
         let requirements = {
             let mut requirements = MaybeUninit::uninit();
@@ -281,19 +338,22 @@ impl MBI {
             unsafe { requirements.assume_init() }
         };
 
-        let memory = allocate_memory(
-            instance_table,
-            device_table,
-            physical_device,
-            device,
-            requirements,
-            flags,
-        )
-        .expect("Failed to allocate memory!");
+        let kind = match tiling {
+            vk::ImageTiling::Linear => AllocationKind::Linear,
+            _ => AllocationKind::Optimal,
+        };
+        let allocation = allocator
+            .allocate(instance_table, device_table, requirements, flags, kind)
+            .expect("Failed to allocate memory!");
 
-        (device_table.bind_image_memory)(device, image.as_ptr(), memory.as_ptr(), 0);
+        (device_table.bind_image_memory)(
+            device,
+            image.as_ptr(),
+            allocation.memory,
+            allocation.offset,
+        );
 
-        return Self { image, memory };
+        return Self { image, allocation };
     }
 }
 
@@ -339,10 +399,18 @@ impl PresentationSync {
 
 struct RenderTarget {
     extent: vk::Extent2D,
+    format: vk::Format,
+    present_mode: vk::PresentModeKHR,
+    samples: vk::SampleCountFlagBits,
     framebuffers: Box<[*mut vk::Framebuffer]>,
     views: Box<[*mut vk::ImageView]>,
     images: Box<[*mut vk::Image]>,
+    /// Rendered to instead of the swapchain image while multisampling, then resolved into it
+    multisampled: Option<(MBI, *mut vk::ImageView)>,
     render_pass: *mut vk::RenderPass,
+    /// Single-sampled, for the last post-processing pass
+    post_framebuffers: Box<[*mut vk::Framebuffer]>,
+    post_render_pass: *mut vk::RenderPass,
     swapchain: *mut vk::SwapchainKHR,
 }
 
@@ -350,14 +418,20 @@ impl RenderTarget {
     fn create(
         device_table: &DeviceTable,
         instance_table: &InstanceTable,
+        allocator: &mut Allocator,
         physical_device: *mut vk::PhysicalDevice,
         device: *mut vk::Device,
         surface: *mut vk::SurfaceKHR,
         window_extent: vk::Extent2D,
+        present_modes: &[vk::PresentModeKHR],
+        samples: vk::SampleCountFlagBits,
+        old_swapchain: *mut vk::SwapchainKHR,
     ) -> Self {
         let capabilities = get_capabilities(&instance_table, physical_device, surface);
         let image_extent = select_extent(capabilities, window_extent);
         let surface_format = get_swapchain_format(&instance_table, physical_device, surface);
+        let present_mode =
+            select_present_mode(&instance_table, physical_device, surface, present_modes);
         let swapchain = create_swapchain(
             &device_table,
             surface,
@@ -365,35 +439,122 @@ impl RenderTarget {
             capabilities,
             surface_format,
             image_extent,
+            present_mode,
+            old_swapchain,
         );
         let images = get_swapchain_images(&device_table, device, swapchain);
 
         let views: Box<[*mut vk::ImageView]> = images
             .iter()
-            .map(|&image| create_image_view(&device_table, device, image, surface_format.format))
+            .map(|&image| create_image_view(&device_table, device, image, surface_format.format, 1))
             .collect();
-        let render_pass = create_render_pass(&device_table, device, surface_format.format);
+        let multisampled = if samples != vk::SampleCountFlagBits::One {
+            let mbi = MBI::create(
+                instance_table,
+                device_table,
+                allocator,
+                device,
+                surface_format.format,
+                image_extent,
+                1,
+                samples,
+                vk::ImageTiling::Optimal,
+                vk::ImageUsageFlagBits::ColorAttachment as u32
+                    | vk::ImageUsageFlagBits::TransientAttachment as u32,
+                vk::ImageLayout::Undefined,
+                vk::MemoryPropertyFlagBits::DeviceLocal as u32,
+            );
+            let view = create_image_view(
+                &device_table,
+                device,
+                mbi.image.as_ptr(),
+                surface_format.format,
+                1,
+            );
+            Some((mbi, view))
+        } else {
+            None
+        };
+
+        let render_pass = create_render_pass(
+            &device_table,
+            device,
+            surface_format.format,
+            samples,
+            vk::ImageLayout::PresentSourceKHR,
+        );
         let framebuffers: Box<[*mut vk::Framebuffer]> = views
             .iter()
-            .map(|&view| create_framebuffer(&device_table, device, render_pass, view, image_extent))
+            .map(|&view| {
+                let attachments = match &multisampled {
+                    Some((_, multisampled_view)) => vec![*multisampled_view, view],
+                    None => vec![view],
+                };
+                create_framebuffer(
+                    &device_table,
+                    device,
+                    render_pass,
+                    &attachments,
+                    image_extent,
+                )
+            })
+            .collect();
+
+        let post_render_pass = create_render_pass(
+            &device_table,
+            device,
+            surface_format.format,
+            vk::SampleCountFlagBits::One,
+            vk::ImageLayout::PresentSourceKHR,
+        );
+        let post_framebuffers: Box<[*mut vk::Framebuffer]> = views
+            .iter()
+            .map(|&view| {
+                create_framebuffer(
+                    &device_table,
+                    device,
+                    post_render_pass,
+                    &[view],
+                    image_extent,
+                )
+            })
             .collect();
 
         return Self {
             extent: image_extent,
+            format: surface_format.format,
+            present_mode,
+            samples,
             framebuffers,
             views,
             images,
+            multisampled,
             render_pass,
+            post_framebuffers,
+            post_render_pass,
             swapchain,
         };
     }
 
-    pub fn destroy(&mut self, device_table: &DeviceTable, device: *mut vk::Device) {
+    pub fn destroy(
+        &mut self,
+        device_table: &DeviceTable,
+        allocator: &mut Allocator,
+        device: *mut vk::Device,
+    ) {
         self.framebuffers
             .iter()
+            .chain(self.post_framebuffers.iter())
             .for_each(|&f| (device_table.destroy_framebuffer)(device, f, null()));
 
         (device_table.destroy_render_pass)(device, self.render_pass, null());
+        (device_table.destroy_render_pass)(device, self.post_render_pass, null());
+
+        if let Some((mbi, view)) = self.multisampled.take() {
+            (device_table.destroy_image_view)(device, view, null());
+            (device_table.destroy_image)(device, mbi.image.as_ptr(), null());
+            allocator.free(device_table, mbi.allocation);
+        }
 
         self.views
             .iter()
@@ -408,8 +569,14 @@ struct PerFrameResources {
     command_pool: *mut vk::CommandPool,
     primary: *mut vk::CommandBuffer,
     secondaries: Vec<*mut vk::CommandBuffer>,
-    descriptor_pool: *mut vk::DescriptorPool,
+    /// Kept across frames once created, so a frame only adds pools when it needs more sets than
+    /// any before it
+    descriptor_pools: Vec<*mut vk::DescriptorPool>,
+    /// Index of the pool sets are allocated from, the ones before it are full
+    current_pool: usize,
     descriptor_sets: Vec<*mut vk::DescriptorSet>,
+    /// Staging buffers for texture updates recorded this frame, freed once the frame retires
+    staging: Vec<MBB>,
 }
 
 impl PerFrameResources {
@@ -436,14 +603,22 @@ impl PerFrameResources {
             count,
         )
         .expect("Failed to allocate secondary command buffers!");
-        let descriptor_sets = allocate_descriptor_sets(
-            device_table,
-            device,
-            self.descriptor_pool,
-            set_layout,
-            count,
-        )
-        .expect("Failed to allocate descriptor sets!");
+        let descriptor_sets = loop {
+            let created = self.current_pool == self.descriptor_pools.len();
+            if created {
+                let max_sets = DESCRIPTOR_POOL_SIZE.max(count as u32);
+                let pool = descriptor_pool_create(device_table, device, max_sets);
+                self.descriptor_pools.push(pool);
+            }
+
+            let pool = self.descriptor_pools[self.current_pool];
+            match allocate_descriptor_sets(device_table, device, pool, set_layout, count) {
+                Some(sets) => break sets,
+                // A new pool has room, so failing to allocate from it isn't fixed by another
+                None if created => panic!("Failed to allocate descriptor sets!"),
+                None => self.current_pool += 1,
+            }
+        };
 
         self.secondaries.extend_from_slice(&secondaries);
         self.descriptor_sets.extend_from_slice(&descriptor_sets);
@@ -456,34 +631,37 @@ impl PerFrameResources {
             allocate_command_buffer(table, device, command_pool, vk::CommandBufferLevel::Primary);
         let secondaries = Vec::new();
 
-        let descriptor_count = 4;
-        let pool_sizes = [vk::DescriptorPoolSize {
-            dtype: vk::DescriptorType::CombinedImageSampler,
-            descriptor_count,
-        }];
-        let descriptor_pool = descriptor_pool_create(table, device, descriptor_count, &pool_sizes);
+        let descriptor_pool = descriptor_pool_create(table, device, DESCRIPTOR_POOL_SIZE);
         let descriptor_sets = Vec::new();
 
         let res = Self {
             command_pool,
             primary,
             secondaries,
-            descriptor_pool,
+            descriptor_pools: vec![descriptor_pool],
+            current_pool: 0,
             descriptor_sets,
+            staging: Vec::new(),
         };
         return res;
     }
 
-    fn destroy(&mut self, table: &DeviceTable, device: *mut vk::Device) {
+    fn destroy(&mut self, table: &DeviceTable, device: *mut vk::Device, allocator: &mut Allocator) {
         (table.destroy_command_pool)(device, self.command_pool, null());
         self.primary = null_mut();
         self.secondaries.clear();
 
-        (table.destroy_descriptor_pool)(device, self.descriptor_pool, null());
+        for pool in self.descriptor_pools.drain(..) {
+            (table.destroy_descriptor_pool)(device, pool, null());
+        }
         self.descriptor_sets.clear();
+
+        self.staging
+            .drain(..)
+            .for_each(|staging| staging.destroy(table, device, allocator));
     }
 
-    fn reset(&mut self, table: &DeviceTable, device: *mut vk::Device) {
+    fn reset(&mut self, table: &DeviceTable, device: *mut vk::Device, allocator: &mut Allocator) {
         (table.reset_command_pool)(device, self.command_pool, 0);
         (table.free_command_buffers)(
             device,
@@ -495,16 +673,23 @@ impl PerFrameResources {
 
         // Resetting the descriptor pool returns all descriptor sets back to the pool, unlike
         // resetting a command pool!
-        (table.reset_descriptor_pool)(device, self.descriptor_pool, null());
+        for &pool in self.descriptor_pools.iter() {
+            (table.reset_descriptor_pool)(device, pool, null());
+        }
+        self.current_pool = 0;
         self.descriptor_sets.clear();
+
+        self.staging
+            .drain(..)
+            .for_each(|staging| staging.destroy(table, device, allocator));
     }
 }
 
 struct Texture {
     image: NonNull<vk::Image>,
     view: *mut vk::ImageView,
-    sampler: NonNull<vk::Sampler>,
-    memory: NonNull<vk::DeviceMemory>,
+    allocation: Option<Allocation>,
+    options: TextureOptions,
 }
 
 struct Queues {
@@ -544,20 +729,106 @@ impl Queues {
     }
 }
 
+/// Settings the renderer is created with.
+#[derive(Clone, Debug)]
+pub struct RendererConfig {
+    /// Present modes to use while vsync is enabled, most preferred first. Falls back to `Fifo`,
+    /// which every driver supports.
+    pub present_modes: Vec<PresentMode>,
+    /// Present modes to use while vsync is disabled, most preferred first. Also falls back to
+    /// `Fifo` if none are supported.
+    pub low_latency_present_modes: Vec<PresentMode>,
+    pub vsync: bool,
+    /// Forces a physical device, by index or by part of its name. Takes precedence over the
+    /// `THYROX_DEVICE` environment variable.
+    pub device: Option<String>,
+    /// Enables `VK_LAYER_KHRONOS_validation` if it is installed. On by default in debug builds.
+    pub validation: bool,
+    /// Watches the shader and material directories and rebuilds materials when their files
+    /// change, for use during development.
+    pub hot_reload: bool,
+    /// Outlines the triangles of every sprite, if the device can draw lines.
+    pub wireframe: bool,
+    /// Samples per pixel for anti-aliasing, usually `One`, `Two`, `Four` or `Eight`. Falls back to
+    /// fewer if the device doesn't support as many.
+    pub samples: SampleCount,
+    /// Full-screen effects applied to the scene in order, which can be changed between frames
+    /// with `post_pass`. All are disabled by default.
+    pub post_passes: Vec<PostPass>,
+}
+
+impl Default for RendererConfig {
+    fn default() -> Self {
+        return Self {
+            present_modes: vec![PresentMode::Mailbox, PresentMode::Fifo],
+            low_latency_present_modes: vec![PresentMode::Immediate, PresentMode::Mailbox],
+            vsync: true,
+            device: None,
+            validation: cfg!(debug_assertions),
+            hot_reload: false,
+            wireframe: false,
+            samples: SampleCount::One,
+            post_passes: PostEffect::ALL.iter().map(|&e| PostPass::new(e)).collect(),
+        };
+    }
+}
+
+impl RendererConfig {
+    fn preferred_present_modes(&self) -> Vec<PresentMode> {
+        if self.vsync {
+            return self.present_modes.clone();
+        }
+
+        return self.low_latency_present_modes.clone();
+    }
+}
+
 pub struct Renderer {
+    config: RendererConfig,
     frame_resources: Box<[PerFrameResources]>,
     scene: Scene,
+    fonts: Vec<Font>,
     sprites: Vec<Sprite>,
     textures: Vec<Texture>,
     vertex_buffer: MBB,
-    transfer_pool: *mut vk::CommandPool,
+    /// The unit quad instanced sprites are drawn with
+    quad_buffer: MBB,
+    allocator: Allocator,
+    uploads: UploadQueue,
+    samplers: SamplerCache,
     presentation_sync: PresentationSync,
+    pipeline_cache: PipelineCache,
+    shader_watcher: Option<ShaderWatcher>,
     material_sprite: Material,
+    material_instanced_sprite: Material,
+    material_primitive: Material,
     material_text: Material,
+    material_sdf_text: Material,
+    /// Single-sample variants drawing the overlay in the final pass after post-processing
+    material_overlay_primitive: Material,
+    material_overlay_text: Material,
+    material_overlay_sdf_text: Material,
+    /// `None` if the device can't draw lines
+    material_wireframe: Option<Material>,
+    /// Loaded from material files for drawing sprites
+    materials: Vec<Material>,
+    post: PostProcess,
+    /// Drawn over the scene while set
+    performance_overlay: Option<PerformanceOverlay>,
+    /// Counts from the last submitted frame, shown by the performance overlay
+    frame_stats: FrameStats,
+    frame_started: Instant,
     render_target: RenderTarget,
+    /// Size of the window's client area, which the swapchain is matched to
+    window_extent: vk::Extent2D,
+    /// Set when the swapchain no longer matches the surface and must be recreated
+    swapchain_outdated: bool,
+    /// Whether line and point polygon modes were enabled
+    non_solid_fill: bool,
     device: Device,
     physical_device: NonNull<vk::PhysicalDevice>,
     surface: SurfaceKHR,
+    _debug_messenger: Option<DebugUtilsMessengerEXT>,
     _instance: Instance,
     device_table: DeviceTable,
     instance_table: InstanceTable,
@@ -565,67 +836,118 @@ pub struct Renderer {
 }
 
 impl Renderer {
+    /// Returns `None` if no image could be acquired this frame, for example while the window is
+    /// minimized. The swapchain is recreated here whenever it has gone out of date.
     pub(crate) fn begin_scene(&mut self, r: f32, g: f32, b: f32) -> Option<usize> {
+        self.frame_started = Instant::now();
+        if let Some(overlay) = &mut self.performance_overlay {
+            overlay.record_frame(self.frame_started);
+        }
+
+        self.uploads
+            .poll(&self.device_table, &self.device, &mut self.allocator);
+        self.reload_shaders();
+
+        // Nothing can be presented to a minimized window
+        if self.window_extent.width == 0 || self.window_extent.height == 0 {
+            return None;
+        }
+
+        if self.swapchain_outdated {
+            self.recreate_render_target();
+        }
+
         let current_frame = self.presentation_sync.current_frame;
-        let resources = &mut self.frame_resources[current_frame];
         let image_acquired = self.presentation_sync.image_acquired[current_frame];
         let drawing_finished = self.presentation_sync.drawing_finished[current_frame];
 
-        let index = acquire_image(
+        let (index, result) = acquire_image(
             &self.device_table,
             *self.device,
             self.render_target.swapchain,
             100_000_000,
             Some(image_acquired),
             None,
-        )?;
+        );
+        match result {
+            vk::Result::Success => {}
+            // The image is still usable and the semaphore will be signalled, so draw this frame
+            vk::Result::SuboptimalKHR => self.swapchain_outdated = true,
+            vk::Result::ErrorOutOfDateKHR => {
+                self.swapchain_outdated = true;
+                return None;
+            }
+            _ => return None,
+        }
+
+        let resources = &mut self.frame_resources[current_frame];
 
         fence_wait_reset(&self.device_table, *self.device, drawing_finished);
-        resources.reset(&self.device_table, *self.device);
+        resources.reset(&self.device_table, *self.device, &mut self.allocator);
         command_buffer_begin_primary(
             &self.device_table,
             resources.primary,
             vk::CommandBufferUsageFlagBits::OneTimeSubmit as u32,
         );
 
+        for font in self.fonts.iter_mut() {
+            font.cache.tick();
+        }
+
+        // The render pass is only started in `end_scene`, after any texture updates the scene
+        // needs have been recorded.
         self.scene = Scene::default();
-        self.clear(index, r, g, b);
+        let clear_colour = self.output_colour(Vector4 {
+            x: r,
+            y: g,
+            z: b,
+            w: 1.0,
+        });
+        self.scene.clear_colour = [clear_colour.x, clear_colour.y, clear_colour.z];
+        self.scene.image_index = index;
         return Some(index as usize);
     }
 
-    pub(crate) fn clear(&self, index: u32, r: f32, g: f32, b: f32) {
-        let info = vk::RenderPassBeginInfo {
-            stype: vk::StructureType::RenderPassBeginInfo,
-            next: null(),
-            render_pass: self.render_target.render_pass,
-            framebuffer: self.render_target.framebuffers[index as usize],
-            render_area: vk::Rect2D {
-                offset: vk::Offset2D { x: 0, y: 0 },
-                extent: self.render_target.extent,
-            },
-            clear_value_count: 1,
-            clear_values: &vk::ClearValue {
-                color: vk::ClearColorValue {
-                    float: [r, g, b, 1.0],
-                },
-            },
+    /// Begins the scene's render pass, which renders to an intermediate target instead of the
+    /// swapchain image while post-processing.
+    pub(crate) fn clear(&mut self, post_processing: bool, r: f32, g: f32, b: f32) {
+        let (render_pass, framebuffer) = if post_processing {
+            let multisampled = self.render_target.multisampled.as_ref();
+            self.post.prepare(
+                &self.instance_table,
+                &self.device_table,
+                &mut self.allocator,
+                *self.device,
+                self.render_target.format,
+                self.render_target.extent,
+                self.render_target.samples,
+                multisampled.map(|(_, view)| *view),
+            )
+        } else {
+            let framebuffer = self.render_target.framebuffers[self.scene.image_index as usize];
+            (self.render_target.render_pass, framebuffer)
         };
-        (self.device_table.cmd_begin_render_pass)(
+
+        begin_render_pass(
+            &self.device_table,
             self.frame_resources[self.presentation_sync.current_frame].primary,
-            &info,
-            vk::SubpassContents::SecondaryCommandBuffers,
+            render_pass,
+            framebuffer,
+            self.render_target.extent,
+            [r, g, b, 1.0],
         );
     }
 
-    pub(crate) fn create_sprite_from_path<P>(&mut self, path: P) -> usize
+    pub(crate) fn create_sprite_from_path<P>(&mut self, path: P, options: TextureOptions) -> usize
     where
         P: AsRef<Path>,
     {
-        let (texture_index, width, height) = self.load_png_from_path(path);
+        let (texture_index, width, height) = self.load_png_from_path(path, options);
         let sprite = Sprite {
             texture_index,
             width,
             height,
+            material: None,
         };
 
         let index = self.sprites.len();
@@ -636,175 +958,763 @@ impl Renderer {
     pub(crate) fn deinit(mut self) {
         (self.device_table.device_wait_idle)(*self.device);
 
+        self.fonts.clear();
         self.sprites.clear();
         (0..self.textures.len()).for_each(|i| self.unload_texture(i));
 
         for frame_resource in self.frame_resources.iter_mut() {
-            frame_resource.destroy(&self.device_table, *self.device);
+            frame_resource.destroy(&self.device_table, *self.device, &mut self.allocator);
         }
 
-        self.vertex_buffer.destroy(&self.device_table, *self.device);
+        self.vertex_buffer
+            .destroy(&self.device_table, *self.device, &mut self.allocator);
+        self.quad_buffer
+            .destroy(&self.device_table, *self.device, &mut self.allocator);
+        self.uploads
+            .destroy(&self.device_table, &self.device, &mut self.allocator);
+        self.samplers.destroy(&self.device_table, *self.device);
+        self.post
+            .release(&self.device_table, &mut self.allocator, *self.device);
+        self.pipeline_cache
+            .destroy(&self.device_table, *self.device);
+        self.render_target
+            .destroy(&self.device_table, &mut self.allocator, *self.device);
+        debug!("GPU memory at shutdown: {}", self.allocator.stats());
+        self.allocator.destroy(&self.device_table);
         self.presentation_sync
             .destroy(&self.device_table, *self.device);
-
-        (self.device_table.destroy_command_pool)(*self.device, self.transfer_pool, null());
-        self.render_target.destroy(&self.device_table, *self.device);
     }
 
     pub(crate) fn draw(&mut self, sprite_index: usize, position: Vector2) {
+        let white = Vector4 {
+            x: 1.0,
+            y: 1.0,
+            z: 1.0,
+            w: 1.0,
+        };
+        self.draw_sprite(sprite_index, Transform::new(position), white, None);
+    }
+
+    /// `radius` is in pixels, so the circle stays round whatever the shape of the window.
+    pub(crate) fn draw_circle(&mut self, centre: Vector2, radius: f32, style: ShapeStyle) {
+        self.draw_shape(style, |builder, style| {
+            let points = builder.circle_points(centre, radius);
+            builder.polygon(&points, style);
+        });
+    }
+
+    pub(crate) fn draw_line(&mut self, start: Vector2, end: Vector2, style: ShapeStyle) {
+        self.draw_shape(style, |builder, style| {
+            builder.line(start, end, style.thickness, style.colour);
+        });
+    }
+
+    /// Filled polygons must be convex, while outlines can be any shape. The last point is joined
+    /// back to the first.
+    pub(crate) fn draw_polygon(&mut self, points: &[Vector2], style: ShapeStyle) {
+        self.draw_shape(style, |builder, style| builder.polygon(points, style));
+    }
+
+    pub(crate) fn draw_rect(&mut self, rectangle: Rectangle, style: ShapeStyle) {
+        let Rectangle {
+            left_top,
+            right_bottom,
+        } = rectangle;
+        let corners = [
+            left_top,
+            Vector2 {
+                x: right_bottom.x,
+                y: left_top.y,
+            },
+            right_bottom,
+            Vector2 {
+                x: left_top.x,
+                y: right_bottom.y,
+            },
+        ];
+        self.draw_polygon(&corners, style);
+    }
+
+    fn draw_shape<F>(&mut self, style: ShapeStyle, build: F)
+    where
+        F: FnOnce(&mut ShapeBuilder, &ShapeStyle),
+    {
+        let style = ShapeStyle {
+            colour: self.output_colour(style.colour),
+            ..style
+        };
+        let mut builder = ShapeBuilder::new(self.render_target.extent);
+        build(&mut builder, &style);
+        self.scene
+            .insert_primitives(&builder.data, PRIMITIVE_VERTEX_LENGTH, style.layer);
+    }
+
+    /// Draws `source`, a rectangle of the sprite's texture in pixels, or the whole texture if no
+    /// rectangle is given. The texture's colour is multiplied by `tint`.
+    pub(crate) fn draw_sprite(
+        &mut self,
+        sprite_index: usize,
+        transform: Transform,
+        tint: Vector4,
+        source: Option<Rectangle>,
+    ) {
+        self.draw_sprite_with_sampler(sprite_index, transform, tint, source, None);
+    }
+
+    /// Like `draw_sprite`, but sampling with `sampler` instead of the texture's own options.
+    pub(crate) fn draw_sprite_with_sampler(
+        &mut self,
+        sprite_index: usize,
+        transform: Transform,
+        tint: Vector4,
+        source: Option<Rectangle>,
+        sampler: Option<SamplerOptions>,
+    ) {
+        let tint = self.output_colour(tint);
+        let sprite = &self.sprites[sprite_index];
         let vertex_data =
-            self.sprites[sprite_index].generate_vertex_data(position, self.render_target.extent);
-        self.scene.insert_sprite(&vertex_data, sprite_index);
+            sprite.generate_vertex_data(transform, tint, source, self.render_target.extent);
+        self.scene.insert_sprite(
+            &vertex_data,
+            transform.layer,
+            sprite.material,
+            sprite.texture_index,
+            sampler,
+        );
+    }
+
+    /// Draws the sprite once for each instance in a single draw call, with the instanced sprite
+    /// material. The instances are drawn together on `layer`, ignoring their own layers.
+    pub(crate) fn draw_instances(
+        &mut self,
+        sprite_index: usize,
+        layer: i32,
+        instances: &[SpriteInstance],
+    ) {
+        let extent = self.render_target.extent;
+        let texture_index = self.sprites[sprite_index].texture_index;
+        for batch in instances.chunks(MAX_INSTANCES_PER_DRAW) {
+            let mut instance_data = Vec::with_capacity(batch.len() * SPRITE_INSTANCE_LENGTH);
+            for instance in batch {
+                let tint = self.output_colour(instance.tint);
+                let data = self.sprites[sprite_index].generate_instance_data(
+                    instance.transform,
+                    tint,
+                    instance.source,
+                    extent,
+                );
+                instance_data.extend_from_slice(&data);
+            }
+
+            self.scene.insert_instances(
+                &instance_data,
+                SPRITE_INSTANCE_LENGTH,
+                layer,
+                texture_index,
+                None,
+            );
+        }
+    }
+
+    pub(crate) fn draw_text(
+        &mut self,
+        font: usize,
+        text: &str,
+        position: Vector2,
+        colour: Vector4,
+        alignment: TextAlignment,
+    ) {
+        let style = TextStyle::new(colour);
+        self.draw_paragraph(font, text, position, style, alignment, None, 1.0);
+    }
+
+    /// Draws `text` broken into lines at newlines and, if `max_width` is given, word-wrapped to
+    /// that width. `position` marks the vertical centre of the block, `max_width` is in normalized
+    /// device coordinates and `line_spacing` scales the line height recommended by the font.
+    pub(crate) fn draw_paragraph(
+        &mut self,
+        font: usize,
+        text: &str,
+        position: Vector2,
+        style: TextStyle,
+        alignment: TextAlignment,
+        max_width: Option<f32>,
+        line_spacing: f32,
+    ) {
+        let mut style = style;
+        style.colour = self.output_colour(style.colour);
+        if let Some(outline) = &mut style.outline {
+            outline.colour = self.output_colour(outline.colour);
+        }
+        if let Some(shadow) = &mut style.shadow {
+            shadow.colour = self.output_colour(shadow.colour);
+        }
+
+        let extent = self.render_target.extent;
+        let options = LayoutOptions {
+            alignment,
+            max_width: max_width
+                .map(|width| (0.5 * width * extent.width as f32 / style.scale) as i32),
+            line_spacing,
+        };
+
+        let font = &mut self.fonts[font];
+        let layout = font.layout(text, options);
+        let vertex_data = Font::generate_vertex_data(
+            &layout,
+            position,
+            style.colour,
+            style.scale,
+            alignment,
+            extent,
+        );
+        let distance_field = match font.cache.format {
+            GlyphFormat::Coverage => None,
+            GlyphFormat::DistanceField => Some(DistanceFieldStyle::new(&style)),
+        };
+        self.scene.insert_glyphs(
+            &vertex_data,
+            TEXT_VERTEX_LENGTH,
+            style.layer,
+            font.texture_index,
+            distance_field,
+        );
+    }
+
+    /// Size of the block `draw_paragraph` would draw, in normalized device coordinates.
+    pub(crate) fn measure_text(
+        &mut self,
+        font: usize,
+        text: &str,
+        scale: f32,
+        max_width: Option<f32>,
+        line_spacing: f32,
+    ) -> Vector2 {
+        let extent = self.render_target.extent;
+        let options = LayoutOptions {
+            alignment: TextAlignment::Left,
+            max_width: max_width.map(|width| (0.5 * width * extent.width as f32 / scale) as i32),
+            line_spacing,
+        };
+
+        let layout = self.fonts[font].layout(text, options);
+        return Vector2 {
+            x: 2.0 * scale * layout.width as f32 / extent.width as f32,
+            y: 2.0 * scale * layout.height as f32 / extent.height as f32,
+        };
     }
 
     pub(crate) fn end_scene(&mut self) {
+        self.draw_performance_overlay();
+        self.update_font_atlases();
+
+        // Uploads go ahead of the frame, so textures loaded since the last frame can be drawn
+        self.uploads.submit(&self.device_table, &self.device);
+
+        let post_processing = !self.post.active(&self.config.post_passes).is_empty();
+        let [r, g, b] = self.scene.clear_colour;
+        self.clear(post_processing, r, g, b);
+
+        let size = self.scene.data.len() as vk::DeviceSize;
+        if self.vertex_buffer.size < size {
+            self.grow_vertex_buffer(size);
+        }
+
         let current_frame = self.presentation_sync.current_frame;
-        let resources = &mut self.frame_resources[current_frame];
-        let primary = resources.primary;
+        let primary = self.frame_resources[current_frame].primary;
 
         // Write the data into the vertex buffer
         self.vertex_buffer.write_region(
-            &self.device_table,
-            *self.device,
             0,
             self.scene.data.len() as vk::DeviceSize,
             self.scene.data.as_ptr(),
         );
 
-        // Allocate secondary command buffers and a corresponding descriptor set for each
-        let count = self.scene.sprite.len();
-        let indices = resources.allocate_descriptors_and_secondaries(
-            &self.device_table,
-            *self.device,
-            self.material_sprite.set_layout,
-            count,
+        self.scene.sort();
+        let draws = std::mem::take(&mut self.scene.draws);
+        let render_pass = self.render_target.render_pass;
+        let (scene_range, mut draw_calls) = self.record_draws(&draws, render_pass, false);
+        self.scene.draws = draws;
+
+        // The overlay goes over the finished image, so post-processing doesn't distort it
+        let overlay = std::mem::take(&mut self.scene.overlay);
+        let render_pass = if post_processing {
+            self.render_target.post_render_pass
+        } else {
+            self.render_target.render_pass
+        };
+        let (overlay_range, overlay_draw_calls) =
+            self.record_draws(&overlay, render_pass, post_processing);
+        self.scene.overlay = overlay;
+        draw_calls += overlay_draw_calls;
+
+        // Without post-processing the overlay is drawn last in the scene's render pass
+        let resources = &mut self.frame_resources[current_frame];
+        let executed = if post_processing {
+            scene_range
+        } else {
+            scene_range.start..overlay_range.end
+        };
+        (self.device_table.cmd_execute_commands)(
+            primary,
+            executed.len() as u32,
+            resources.secondaries[executed].as_ptr(),
         );
 
-        // Record all sprite commands
-        let sids = self.scene.sprite.iter();
-        let idxs = indices.iter();
+        (self.device_table.cmd_end_render_pass)(primary);
 
-        // Update the descriptor set and record the command buffer
-        for (sid, &idx) in sids.zip(idxs) {
-            descriptor_set_update_sampled_image(
+        if post_processing {
+            let sampler =
+                self.samplers
+                    .get(&self.device_table, *self.device, SamplerOptions::default());
+            let passes = self.post.active(&self.config.post_passes);
+            let overlay = resources.secondaries[overlay_range].to_vec();
+            self.post.record(
                 &self.device_table,
                 *self.device,
-                resources.descriptor_sets[idx],
-                &self.textures[self.sprites[sid.index].texture_index],
+                resources,
+                &passes,
+                sampler,
+                self.render_target.post_render_pass,
+                self.render_target.post_framebuffers[self.scene.image_index as usize],
+                &overlay,
             );
+            draw_calls += passes.len();
+        }
+
+        command_buffer_end_and_submit(
+            &self.device_table,
+            primary,
+            self.device.queues.graphics,
+            Some(self.presentation_sync.image_acquired[current_frame]),
+            vk::PipelineStageFlagBits::ColorAttachmentOutput as u32,
+            Some(self.presentation_sync.image_ready[current_frame]),
+            Some(self.presentation_sync.drawing_finished[current_frame]),
+        );
+
+        self.frame_stats = FrameStats {
+            cpu_time: self.frame_started.elapsed(),
+            draw_calls,
+            vertex_bytes: self.scene.data.len(),
+        };
+    }
+
+    /// Records a secondary command buffer with its own descriptor set for each batch of draws
+    /// sharing a material, texture and sampler, to be executed in `render_pass`. Primitives and text are drawn with single-sample pipelines if
+    /// `single_sample` is set, for the final render pass after post-processing. Returns the range
+    /// of the frame's secondaries that were recorded and the number of draw calls.
+    fn record_draws(
+        &mut self,
+        draws: &[Draw],
+        render_pass: *mut vk::RenderPass,
+        single_sample: bool,
+    ) -> (Range<usize>, usize) {
+        let resources = &mut self.frame_resources[self.presentation_sync.current_frame];
+        let first = resources.secondaries.len();
+        let show_wireframe = self.config.wireframe;
+        let mut draw_calls = 0;
+        for batch in scene::batches(draws) {
+            let draw = &draws[batch.start];
+            let material = match (draw.material, single_sample) {
+                (MaterialKey::Sprite(Some(material)), _) => &self.materials[material],
+                (MaterialKey::Sprite(None), _) => &self.material_sprite,
+                (MaterialKey::InstancedSprite, _) => &self.material_instanced_sprite,
+                (MaterialKey::Primitive, false) => &self.material_primitive,
+                (MaterialKey::Primitive, true) => &self.material_overlay_primitive,
+                (MaterialKey::Text, false) => &self.material_text,
+                (MaterialKey::Text, true) => &self.material_overlay_text,
+                (MaterialKey::DistanceFieldText, false) => &self.material_sdf_text,
+                (MaterialKey::DistanceFieldText, true) => &self.material_overlay_sdf_text,
+            };
+            let idx = resources.allocate_descriptors_and_secondaries(
+                &self.device_table,
+                *self.device,
+                material.set_layout,
+                1,
+            )[0];
+
+            if let Some(texture_index) = draw.texture_index {
+                let texture = &self.textures[texture_index];
+                let sampler = draw.sampler.unwrap_or(texture.options.sampler);
+                descriptor_set_update_sampled_image(
+                    &self.device_table,
+                    *self.device,
+                    resources.descriptor_sets[idx],
+                    texture.view,
+                    self.samplers.get(&self.device_table, *self.device, sampler),
+                );
+            }
 
             let secondary = resources.secondaries[idx];
             command_buffer_begin_secondary(
                 &self.device_table,
                 secondary,
-                self.render_target.render_pass,
+                render_pass,
                 vk::CommandBufferUsageFlagBits::OneTimeSubmit as u32,
             );
             set_scissor_and_viewport(&self.device_table, secondary, self.render_target.extent);
-            bind_graphics_pipeline(&self.device_table, secondary, self.material_sprite.pipeline);
-            bind_sampled_image_descriptor(
-                &self.device_table,
-                secondary,
-                self.material_sprite.pipeline_layout,
-                resources.descriptor_sets[idx],
-            );
-            bind_vertex_buffer(
-                &self.device_table,
-                secondary,
-                &self.vertex_buffer,
-                sid.offset as vk::DeviceSize,
-            );
-            (self.device_table.cmd_draw)(secondary, 6, 1, 0, 0);
+            bind_graphics_pipeline(&self.device_table, secondary, material.pipeline);
+            if draw.texture_index.is_some() {
+                bind_sampled_image_descriptor(
+                    &self.device_table,
+                    secondary,
+                    material.pipeline_layout,
+                    resources.descriptor_sets[idx],
+                );
+            }
+
+            for draw in &draws[batch] {
+                bind_vertex_buffer(
+                    &self.device_table,
+                    secondary,
+                    &self.vertex_buffer,
+                    draw.offset as vk::DeviceSize,
+                );
+                draw_calls += 1;
+
+                match &draw.kind {
+                    DrawKind::Sprite => {
+                        (self.device_table.cmd_draw)(secondary, 6, 1, 0, 0);
+                        let wireframe = self.material_wireframe.as_ref().filter(|_| show_wireframe);
+                        if let Some(wireframe) = wireframe {
+                            bind_graphics_pipeline(
+                                &self.device_table,
+                                secondary,
+                                wireframe.pipeline,
+                            );
+                            (self.device_table.cmd_draw)(secondary, 6, 1, 0, 0);
+                            bind_graphics_pipeline(
+                                &self.device_table,
+                                secondary,
+                                material.pipeline,
+                            );
+                            draw_calls += 1;
+                        }
+                    }
+                    DrawKind::Instances { count } => {
+                        // The instances take the place of the vertices, which are the shared quad
+                        let buffers = [self.quad_buffer.buffer, self.vertex_buffer.buffer];
+                        let offsets = [0, draw.offset as vk::DeviceSize];
+                        (self.device_table.cmd_bind_vertex_buffers)(
+                            secondary,
+                            0,
+                            2,
+                            buffers.as_ptr(),
+                            offsets.as_ptr(),
+                        );
+                        (self.device_table.cmd_draw)(secondary, 6, *count as u32, 0, 0);
+                    }
+                    DrawKind::Primitives { num_vertices } => {
+                        (self.device_table.cmd_draw)(secondary, *num_vertices as u32, 1, 0, 0);
+                    }
+                    DrawKind::Glyphs {
+                        num_vertices,
+                        distance_field,
+                    } => {
+                        if let Some(style) = distance_field {
+                            (self.device_table.cmd_push_constants)(
+                                secondary,
+                                material.pipeline_layout,
+                                vk::ShaderStageFlagBits::Fragment as u32,
+                                0,
+                                std::mem::size_of::<DistanceFieldStyle>() as u32,
+                                style as *const DistanceFieldStyle as *const _,
+                            );
+                        }
+                        (self.device_table.cmd_draw)(secondary, *num_vertices as u32, 1, 0, 0);
+                    }
+                }
+            }
             (self.device_table.end_command_buffer)(secondary);
         }
 
-        // Recording glyph draw
-        let glyph_indices = resources.allocate_descriptors_and_secondaries(
+        return (first..resources.secondaries.len(), draw_calls);
+    }
+
+    /// Replaces the vertex buffer with one of at least `size` bytes, once frames in flight are done
+    /// reading the old one.
+    fn grow_vertex_buffer(&mut self, size: vk::DeviceSize) {
+        let size = size.next_power_of_two();
+        info!("Growing the vertex buffer to {} MiB", size / (1024 * 1024));
+        (self.device_table.device_wait_idle)(*self.device);
+
+        let vertex_buffer = MBB::create(
+            &self.instance_table,
             &self.device_table,
+            &mut self.allocator,
             *self.device,
-            self.material_text.set_layout,
-            self.scene.glyphs.len(),
+            size,
+            vk::BufferUsageFlagBits::VertexBuffer as u32,
+            vk::MemoryPropertyFlagBits::HostCoherent as u32
+                | vk::MemoryPropertyFlagBits::HostVisible as u32,
         );
+        std::mem::replace(&mut self.vertex_buffer, vertex_buffer).destroy(
+            &self.device_table,
+            *self.device,
+            &mut self.allocator,
+        );
+    }
 
-        let gids = self.scene.glyphs.iter();
-        let idxs = glyph_indices.iter();
-        for (gid, &idx) in gids.zip(idxs) {
-            descriptor_set_update_sampled_image(
-                &self.device_table,
-                *self.device,
-                resources.descriptor_sets[idx],
-                &self.textures[gid.texture_index],
-            );
+    /// Adds the performance overlay, if shown, to the scene's overlay in the top-left corner. The
+    /// counts are from the previous frame, as this one's aren't known until it has been recorded.
+    fn draw_performance_overlay(&mut self) {
+        let overlay = match &self.performance_overlay {
+            Some(overlay) => overlay,
+            None => return,
+        };
 
-            let secondary = resources.secondaries[idx];
-            command_buffer_begin_secondary(
-                &self.device_table,
-                secondary,
-                self.render_target.render_pass,
-                vk::CommandBufferUsageFlagBits::OneTimeSubmit as u32,
-            );
+        let allocations = self.textures.iter().filter_map(|t| t.allocation.as_ref());
+        let texture_count = allocations.clone().count();
+        let texture_bytes = allocations.map(|allocation| allocation.size).sum();
+        let text = overlay.text(&self.frame_stats, texture_count, texture_bytes);
+        let frame_times: Vec<f32> = overlay.frame_times().collect();
+        let font = overlay.font;
+
+        let white = Vector4 {
+            x: 1.0,
+            y: 1.0,
+            z: 1.0,
+            w: 1.0,
+        };
+        let margin = 0.02;
+        let graph_height = 0.2;
+        let text_size = self.measure_text(font, &text, 1.0, None, 1.0);
+        let width = text_size.x.max(0.5);
+        let left = -1.0 + 2.0 * margin;
+        let top = 1.0 - 2.0 * margin;
+        let graph_bottom = top - text_size.y - margin - graph_height;
+
+        // Drawn into an empty list of draws, which is then moved into the overlay
+        let scene = std::mem::take(&mut self.scene.draws);
+
+        let background = ShapeStyle::filled(Vector4 {
+            x: 0.0,
+            y: 0.0,
+            z: 0.0,
+            w: 0.6,
+        });
+        let panel = Rectangle {
+            left_top: Vector2 {
+                x: left - margin,
+                y: top + margin,
+            },
+            right_bottom: Vector2 {
+                x: left + width + margin,
+                y: graph_bottom - margin,
+            },
+        };
+        self.draw_rect(panel, background);
 
-            set_scissor_and_viewport(&self.device_table, secondary, self.render_target.extent);
-            bind_graphics_pipeline(&self.device_table, secondary, self.material_text.pipeline);
-            bind_sampled_image_descriptor(
-                &self.device_table,
-                secondary,
-                self.material_text.pipeline_layout,
-                resources.descriptor_sets[idx],
-            );
+        let style = TextStyle::new(white);
+        let position = Vector2 {
+            x: left,
+            y: top - 0.5 * text_size.y,
+        };
+        self.draw_paragraph(font, &text, position, style, TextAlignment::Left, None, 1.0);
+
+        // Frame times are plotted up to 1/30 of a second, with a line marking 1/60
+        let graph_y = |seconds: f32| graph_bottom + graph_height * (seconds * 30.0).min(1.0);
+        let target = ShapeStyle::outlined(
+            Vector4 {
+                x: 0.5,
+                y: 0.5,
+                z: 0.5,
+                w: 1.0,
+            },
+            1.0,
+        );
+        let target_y = graph_y(1.0 / 60.0);
+        self.draw_line(
+            Vector2 {
+                x: left,
+                y: target_y,
+            },
+            Vector2 {
+                x: left + width,
+                y: target_y,
+            },
+            target,
+        );
 
-            bind_vertex_buffer(
-                &self.device_table,
-                secondary,
-                &self.vertex_buffer,
-                gid.offset as vk::DeviceSize,
-            );
-            (self.device_table.cmd_draw)(secondary, gid.num_vertices as u32, 1, 0, 0);
-            (self.device_table.end_command_buffer)(secondary);
+        let step = width / (PerformanceOverlay::HISTORY - 1) as f32;
+        let points: Vec<_> = frame_times
+            .iter()
+            .enumerate()
+            .map(|(i, &seconds)| Vector2 {
+                x: left + step * i as f32,
+                y: graph_y(seconds),
+            })
+            .collect();
+        let graph = ShapeStyle::outlined(
+            Vector4 {
+                x: 0.3,
+                y: 1.0,
+                z: 0.3,
+                w: 1.0,
+            },
+            1.5,
+        );
+        self.draw_shape(graph, |builder, style| {
+            for segment in points.windows(2) {
+                builder.line(segment[0], segment[1], style.thickness, style.colour);
+            }
+        });
+
+        self.scene.overlay = std::mem::replace(&mut self.scene.draws, scene);
+    }
+
+    /// Uploads glyphs rasterized since the last frame. Must be called before the render pass is
+    /// started, as the copies are recorded into the frame's primary command buffer.
+    fn update_font_atlases(&mut self) {
+        for i in 0..self.fonts.len() {
+            let texture_index = self.fonts[i].texture_index;
+            match self.fonts[i].cache.take_update() {
+                AtlasUpdate::None => {}
+                AtlasUpdate::Regions(regions) => {
+                    let atlas = self.fonts[i].cache.atlas();
+                    let data: Box<[Box<[u8]>]> = regions
+                        .iter()
+                        .map(|&region| atlas.region_data(region))
+                        .collect();
+                    self.update_texture_regions(texture_index, &regions, &data);
+                }
+                AtlasUpdate::Resized => {
+                    let atlas = self.fonts[i].cache.atlas().clone();
+                    self.replace_texture(
+                        texture_index,
+                        atlas.width,
+                        atlas.height,
+                        (atlas.width * atlas.height) as vk::DeviceSize,
+                        &atlas.data,
+                        vk::Format::R8UNORM,
+                    );
+                }
+            }
         }
+    }
 
-        // Execute all recorder secondary command buffers
-        (self.device_table.cmd_execute_commands)(
-            primary,
-            resources.secondaries.len() as u32,
-            resources.secondaries.as_ptr(),
+    /// Records copies of tightly packed `data` into `regions` of a single-channel texture. The
+    /// copies execute on the graphics queue ahead of the frame's render pass.
+    fn update_texture_regions(&mut self, index: usize, regions: &[Region], data: &[Box<[u8]>]) {
+        let size: usize = data.iter().map(|d| d.len()).sum();
+        if size == 0 {
+            return;
+        }
+
+        let staging = MBB::create(
+            &self.instance_table,
+            &self.device_table,
+            &mut self.allocator,
+            *self.device,
+            size as vk::DeviceSize,
+            vk::BufferUsageFlagBits::TransferSource as u32,
+            vk::MemoryPropertyFlagBits::HostCoherent as u32
+                | vk::MemoryPropertyFlagBits::HostVisible as u32,
         );
 
-        (self.device_table.cmd_end_render_pass)(primary);
+        let mut offset = 0;
+        let copies: Box<[vk::BufferImageCopy]> = regions
+            .iter()
+            .zip(data.iter())
+            .map(|(region, data)| {
+                staging.write_region(offset, data.len() as vk::DeviceSize, data.as_ptr());
+
+                let copy = vk::BufferImageCopy {
+                    buffer_offset: offset,
+                    buffer_row_length: region.width,
+                    buffer_image_height: region.height,
+                    image_subresource: vk::ImageSubresourceLayers {
+                        aspect_mask: vk::ImageAspectFlagBits::Color as u32,
+                        mip_level: 0,
+                        base_array_layer: 0,
+                        layer_count: 1,
+                    },
+                    image_offset: (region.x as i32, region.y as i32, 0).into(),
+                    image_extent: (region.width, region.height, 1).into(),
+                };
+                offset += data.len() as vk::DeviceSize;
+                copy
+            })
+            .collect();
 
-        command_buffer_end_and_submit(
+        let resources = &mut self.frame_resources[self.presentation_sync.current_frame];
+        let image = self.textures[index].image.as_ptr();
+        let barrier = ImageMemoryBarrier {
+            src_access_mask: vk::AccessFlagBits::ShaderRead as u32,
+            dst_access_mask: vk::AccessFlagBits::TransferWrite as u32,
+            old_layout: vk::ImageLayout::ShaderReadOnlyOptimal,
+            new_layout: vk::ImageLayout::TransferDestinationOptimal,
+            src_queue_family_index: vk::QUEUE_FAMILY_IGNORED,
+            dst_queue_family_index: vk::QUEUE_FAMILY_IGNORED,
+        };
+        record_image_memory_barrier(
             &self.device_table,
-            primary,
-            self.device.queues.graphics,
-            Some(self.presentation_sync.image_acquired[current_frame]),
-            vk::PipelineStageFlagBits::ColorAttachmentOutput as u32,
-            Some(self.presentation_sync.image_ready[current_frame]),
-            Some(self.presentation_sync.drawing_finished[current_frame]),
+            resources.primary,
+            vk::PipelineStageFlagBits::FragmentShader as u32,
+            vk::PipelineStageFlagBits::Transfer as u32,
+            barrier,
+            image,
+        );
+
+        (self.device_table.cmd_copy_buffer_to_image)(
+            resources.primary,
+            staging.buffer,
+            image,
+            vk::ImageLayout::TransferDestinationOptimal,
+            copies.len() as u32,
+            copies.as_ptr(),
+        );
+
+        let barrier = ImageMemoryBarrier {
+            src_access_mask: vk::AccessFlagBits::TransferWrite as u32,
+            dst_access_mask: vk::AccessFlagBits::ShaderRead as u32,
+            old_layout: vk::ImageLayout::TransferDestinationOptimal,
+            new_layout: vk::ImageLayout::ShaderReadOnlyOptimal,
+            src_queue_family_index: vk::QUEUE_FAMILY_IGNORED,
+            dst_queue_family_index: vk::QUEUE_FAMILY_IGNORED,
+        };
+        record_image_memory_barrier(
+            &self.device_table,
+            resources.primary,
+            vk::PipelineStageFlagBits::Transfer as u32,
+            vk::PipelineStageFlagBits::FragmentShader as u32,
+            barrier,
+            image,
         );
+
+        resources.staging.push(staging);
     }
 
     pub fn sprite_half_dimensions(&self, sprite_index: usize) -> (f32, f32) {
         return self.sprites[sprite_index].pixels_to_ndc(self.render_target.extent);
     }
 
-    pub fn init(window: &crate::Window) -> Self {
+    pub fn init(window: &crate::Window, config: RendererConfig) -> Self {
         let mut loader = Loader::init();
 
         // Instance creation and instance function loading
-        let (instance, instance_table) = {
-            let vk_instance = create_instance(&loader).expect("Failed to create instance!");
+        let (instance, instance_table, debug_utils) = {
+            let (vk_instance, debug_utils) =
+                create_instance(&loader, config.validation).expect("Failed to create instance!");
             let instance_table = loader.load_instance_functions(vk_instance);
+            let debug_utils = if debug_utils {
+                Some(loader.load_debug_utils_functions(vk_instance))
+            } else {
+                None
+            };
             let instance = Instance {
                 destroy_instance: instance_table.destroy_instance,
                 instance: vk_instance,
             };
-            (instance, instance_table)
+            (instance, instance_table, debug_utils)
         };
 
-        let physical_device = select_physical_device(&instance_table, *instance)
-            .expect("Failed to select physical device!");
+        // Validation messages are forwarded to the log until the instance is destroyed
+        let debug_messenger = debug_utils
+            .as_ref()
+            .and_then(|table| create_debug_utils_messenger(table, *instance));
 
-        // Surface creation
+        // Surface creation. Devices are chosen by whether they can present to it.
         let surface = {
             let surface = window
                 .create_surface(&instance_table, *instance)
@@ -815,24 +1725,91 @@ impl Renderer {
                 surface,
             }
         };
+
+        let device_override = config
+            .device
+            .clone()
+            .or_else(|| std::env::var("THYROX_DEVICE").ok());
+        let physical_device = select_physical_device(
+            &instance_table,
+            *instance,
+            *surface,
+            device_override.as_deref(),
+        )
+        .expect("Failed to select physical device!");
         let (device, device_table) =
             Device::new(&loader, &instance_table, physical_device.as_ptr(), *surface);
         let _vulkan = loader.take_library();
+        let mut allocator = Allocator::new(&instance_table, physical_device.as_ptr(), *device);
 
         // Render target
+        let samples =
+            select_sample_count(&instance_table, physical_device.as_ptr(), config.samples);
+        if samples != config.samples {
+            warn!(
+                "{:?} samples per pixel aren't supported, using {:?}",
+                config.samples, samples
+            );
+        }
+        let window_extent = window.dimensions_inner().into();
         let render_target = RenderTarget::create(
             &device_table,
             &instance_table,
+            &mut allocator,
             physical_device.as_ptr(),
             *device,
             *surface,
-            window.dimensions_inner().into(),
+            window_extent,
+            &config.preferred_present_modes(),
+            samples,
+            null_mut(),
         );
 
-        let material_sprite =
-            Material::sprite(&device_table, *device, render_target.render_pass).unwrap();
-        let material_text =
-            Material::text(&device_table, *device, render_target.render_pass).unwrap();
+        let pipeline_cache = PipelineCache::new(
+            &instance_table,
+            &device_table,
+            physical_device.as_ptr(),
+            *device,
+        );
+        let non_solid_fill = supports_non_solid_fill(&instance_table, physical_device.as_ptr());
+        let context = PipelineContext {
+            render_pass: render_target.render_pass,
+            pipeline_cache: pipeline_cache.as_ptr(),
+            samples,
+            non_solid_fill,
+        };
+        let load = |path| Material::load(&device_table, *device, path, &context);
+        let material_sprite = load("materials/sprite.material").unwrap();
+        let material_instanced_sprite = load("materials/instanced_sprite.material").unwrap();
+        let material_primitive = load("materials/primitive.material").unwrap();
+        let material_text = load("materials/text.material").unwrap();
+        let material_sdf_text = load("materials/sdf_text.material").unwrap();
+        let material_wireframe = if non_solid_fill {
+            load("materials/wireframe.material")
+                .map_err(|e| warn!("Failed to load the wireframe material: {}", e))
+                .ok()
+        } else {
+            None
+        };
+        let post_context = PipelineContext {
+            render_pass: render_target.post_render_pass,
+            samples: vk::SampleCountFlagBits::One,
+            ..context
+        };
+        let post = PostProcess::new(&device_table, *device, &post_context);
+        let load = |path| Material::load(&device_table, *device, path, &post_context);
+        let material_overlay_primitive = load("materials/primitive.material").unwrap();
+        let material_overlay_text = load("materials/text.material").unwrap();
+        let material_overlay_sdf_text = load("materials/sdf_text.material").unwrap();
+        let shader_watcher = if config.hot_reload {
+            let watcher = ShaderWatcher::new();
+            if watcher.is_none() {
+                warn!("Failed to watch shaders, hot reload is disabled");
+            }
+            watcher
+        } else {
+            None
+        };
         let num_images = render_target.images.len();
         // Synchronization primitives required for presentation
         let presentation_sync = PresentationSync::create(&device_table, *device, num_images);
@@ -840,20 +1817,30 @@ impl Renderer {
         let vertex_buffer = MBB::create(
             &instance_table,
             &device_table,
-            physical_device.as_ptr(),
+            &mut allocator,
             *device,
-            5 * 1024 * 1024,
+            VERTEX_BUFFER_SIZE,
             vk::BufferUsageFlagBits::VertexBuffer as u32,
             vk::MemoryPropertyFlagBits::HostCoherent as u32
                 | vk::MemoryPropertyFlagBits::HostVisible as u32,
         );
-
-        let transfer_pool = create_command_pool(
+        let quad_buffer = MBB::create(
+            &instance_table,
             &device_table,
+            &mut allocator,
             *device,
-            0,
-            device.queue_family_indices[TRANSFER],
+            std::mem::size_of_val(&UNIT_QUAD) as vk::DeviceSize,
+            vk::BufferUsageFlagBits::VertexBuffer as u32,
+            vk::MemoryPropertyFlagBits::HostCoherent as u32
+                | vk::MemoryPropertyFlagBits::HostVisible as u32,
         );
+        quad_buffer.fill(UNIT_QUAD.as_ptr() as *const u8);
+
+        let uploads = UploadQueue::new(&instance_table, &device_table, &mut allocator, &device);
+        let samplers = SamplerCache::new(max_sampler_anisotropy(
+            &instance_table,
+            physical_device.as_ptr(),
+        ));
         let frame_resources: Box<[PerFrameResources]> = (0..num_images)
             .map(|_| {
                 PerFrameResources::create(
@@ -865,19 +1852,42 @@ impl Renderer {
             .collect();
 
         let renderer = Self {
+            config,
             frame_resources,
             scene: Scene::default(),
+            fonts: Vec::new(),
             sprites: Vec::new(),
             textures: Vec::new(),
             vertex_buffer,
-            transfer_pool,
+            quad_buffer,
+            allocator,
+            uploads,
+            samplers,
+            pipeline_cache,
+            shader_watcher,
             material_sprite,
+            material_instanced_sprite,
+            material_primitive,
             material_text,
+            material_sdf_text,
+            material_overlay_primitive,
+            material_overlay_text,
+            material_overlay_sdf_text,
+            material_wireframe,
+            materials: Vec::new(),
+            post,
+            performance_overlay: None,
+            frame_stats: FrameStats::default(),
+            frame_started: Instant::now(),
             presentation_sync,
             render_target,
+            window_extent,
+            swapchain_outdated: false,
+            non_solid_fill,
             device,
             physical_device,
             surface,
+            _debug_messenger: debug_messenger,
             _instance: instance,
             device_table,
             instance_table,
@@ -886,7 +1896,81 @@ impl Renderer {
         return renderer;
     }
 
-    pub fn load_png_from_path<P>(&mut self, path: P) -> (usize, u32, u32)
+    /// Opens the font at `path` with a glyph cache that rasterizes characters as they are drawn.
+    /// Returns a handle for use with `draw_text`.
+    pub fn load_font<P>(&mut self, path: P, size: u32, format: GlyphFormat) -> usize
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref();
+        let font = crate::assets::read(path).or_else(|| {
+            warn!("Failed to read font {:?}, using the fallback font", path);
+            crate::assets::read(crate::assets::FALLBACK_FONT)
+        });
+        let mut cache = font
+            .and_then(|font| GlyphCache::new(font, size, format))
+            .expect("Failed to load font!");
+
+        // Warm the cache with printable ASCII, so most text doesn't need an atlas update
+        (' '..='~').for_each(|c| {
+            cache.glyph(c);
+        });
+        cache.take_update();
+
+        // Atlases are updated a region at a time, which would leave mip levels stale
+        let atlas = cache.atlas();
+        let options = TextureOptions {
+            mipmaps: false,
+            ..TextureOptions::default()
+        };
+        let texture_index = self.load_texture(
+            atlas.width,
+            atlas.height,
+            (atlas.width * atlas.height) as vk::DeviceSize,
+            &atlas.data,
+            vk::Format::R8UNORM,
+            options,
+        );
+
+        let index = self.fonts.len();
+        self.fonts.push(Font {
+            texture_index,
+            cache,
+        });
+        return index;
+    }
+
+    /// Loads a material file for drawing sprites, or finds it among those already loaded. Its
+    /// shaders must take the same vertices and descriptors as the sprite material.
+    pub(crate) fn load_material<P>(&mut self, path: P) -> Result<usize, MaterialError>
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref();
+        if let Some(index) = self.materials.iter().position(|m| m.path() == path) {
+            return Ok(index);
+        }
+
+        let material = Material::load(
+            &self.device_table,
+            *self.device,
+            path,
+            &self.pipeline_context(),
+        )?;
+        let sprite = &self.material_sprite.description;
+        if material.description.vertex_layout != sprite.vertex_layout
+            || material.description.instance_layout != sprite.instance_layout
+            || material.description.bindings != sprite.bindings
+        {
+            return Err(MaterialError::NotSpriteCompatible);
+        }
+
+        let index = self.materials.len();
+        self.materials.push(material);
+        return Ok(index);
+    }
+
+    pub fn load_png_from_path<P>(&mut self, path: P, options: TextureOptions) -> (usize, u32, u32)
     where
         P: AsRef<Path>,
     {
@@ -896,7 +1980,8 @@ impl Renderer {
             height,
             (4 * width * height) as vk::DeviceSize,
             &pixels,
-            vk::Format::R8G8B8A8UNORM,
+            vk::Format::R8G8B8A8SRGB,
+            options,
         );
         return (index, width, height);
     }
@@ -908,191 +1993,369 @@ impl Renderer {
         size: vk::DeviceSize,
         pixels: &[u8],
         format: vk::Format,
+        options: TextureOptions,
     ) -> usize {
-        // Prepare staging buffer
-        let staging = MBB::create(
-            &self.instance_table,
-            &self.device_table,
-            self.physical_device.as_ptr(),
-            *self.device,
-            size,
-            vk::BufferUsageFlagBits::TransferSource as u32,
-            vk::MemoryPropertyFlagBits::HostCoherent as u32
-                | vk::MemoryPropertyFlagBits::HostVisible as u32,
-        );
-        staging.fill(&self.device_table, *self.device, pixels.as_ptr());
+        let texture = self.create_texture(width, height, size, pixels, format, options);
+
+        let index = self.textures.len();
+        self.textures.push(texture);
+        return index;
+    }
 
-        // Create texture image and memory
-        let image_usage = vk::ImageUsageFlagBits::TransferDestination as u32
+    /// Creates a texture whose contents are uploaded asynchronously. It can be drawn right away,
+    /// as the upload is submitted ahead of the frame.
+    fn create_texture(
+        &mut self,
+        width: u32,
+        height: u32,
+        size: vk::DeviceSize,
+        pixels: &[u8],
+        format: vk::Format,
+        options: TextureOptions,
+    ) -> Texture {
+        let mip_levels = if options.mipmaps && self.supports_mipmaps(format) {
+            mip_levels(width, height)
+        } else {
+            1
+        };
+
+        // Create texture image and memory. Mip levels are generated by blitting between levels.
+        let mut image_usage = vk::ImageUsageFlagBits::TransferDestination as u32
             | vk::ImageUsageFlagBits::Sampled as u32;
-        let MBI { image, memory } = MBI::create(
+        if 1 < mip_levels {
+            image_usage |= vk::ImageUsageFlagBits::TransferSource as u32;
+        }
+        let MBI { image, allocation } = MBI::create(
             &self.instance_table,
             &self.device_table,
-            self.physical_device.as_ptr(),
+            &mut self.allocator,
             *self.device,
             format,
             (width, height).into(),
+            mip_levels,
+            vk::SampleCountFlagBits::One,
             vk::ImageTiling::Optimal,
             image_usage,
             vk::ImageLayout::Undefined,
             vk::MemoryPropertyFlagBits::DeviceLocal as u32,
         );
 
-        let view = create_image_view(&self.device_table, *self.device, image.as_ptr(), format);
-        let sampler =
-            create_sampler(&self.device_table, *self.device).expect("Failed to create sampler!");
-
-        // Prepare command buffer for recording
-        let transfer_buffer = allocate_command_buffer(
+        let view = create_image_view(
             &self.device_table,
             *self.device,
-            self.transfer_pool,
-            vk::CommandBufferLevel::Primary,
+            image.as_ptr(),
+            format,
+            mip_levels,
         );
-        command_buffer_begin_primary(
+
+        self.uploads.upload_image(
+            &self.instance_table,
             &self.device_table,
-            transfer_buffer,
-            vk::CommandBufferUsageFlagBits::OneTimeSubmit as u32,
+            &mut self.allocator,
+            &self.device,
+            image.as_ptr(),
+            (width, height).into(),
+            mip_levels,
+            &pixels[..size as usize],
         );
 
-        // Initial barrier transitioning to ImageLayout::TransferDestinationOptimal
-        {
-            let barrier = ImageMemoryBarrier {
-                src_access_mask: 0,
-                dst_access_mask: vk::AccessFlagBits::TransferWrite as u32,
-                old_layout: vk::ImageLayout::Undefined,
-                new_layout: vk::ImageLayout::TransferDestinationOptimal,
-                src_queue_family_index: vk::QUEUE_FAMILY_IGNORED,
-                dst_queue_family_index: vk::QUEUE_FAMILY_IGNORED,
-            };
-            record_image_memory_barrier(
-                &self.device_table,
-                transfer_buffer,
-                vk::PipelineStageFlagBits::TopOfPipe as u32,
-                vk::PipelineStageFlagBits::Transfer as u32,
-                barrier,
-                image.as_ptr(),
-            );
+        let texture = Texture {
+            image,
+            view,
+            allocation: Some(allocation),
+            options,
+        };
+        return texture;
+    }
+
+    /// Mip levels are generated with linear blits, which not every format supports.
+    fn supports_mipmaps(&self, format: vk::Format) -> bool {
+        let mut properties = MaybeUninit::uninit();
+        (self.instance_table.get_physical_device_format_properties)(
+            self.physical_device.as_ptr(),
+            format,
+            properties.as_mut_ptr(),
+        );
+        let properties: vk::FormatProperties = unsafe { properties.assume_init() };
+
+        let required = vk::FormatFeatureFlagBits::BlitSource as u32
+            | vk::FormatFeatureFlagBits::BlitDestination as u32
+            | vk::FormatFeatureFlagBits::SampledImageFilterLinear as u32;
+        let supported = properties.optimal_tiling_features & required == required;
+        if !supported {
+            warn!("Mipmaps can't be generated for {:?} textures", format);
         }
+        return supported;
+    }
+
+    /// The pass for `effect` in the post-processing stack, to toggle or adjust.
+    pub(crate) fn post_pass(&mut self, effect: PostEffect) -> Option<&mut PostPass> {
+        return self
+            .config
+            .post_passes
+            .iter_mut()
+            .find(|pass| pass.effect == effect);
+    }
 
-        let image_subresource = vk::ImageSubresourceLayers {
-            aspect_mask: vk::ImageAspectFlagBits::Color as u32,
-            mip_level: 0,
-            base_array_layer: 0,
-            layer_count: 1,
+    fn pipeline_context(&self) -> PipelineContext {
+        return PipelineContext {
+            render_pass: self.render_target.render_pass,
+            pipeline_cache: self.pipeline_cache.as_ptr(),
+            samples: self.render_target.samples,
+            non_solid_fill: self.non_solid_fill,
         };
-        let region = vk::BufferImageCopy {
-            buffer_offset: 0,
-            buffer_row_length: width,
-            buffer_image_height: height,
-            image_subresource,
-            image_offset: (0, 0, 0).into(),
-            image_extent: (width, height, 1).into(),
+    }
+
+    /// Post-processing passes read single samples and render without multisampling.
+    fn post_pipeline_context(&self) -> PipelineContext {
+        return PipelineContext {
+            render_pass: self.render_target.post_render_pass,
+            samples: vk::SampleCountFlagBits::One,
+            ..self.pipeline_context()
         };
-        (self.device_table.cmd_copy_buffer_to_image)(
-            transfer_buffer,
-            staging.buffer,
-            image.as_ptr(),
-            vk::ImageLayout::TransferDestinationOptimal,
-            1,
-            &region,
-        );
+    }
 
-        // Final barrier transitioning to ImageLayout::ShaderReadOnlyOptimal
-        {
-            let barrier = ImageMemoryBarrier {
-                src_access_mask: vk::AccessFlagBits::TransferWrite as u32,
-                dst_access_mask: vk::AccessFlagBits::ShaderRead as u32,
-                old_layout: vk::ImageLayout::TransferDestinationOptimal,
-                new_layout: vk::ImageLayout::ShaderReadOnlyOptimal,
-                src_queue_family_index: vk::QUEUE_FAMILY_IGNORED,
-                dst_queue_family_index: vk::QUEUE_FAMILY_IGNORED,
-            };
-            record_image_memory_barrier(
-                &self.device_table,
-                transfer_buffer,
-                vk::PipelineStageFlagBits::Transfer as u32,
-                vk::PipelineStageFlagBits::FragmentShader as u32,
-                barrier,
-                image.as_ptr(),
-            );
+    pub fn present(&mut self, index: usize) {
+        let info = vk::PresentInfoKHR {
+            stype: vk::StructureType::PresentInfoKHR,
+            next: null(),
+            wait_semaphore_count: 1,
+            wait_semaphores: &self.presentation_sync.image_ready
+                [self.presentation_sync.current_frame],
+            swapchain_count: 1,
+            swapchains: &self.render_target.swapchain,
+            image_indices: &(index as u32),
+            results: null_mut(),
+        };
+        let result = (self.device_table.queue_present_khr)(self.device.queues.presentation, &info);
+        if let vk::Result::ErrorOutOfDateKHR | vk::Result::SuboptimalKHR = result {
+            self.swapchain_outdated = true;
         }
 
-        let fence = create_fence(&self.device_table, *self.device, 0);
-        command_buffer_end_and_submit(
-            &self.device_table,
-            transfer_buffer,
-            self.device.queues.transfer,
-            None,
-            0,
-            None,
-            Some(fence),
-        );
-        fence_wait_reset(&self.device_table, *self.device, fence);
+        self.presentation_sync.current_frame += 1;
+        if self.presentation_sync.num_images <= self.presentation_sync.current_frame {
+            self.presentation_sync.current_frame = 0;
+        }
+    }
 
-        (self.device_table.free_command_buffers)(
-            *self.device,
-            self.transfer_pool,
-            1,
-            &transfer_buffer,
-        );
-        staging.destroy(&self.device_table, *self.device);
-        fence_destroy(&self.device_table, *self.device, fence);
+    /// Records the new size of the window. The swapchain is recreated when the next scene begins,
+    /// so a burst of resize events only recreates it once.
+    pub fn resize(&mut self, width: u32, height: u32) {
+        let extent = vk::Extent2D { width, height };
+        if extent != self.window_extent {
+            self.window_extent = extent;
+            self.swapchain_outdated = true;
+        }
+    }
 
-        let texture = Texture {
-            image,
-            view,
-            sampler,
-            memory,
-        };
+    /// Samples per pixel in use, which may be fewer than were configured.
+    pub fn samples(&self) -> SampleCount {
+        return self.render_target.samples;
+    }
+
+    /// Draws the sprite with a material from `load_material`, or the sprite material if `None`.
+    pub(crate) fn set_sprite_material(&mut self, sprite_index: usize, material: Option<usize>) {
+        self.sprites[sprite_index].material = material;
+    }
+
+    /// Shows frame times and resource counts over the scene, drawn with `font`, or hides them if
+    /// `None`.
+    pub(crate) fn set_performance_overlay(&mut self, font: Option<usize>) {
+        self.performance_overlay = font.map(PerformanceOverlay::new);
+    }
+
+    pub(crate) fn performance_overlay(&self) -> bool {
+        return self.performance_overlay.is_some();
+    }
+
+    /// Returns whether the overlay is shown, which it can't be if the device can't draw lines.
+    pub(crate) fn set_wireframe(&mut self, enabled: bool) -> bool {
+        self.config.wireframe = enabled;
+        if enabled && self.material_wireframe.is_none() {
+            warn!("The device can't draw lines, so the wireframe overlay is unavailable");
+        }
+
+        return self.wireframe();
+    }
+
+    /// Switches between `present_modes` and `low_latency_present_modes`, which may tear but add
+    /// less latency. The swapchain is recreated by the next `begin_scene`, which logs the present
+    /// mode it picks.
+    pub fn set_vsync(&mut self, enabled: bool) {
+        if self.config.vsync != enabled {
+            self.config.vsync = enabled;
+            self.swapchain_outdated = true;
+        }
+    }
 
-        let index = self.textures.len();
-        self.textures.push(texture);
-        return index;
+    pub fn vsync(&self) -> bool {
+        return self.config.vsync;
     }
 
-    pub fn present(&mut self, index: usize) {
-        let info = vk::PresentInfoKHR {
-            stype: vk::StructureType::PresentInfoKHR,
-            next: null(),
-            wait_semaphore_count: 1,
-            wait_semaphores: &self.presentation_sync.image_ready
-                [self.presentation_sync.current_frame],
-            swapchain_count: 1,
-            swapchains: &self.render_target.swapchain,
-            image_indices: &(index as u32),
-            results: null_mut(),
-        };
-        (self.device_table.queue_present_khr)(self.device.queues.presentation, &info);
+    pub(crate) fn wireframe(&self) -> bool {
+        return self.config.wireframe && self.material_wireframe.is_some();
+    }
 
-        self.presentation_sync.current_frame += 1;
-        if self.presentation_sync.num_images <= self.presentation_sync.current_frame {
-            self.presentation_sync.current_frame = 0;
+    /// Colours passed to the renderer are sRGB-encoded. When the swapchain encodes its output
+    /// itself, they are decoded first, so they look the same whichever format was picked.
+    fn output_colour(&self, colour: Vector4) -> Vector4 {
+        if !is_srgb(self.render_target.format) {
+            return colour;
         }
+
+        return Vector4 {
+            x: srgb_to_linear(colour.x),
+            y: srgb_to_linear(colour.y),
+            z: srgb_to_linear(colour.z),
+            w: colour.w,
+        };
     }
 
-    pub fn resize(&mut self, window: &crate::Window) {
-        (self.device_table.device_wait_idle)(*self.device);
-        self.render_target.destroy(&self.device_table, *self.device);
-        self.render_target = RenderTarget::create(
+    /// Creates a new swapchain, handing over the old one so the presentation engine can retire it
+    /// gracefully. Only frames still in flight are waited on, instead of the whole device.
+    fn recreate_render_target(&mut self) {
+        if self.window_extent.width == 0 || self.window_extent.height == 0 {
+            self.swapchain_outdated = true;
+            return;
+        }
+
+        let fences = &self.presentation_sync.drawing_finished;
+        (self.device_table.wait_for_fences)(
+            *self.device,
+            fences.len() as u32,
+            fences.as_ptr(),
+            true as u32,
+            u64::MAX,
+        );
+        // The fences don't cover presentation, and the old swapchain can't be destroyed while a
+        // present of one of its images is still pending
+        (self.device_table.queue_wait_idle)(self.device.queues.presentation);
+
+        let render_target = RenderTarget::create(
             &self.device_table,
             &self.instance_table,
+            &mut self.allocator,
             self.physical_device.as_ptr(),
             *self.device,
             *self.surface,
-            window.dimensions_inner().into(),
+            self.window_extent,
+            &self.config.preferred_present_modes(),
+            self.render_target.samples,
+            self.render_target.swapchain,
         );
+        if render_target.present_mode != self.render_target.present_mode {
+            info!("Presenting with {:?}", render_target.present_mode);
+        }
+        let mut old = std::mem::replace(&mut self.render_target, render_target);
+        old.destroy(&self.device_table, &mut self.allocator, *self.device);
+        self.post
+            .release(&self.device_table, &mut self.allocator, *self.device);
+        self.swapchain_outdated = false;
+    }
+
+    /// Rebuilds the materials whose files or shaders changed on disk. Materials that fail to
+    /// build keep their old pipeline, so a broken shader can be fixed without a restart.
+    fn reload_shaders(&mut self) {
+        let changed = match self.shader_watcher.as_mut() {
+            Some(watcher) => watcher.poll(),
+            None => return,
+        };
+        if changed.is_empty() {
+            return;
+        }
+
+        let context = self.pipeline_context();
+        let post_context = self.post_pipeline_context();
+        let overlay = [
+            &mut self.material_overlay_primitive,
+            &mut self.material_overlay_text,
+            &mut self.material_overlay_sdf_text,
+        ];
+        let builtin = [
+            &mut self.material_sprite,
+            &mut self.material_instanced_sprite,
+            &mut self.material_primitive,
+            &mut self.material_text,
+            &mut self.material_sdf_text,
+        ];
+        let materials = IntoIterator::into_iter(builtin)
+            .chain(self.material_wireframe.iter_mut())
+            .chain(self.materials.iter_mut())
+            .map(|material| (material, &context))
+            .chain(
+                IntoIterator::into_iter(overlay)
+                    .chain(self.post.materials_mut())
+                    .map(|material| (material, &post_context)),
+            );
+        let mut idle = false;
+        for (material, context) in materials {
+            let path = match changed.iter().find(|path| material.uses_file(path)) {
+                Some(path) => path,
+                None => continue,
+            };
+
+            let reloaded = material.reload(&self.device_table, context);
+            match reloaded {
+                Ok(reloaded) => {
+                    // The old pipeline may still be in use by frames in flight
+                    if !idle {
+                        (self.device_table.device_wait_idle)(*self.device);
+                        idle = true;
+                    }
+                    *material = reloaded;
+                    info!("Reloaded {:?}", path);
+                }
+                Err(e) => error!(
+                    "Failed to reload {:?}, keeping the old pipeline: {}",
+                    path, e
+                ),
+            }
+        }
+    }
+
+    /// Replaces the texture at `index` with new contents, keeping its index and options. The
+    /// device is idled first, as frames in flight may still be sampling the old texture.
+    pub fn replace_texture(
+        &mut self,
+        index: usize,
+        width: u32,
+        height: u32,
+        size: vk::DeviceSize,
+        pixels: &[u8],
+        format: vk::Format,
+    ) {
+        (self.device_table.device_wait_idle)(*self.device);
+        self.unload_texture(index);
+        let options = self.textures[index].options;
+        self.textures[index] = self.create_texture(width, height, size, pixels, format, options);
     }
 
     pub fn unload_texture(&mut self, index: usize) {
-        let texture = &self.textures[index];
-        (self.device_table.destroy_sampler)(*self.device, texture.sampler.as_ptr(), null());
+        // Recorded uploads may still refer to the texture
+        self.uploads
+            .finish(&self.device_table, &self.device, &mut self.allocator);
+
+        let texture = &mut self.textures[index];
         (self.device_table.destroy_image_view)(*self.device, texture.view, null());
         (self.device_table.destroy_image)(*self.device, texture.image.as_ptr(), null());
-        (self.device_table.free_memory)(*self.device, texture.memory.as_ptr(), null());
+        if let Some(allocation) = texture.allocation.take() {
+            self.allocator.free(&self.device_table, allocation);
+        }
+    }
+
+    /// Whether every texture loaded so far has finished uploading, for loading screens.
+    pub(crate) fn uploads_finished(&self) -> bool {
+        return self.uploads.is_idle();
+    }
+
+    /// Current usage of the GPU memory allocator.
+    pub(crate) fn memory_stats(&self) -> MemoryStats {
+        return self.allocator.stats();
     }
 }
 
+/// Returns the index of the acquired image, which is only valid if the result is `Success` or
+/// `SuboptimalKHR`.
 fn acquire_image(
     device_table: &DeviceTable,
     device: *mut vk::Device,
@@ -1100,7 +2363,7 @@ fn acquire_image(
     timeout: u64,
     semaphore: Option<*mut vk::Semaphore>,
     fence: Option<*mut vk::Fence>,
-) -> Option<u32> {
+) -> (u32, vk::Result) {
     let mut index = 0;
     let result = (device_table.acquire_next_image_khr)(
         device,
@@ -1111,10 +2374,7 @@ fn acquire_image(
         &mut index,
     );
 
-    return match result {
-        vk::Result::Success => Some(index),
-        _ => None,
-    };
+    return (index, result);
 }
 
 fn allocate_command_buffer(
@@ -1186,31 +2446,35 @@ fn allocate_descriptor_sets(
     return Some(sets);
 }
 
-fn allocate_memory(
-    instance_table: &InstanceTable,
+fn begin_render_pass(
     device_table: &DeviceTable,
-    physical_device: *mut vk::PhysicalDevice,
-    device: *mut vk::Device,
-    requirements: vk::MemoryRequirements,
-    flags: vk::MemoryPropertyFlags,
-) -> Option<NonNull<vk::DeviceMemory>> {
-    let memory_type_index = find_memory_type(
-        instance_table,
-        physical_device,
-        requirements.memory_type_bits,
-        flags,
-    )
-    .expect("Failed to find appropriate memory type!");
-
-    let allocate_info = vk::MemoryAllocateInfo {
-        stype: vk::StructureType::MemoryAllocateInfo,
+    command_buffer: *mut vk::CommandBuffer,
+    render_pass: *mut vk::RenderPass,
+    framebuffer: *mut vk::Framebuffer,
+    extent: vk::Extent2D,
+    clear_colour: [f32; 4],
+) {
+    let info = vk::RenderPassBeginInfo {
+        stype: vk::StructureType::RenderPassBeginInfo,
         next: null(),
-        allocation_size: requirements.size,
-        memory_type_index,
+        render_pass,
+        framebuffer,
+        render_area: vk::Rect2D {
+            offset: vk::Offset2D { x: 0, y: 0 },
+            extent,
+        },
+        clear_value_count: 1,
+        clear_values: &vk::ClearValue {
+            color: vk::ClearColorValue {
+                float: clear_colour,
+            },
+        },
     };
-    let mut memory = null_mut();
-    (device_table.allocate_memory)(device, &allocate_info, null(), &mut memory);
-    return NonNull::new(memory);
+    (device_table.cmd_begin_render_pass)(
+        command_buffer,
+        &info,
+        vk::SubpassContents::SecondaryCommandBuffers,
+    );
 }
 
 fn bind_sampled_image_descriptor(
@@ -1344,6 +2608,25 @@ fn create_command_pool(
     return command_pool;
 }
 
+fn create_debug_utils_messenger(
+    table: &DebugUtilsTable,
+    instance: *mut vk::Instance,
+) -> Option<DebugUtilsMessengerEXT> {
+    let info = debug_utils_messenger_create_info();
+    let mut messenger = null_mut();
+    let result = (table.create_debug_utils_messenger_ext)(instance, &info, null(), &mut messenger);
+    if result != vk::Result::Success || messenger.is_null() {
+        warn!("Failed to create debug messenger: {:?}", result);
+        return None;
+    }
+
+    return Some(DebugUtilsMessengerEXT {
+        instance,
+        destroy_debug_utils_messenger_ext: table.destroy_debug_utils_messenger_ext,
+        messenger,
+    });
+}
+
 fn create_device(
     table: &InstanceTable,
     physical_device: *mut vk::PhysicalDevice,
@@ -1358,11 +2641,11 @@ fn create_device(
                 .iter()
                 .all(|previous| *previous != *current)
         })
-        .map(|(unique_index, _)| vk::DeviceQueueCreateInfo {
+        .map(|(_, &family)| vk::DeviceQueueCreateInfo {
             stype: vk::StructureType::DeviceQueueCreateInfo,
             next: null(),
             flags: 0,
-            queue_family_index: unique_index as u32,
+            queue_family_index: family,
             queue_count: 1,
             queue_priorities: &priority,
         })
@@ -1372,6 +2655,13 @@ fn create_device(
     let swapchain_c = CStr::from_bytes_with_nul(&swapchain.as_bytes()).unwrap();
     let extensions = [swapchain_c.as_ptr()];
 
+    // Anisotropic filtering and the wireframe overlay's lines are the only optional features used
+    let mut supported: vk::PhysicalDeviceFeatures = unsafe { std::mem::zeroed() };
+    (table.get_physical_device_features)(physical_device, &mut supported);
+    let mut features: vk::PhysicalDeviceFeatures = unsafe { std::mem::zeroed() };
+    features.sampler_anisotropy = supported.sampler_anisotropy;
+    features.fill_mode_non_solid = supported.fill_mode_non_solid;
+
     let info = vk::DeviceCreateInfo {
         stype: vk::StructureType::DeviceCreateInfo,
         next: null(),
@@ -1382,7 +2672,7 @@ fn create_device(
         enabled_layer_names: null(), // Deprecated
         enabled_extension_count: extensions.len() as u32,
         enabled_extension_names: extensions.as_ptr(),
-        enabled_features: null(),
+        enabled_features: &features,
     };
 
     let mut device = null_mut();
@@ -1411,7 +2701,7 @@ fn create_framebuffer(
     table: &DeviceTable,
     device: *mut vk::Device,
     render_pass: *mut vk::RenderPass,
-    view: *mut vk::ImageView,
+    views: &[*mut vk::ImageView],
     image_extent: vk::Extent2D,
 ) -> *mut vk::Framebuffer {
     let info = vk::FramebufferCreateInfo {
@@ -1419,8 +2709,8 @@ fn create_framebuffer(
         next: null(),
         flags: 0,
         render_pass,
-        attachment_count: 1,
-        attachments: &view,
+        attachment_count: views.len() as u32,
+        attachments: views.as_ptr(),
         width: image_extent.width,
         height: image_extent.height,
         layers: 1,
@@ -1436,6 +2726,8 @@ fn create_image(
     device: *mut vk::Device,
     format: vk::Format,
     extent: vk::Extent2D,
+    mip_levels: u32,
+    samples: vk::SampleCountFlagBits,
     tiling: vk::ImageTiling,
     usage: vk::ImageUsageFlags,
     initial_layout: vk::ImageLayout,
@@ -1452,9 +2744,9 @@ fn create_image(
         image_type: vk::ImageType::TwoDimensional,
         format,
         extent: (extent.width, extent.height, 1).into(),
-        mip_levels: 1,
+        mip_levels,
         array_layers: 1,
-        samples: vk::SampleCountFlagBits::One as u32,
+        samples: samples as u32,
         tiling,
         usage,
         sharing_mode: vk::SharingMode::Exclusive,
@@ -1473,11 +2765,12 @@ fn create_image_view(
     device: *mut vk::Device,
     image: *mut vk::Image,
     format: vk::Format,
+    mip_levels: u32,
 ) -> *mut vk::ImageView {
     let subresource_range = vk::ImageSubresourceRange {
         aspect_mask: vk::ImageAspectFlagBits::Color as u32,
         base_mip_level: 0,
-        mip_levels: 1,
+        mip_levels,
         base_array_layer: 0,
         array_layers: 1,
     };
@@ -1503,7 +2796,9 @@ fn create_image_view(
 }
 
 // TODO: Make this function less sneaky!
-fn create_instance(loader: &Loader) -> Option<*mut vk::Instance> {
+/// Creates the instance, enabling validation if requested and available. The second value is
+/// whether `VK_EXT_debug_utils` was enabled along with it.
+fn create_instance(loader: &Loader, validation: bool) -> Option<(*mut vk::Instance, bool)> {
     let create_instance = loader.load_vk_create_instance();
     let application_name = "Pong!\0";
     let application_name_c = CStr::from_bytes_with_nul(application_name.as_bytes()).unwrap();
@@ -1521,20 +2816,49 @@ fn create_instance(loader: &Loader) -> Option<*mut vk::Instance> {
         api_version: vk::API_VERSION_1_0,
     };
 
-    let validation_layer = "VK_LAYER_KHRONOS_validation\0";
-    let validation_layer_c = CStr::from_bytes_with_nul(validation_layer.as_bytes()).unwrap();
-    let layers = [validation_layer_c.as_ptr()];
+    let available_layers = enumerate_instance_layers(loader);
+    let available_extensions = enumerate_instance_extensions(loader);
+
+    let validation_layer = cstr!("VK_LAYER_KHRONOS_validation");
+    let mut layers = Vec::new();
+    if validation {
+        if available_layers
+            .iter()
+            .any(|layer| layer.as_c_str() == validation_layer)
+        {
+            layers.push(validation_layer.as_ptr());
+        } else {
+            warn!("Validation was requested, but VK_LAYER_KHRONOS_validation is not installed");
+        }
+    }
 
-    let surface = "VK_KHR_surface\0";
-    let surface_c = CStr::from_bytes_with_nul(surface.as_bytes()).unwrap();
-    let extensions = [
-        surface_c.as_ptr(),
+    let surface = cstr!("VK_KHR_surface");
+    let mut extensions = vec![
+        surface.as_ptr(),
         crate::Window::surface_extension_name().as_ptr(),
     ];
 
+    // Messages are only worth collecting while validating
+    let debug_utils_extension = cstr!("VK_EXT_debug_utils");
+    let debug_utils = !layers.is_empty()
+        && available_extensions
+            .iter()
+            .any(|extension| extension.as_c_str() == debug_utils_extension);
+    if debug_utils {
+        extensions.push(debug_utils_extension.as_ptr());
+    }
+
+    // Chained so that messages from instance creation and destruction are also reported
+    let messenger_info = debug_utils_messenger_create_info();
+    let next = if debug_utils {
+        &messenger_info as *const _ as *const c_void
+    } else {
+        null()
+    };
+
     let info = vk::InstanceCreateInfo {
         stype: vk::StructureType::InstanceCreateInfo,
-        next: null(),
+        next,
         flags: 0,
         application_info: &app_info,
         enabled_layer_count: layers.len() as u32,
@@ -1548,30 +2872,61 @@ fn create_instance(loader: &Loader) -> Option<*mut vk::Instance> {
     if result != vk::Result::Success || instance.is_null() {
         return None;
     } else {
-        return Some(instance);
+        return Some((instance, debug_utils));
     }
 }
 
+/// A single subpass rendering to a colour attachment, which is left in `final_layout`.
 fn create_render_pass(
     table: &DeviceTable,
     device: *mut vk::Device,
     format: vk::Format,
+    samples: vk::SampleCountFlagBits,
+    final_layout: vk::ImageLayout,
 ) -> *mut vk::RenderPass {
-    let attachment = vk::AttachmentDescription {
-        flags: 0,
-        format,
-        samples: vk::SampleCountFlagBits::One,
-        load_op: vk::AttachmentLoadOp::Clear,
-        store_op: vk::AttachmentStoreOp::Store,
-        stencil_load_op: vk::AttachmentLoadOp::DontCare,
-        stencil_store_op: vk::AttachmentStoreOp::DontCare,
-        initial_layout: vk::ImageLayout::Undefined,
-        final_layout: vk::ImageLayout::PresentSourceKHR,
-    };
+    // Multisampled passes resolve into a second attachment and discard the samples, so the
+    // framebuffer's last view is always the image that ends up in `final_layout`
+    let multisampled = samples != vk::SampleCountFlagBits::One;
+    let attachments = [
+        vk::AttachmentDescription {
+            flags: 0,
+            format,
+            samples,
+            load_op: vk::AttachmentLoadOp::Clear,
+            store_op: if multisampled {
+                vk::AttachmentStoreOp::DontCare
+            } else {
+                vk::AttachmentStoreOp::Store
+            },
+            stencil_load_op: vk::AttachmentLoadOp::DontCare,
+            stencil_store_op: vk::AttachmentStoreOp::DontCare,
+            initial_layout: vk::ImageLayout::Undefined,
+            final_layout: if multisampled {
+                vk::ImageLayout::ColorAttachmentOptimal
+            } else {
+                final_layout
+            },
+        },
+        vk::AttachmentDescription {
+            flags: 0,
+            format,
+            samples: vk::SampleCountFlagBits::One,
+            load_op: vk::AttachmentLoadOp::DontCare,
+            store_op: vk::AttachmentStoreOp::Store,
+            stencil_load_op: vk::AttachmentLoadOp::DontCare,
+            stencil_store_op: vk::AttachmentStoreOp::DontCare,
+            initial_layout: vk::ImageLayout::Undefined,
+            final_layout,
+        },
+    ];
     let color_attachment = vk::AttachmentReference {
         attachment: 0,
         layout: vk::ImageLayout::ColorAttachmentOptimal,
     };
+    let resolve_attachment = vk::AttachmentReference {
+        attachment: 1,
+        layout: vk::ImageLayout::ColorAttachmentOptimal,
+    };
     let subpass = vk::SubpassDescription {
         flags: 0,
         pipeline_bind_point: vk::PipelineBindPoint::Graphics,
@@ -1579,55 +2934,82 @@ fn create_render_pass(
         input_attachments: null(),
         color_attachment_count: 1,
         color_attachments: &color_attachment,
-        resolve_attachments: null(),
+        resolve_attachments: if multisampled {
+            &resolve_attachment
+        } else {
+            null()
+        },
         depth_stencil_attachment: null(),
         preserve_attachment_count: 0,
         preserve_attachments: null(),
     };
-    let dependency = vk::SubpassDependency {
-        source_subpass: vk::SUBPASS_EXTERNAL,
-        destination_subpass: 0,
-        source_stage_mask: vk::PipelineStageFlagBits::ColorAttachmentOutput as u32,
-        destination_stage_mask: vk::PipelineStageFlagBits::ColorAttachmentOutput as u32,
-        source_access_mask: 0,
-        destination_access_mask: vk::AccessFlagBits::ColorAttachmentWrite as u32,
-        dependency_flags: vk::DependencyFlags::None,
-    };
+    // Post-processing targets and multisampled images are shared by frames in flight. Targets are
+    // sampled by the previous frame's passes before being rendered to again, and by the next pass
+    // afterwards
+    let dependencies = [
+        vk::SubpassDependency {
+            source_subpass: vk::SUBPASS_EXTERNAL,
+            destination_subpass: 0,
+            source_stage_mask: vk::PipelineStageFlagBits::ColorAttachmentOutput as u32
+                | vk::PipelineStageFlagBits::FragmentShader as u32,
+            destination_stage_mask: vk::PipelineStageFlagBits::ColorAttachmentOutput as u32,
+            source_access_mask: vk::AccessFlagBits::ColorAttachmentWrite as u32,
+            destination_access_mask: vk::AccessFlagBits::ColorAttachmentWrite as u32,
+            dependency_flags: vk::DependencyFlags::None,
+        },
+        vk::SubpassDependency {
+            source_subpass: 0,
+            destination_subpass: vk::SUBPASS_EXTERNAL,
+            source_stage_mask: vk::PipelineStageFlagBits::ColorAttachmentOutput as u32,
+            destination_stage_mask: vk::PipelineStageFlagBits::FragmentShader as u32,
+            source_access_mask: vk::AccessFlagBits::ColorAttachmentWrite as u32,
+            destination_access_mask: vk::AccessFlagBits::ShaderRead as u32,
+            dependency_flags: vk::DependencyFlags::None,
+        },
+    ];
     let info = vk::RenderPassCreateInfo {
         stype: vk::StructureType::RenderPassCreateInfo,
         next: null(),
         flags: 0,
-        attachment_count: 1,
-        attachments: &attachment,
+        attachment_count: if multisampled { 2 } else { 1 },
+        attachments: attachments.as_ptr(),
         subpass_count: 1,
         subpasses: &subpass,
-        dependency_count: 1,
-        dependencies: &dependency,
+        dependency_count: dependencies.len() as u32,
+        dependencies: dependencies.as_ptr(),
     };
     let mut render_pass = null_mut();
     (table.create_render_pass)(device, &info, null(), &mut render_pass);
     return render_pass;
 }
 
-fn create_sampler(table: &DeviceTable, device: *mut vk::Device) -> Option<NonNull<vk::Sampler>> {
+fn create_sampler(
+    table: &DeviceTable,
+    device: *mut vk::Device,
+    options: SamplerOptions,
+) -> Option<NonNull<vk::Sampler>> {
+    let mipmap_mode = match options.filter {
+        vk::Filter::Nearest => vk::SamplerMipmapMode::Nearest,
+        _ => vk::SamplerMipmapMode::Linear,
+    };
     let info = vk::SamplerCreateInfo {
         stype: vk::StructureType::SamplerCreateInfo,
         next: null(),
         flags: 0,
-        mag_filter: vk::Filter::Linear,
-        min_filter: vk::Filter::Linear,
-        mipmap_mode: vk::SamplerMipmapMode::Linear,
-        address_mode_u: vk::SamplerAddressMode::ClampToBorder,
-        address_mode_v: vk::SamplerAddressMode::ClampToBorder,
-        address_mode_w: vk::SamplerAddressMode::ClampToBorder,
+        mag_filter: options.filter,
+        min_filter: options.filter,
+        mipmap_mode,
+        address_mode_u: options.address_mode,
+        address_mode_v: options.address_mode,
+        address_mode_w: options.address_mode,
         mip_lod_bias: 0.0,
-        anisotropy_enable: false as u32,
-        max_anisotropy: 0.0,
-        compare_enable: true as u32,
-        compare_op: vk::CompareOp::Equal,
+        anisotropy_enable: (1.0 < options.anisotropy) as u32,
+        max_anisotropy: options.anisotropy,
+        compare_enable: false as u32,
+        compare_op: vk::CompareOp::Always,
         min_lod: 0.0,
-        max_lod: 0.0,
-        border_color: vk::BorderColor::FloatOpaqueBlack,
+        max_lod: vk::LOD_CLAMP_NONE,
+        border_color: vk::BorderColor::FloatTransparentBlack,
         unnormalized_coordinates: false as u32,
     };
 
@@ -1659,6 +3041,8 @@ fn create_swapchain(
     capabilities: vk::SurfaceCapabilitiesKHR,
     surface_format: vk::SurfaceFormatKHR,
     image_extent: vk::Extent2D,
+    present_mode: vk::PresentModeKHR,
+    old_swapchain: *mut vk::SwapchainKHR,
 ) -> *mut vk::SwapchainKHR {
     let min_image_count = u32::min(
         capabilities.min_image_count,
@@ -1681,9 +3065,9 @@ fn create_swapchain(
         queue_family_indices: null(),
         pre_transform: capabilities.current_transform,
         composite_alpha: vk::CompositeAlphaFlagsKHR::Opaque, // TODO: Can get this from capabilities
-        present_mode: vk::PresentModeKHR::Fifo,
+        present_mode,
         clipped: true as u32,
-        old_swapchain: null_mut(),
+        old_swapchain,
     };
 
     let mut swapchain = null_mut();
@@ -1691,12 +3075,68 @@ fn create_swapchain(
     return swapchain;
 }
 
+/// Forwards validation messages to the log at the matching level.
+extern "system" fn debug_utils_callback(
+    message_severity: vk::DebugUtilsMessageSeverityFlagsEXT,
+    _message_types: vk::DebugUtilsMessageTypeFlagsEXT,
+    callback_data: *const vk::DebugUtilsMessengerCallbackDataEXT,
+    _user_data: *mut c_void,
+) -> vk::Bool32 {
+    let message = unsafe { CStr::from_ptr((*callback_data).message) }.to_string_lossy();
+    let is = |bit: vk::DebugUtilsMessageSeverityFlagBitsEXT| message_severity & bit as u32 != 0;
+    if is(vk::DebugUtilsMessageSeverityFlagBitsEXT::Error) {
+        error!("{}", message);
+    } else if is(vk::DebugUtilsMessageSeverityFlagBitsEXT::Warning) {
+        warn!("{}", message);
+    } else if is(vk::DebugUtilsMessageSeverityFlagBitsEXT::Info) {
+        info!("{}", message);
+    } else {
+        debug!("{}", message);
+    }
+
+    // The call that triggered the message must not be aborted
+    return false as u32;
+}
+
+/// Only severities that the log would print are requested from the driver.
+fn debug_utils_messenger_create_info() -> vk::DebugUtilsMessengerCreateInfoEXT {
+    use vk::DebugUtilsMessageSeverityFlagBitsEXT as Severity;
+
+    let max_level = crate::log::max_level();
+    let severities = [
+        (Level::Error, Severity::Error),
+        (Level::Warn, Severity::Warning),
+        (Level::Info, Severity::Info),
+        (Level::Debug, Severity::Verbose),
+    ];
+    let message_severity = severities
+        .iter()
+        .filter(|(level, _)| *level <= max_level)
+        .fold(0, |mask, (_, bit)| mask | *bit as u32);
+
+    return vk::DebugUtilsMessengerCreateInfoEXT {
+        stype: vk::StructureType::DebugUtilsMessengerCreateInfoEXT,
+        next: null(),
+        flags: 0,
+        message_severity,
+        message_type: vk::DebugUtilsMessageTypeFlagBitsEXT::General as u32
+            | vk::DebugUtilsMessageTypeFlagBitsEXT::Validation as u32
+            | vk::DebugUtilsMessageTypeFlagBitsEXT::Performance as u32,
+        user_callback: debug_utils_callback,
+        user_data: null_mut(),
+    };
+}
+
+/// A pool of `max_sets` sets with a combined image sampler each.
 fn descriptor_pool_create(
     dt: &DeviceTable,
     device: *mut vk::Device,
     max_sets: u32,
-    pool_sizes: &[vk::DescriptorPoolSize],
 ) -> *mut vk::DescriptorPool {
+    let pool_sizes = [vk::DescriptorPoolSize {
+        dtype: vk::DescriptorType::CombinedImageSampler,
+        descriptor_count: max_sets,
+    }];
     let info = vk::DescriptorPoolCreateInfo {
         stype: vk::StructureType::DescriptorPoolCreateInfo,
         next: null(),
@@ -1708,6 +3148,10 @@ fn descriptor_pool_create(
 
     let mut descriptor_pool = null_mut();
     (dt.create_descriptor_pool)(device, &info, null(), &mut descriptor_pool);
+    assert!(
+        !descriptor_pool.is_null(),
+        "Failed to create a descriptor pool!"
+    );
     return descriptor_pool;
 }
 
@@ -1715,11 +3159,12 @@ fn descriptor_set_update_sampled_image(
     device_table: &DeviceTable,
     device: *mut vk::Device,
     descriptor_set: *mut vk::DescriptorSet,
-    texture: &Texture,
+    image_view: *mut vk::ImageView,
+    sampler: *mut vk::Sampler,
 ) {
     let image_info = vk::DescriptorImageInfo {
-        sampler: texture.sampler.as_ptr(),
-        image_view: texture.view,
+        sampler,
+        image_view,
         image_layout: vk::ImageLayout::ShaderReadOnlyOptimal,
     };
     let write = vk::WriteDescriptorSet {
@@ -1737,6 +3182,44 @@ fn descriptor_set_update_sampled_image(
     (device_table.update_descriptor_sets)(device, 1, &write, 0, null());
 }
 
+fn enumerate_instance_extensions(loader: &Loader) -> Vec<CString> {
+    let enumerate = loader.load_vk_enumerate_instance_extension_properties();
+    let mut count = 0;
+    enumerate(null(), &mut count, null_mut());
+    let empty = vk::ExtensionProperties {
+        extension_name: [0; vk::MAX_EXTENSION_NAME_SIZE],
+        spec_version: 0,
+    };
+    let mut properties = vec![empty; count as usize];
+    enumerate(null(), &mut count, properties.as_mut_ptr());
+    properties.truncate(count as usize);
+
+    return properties
+        .iter()
+        .map(|property| unsafe { CStr::from_ptr(property.extension_name.as_ptr()) }.to_owned())
+        .collect();
+}
+
+fn enumerate_instance_layers(loader: &Loader) -> Vec<CString> {
+    let enumerate = loader.load_vk_enumerate_instance_layer_properties();
+    let mut count = 0;
+    enumerate(&mut count, null_mut());
+    let empty = vk::LayerProperties {
+        layer_name: [0; vk::MAX_EXTENSION_NAME_SIZE],
+        spec_version: 0,
+        implementation_version: 0,
+        description: [0; vk::MAX_DESCRIPTION_SIZE],
+    };
+    let mut properties = vec![empty; count as usize];
+    enumerate(&mut count, properties.as_mut_ptr());
+    properties.truncate(count as usize);
+
+    return properties
+        .iter()
+        .map(|property| unsafe { CStr::from_ptr(property.layer_name.as_ptr()) }.to_owned())
+        .collect();
+}
+
 fn get_swapchain_images(
     table: &DeviceTable,
     device: *mut vk::Device,
@@ -1807,6 +3290,23 @@ fn find_queue(properties: &[vk::QueueFamilyProperties], bit: vk::QueueFlagBits)
         .map(|(i, _)| i as u32);
 }
 
+/// Prefers a family dedicated to transfers, which usually maps to a DMA engine, as long as it can
+/// copy images at any offset. Graphics families always support transfers, so `graphics` is the
+/// fallback.
+fn find_transfer_queue(properties: &[vk::QueueFamilyProperties], graphics: u32) -> u32 {
+    let transfer = vk::QueueFlagBits::Transfer as u32;
+    let graphics_bit = vk::QueueFlagBits::Graphics as u32;
+    return properties
+        .iter()
+        .position(|prop| {
+            let granularity = prop.min_image_transfer_granularity;
+            (prop.queue_flags & transfer) == transfer
+                && (prop.queue_flags & graphics_bit) == 0
+                && (granularity.width, granularity.height, granularity.depth) == (1, 1, 1)
+        })
+        .map_or(graphics, |i| i as u32);
+}
+
 fn get_capabilities(
     table: &InstanceTable,
     physical_device: *mut vk::PhysicalDevice,
@@ -1838,21 +3338,75 @@ fn get_queue_family_properties(
     return properties;
 }
 
+/// Prefers an sRGB format, so that the linear colours written by the shaders are encoded for
+/// display the same way on every driver. Falls back to whatever the surface lists first.
 fn get_swapchain_format(
     table: &InstanceTable,
     physical_device: *mut vk::PhysicalDevice,
     surface: *mut vk::SurfaceKHR,
 ) -> vk::SurfaceFormatKHR {
-    let mut count = 1;
-    let mut format = MaybeUninit::uninit();
+    let preferred = vk::SurfaceFormatKHR {
+        format: vk::Format::B8G8R8A8SRGB,
+        color_space: vk::ColorSpaceKHR::SRGBNonlinearKHR,
+    };
+
+    let mut count = 0;
     (table.get_physical_device_surface_formats_khr)(
         physical_device,
         surface,
         &mut count,
-        format.as_mut_ptr(),
+        null_mut(),
     );
+    let mut formats = vec![preferred; count as usize];
+    (table.get_physical_device_surface_formats_khr)(
+        physical_device,
+        surface,
+        &mut count,
+        formats.as_mut_ptr(),
+    );
+
+    // A single undefined format means the surface has no preference
+    if let [format] = formats[..] {
+        if format.format == vk::Format::Undefined {
+            return preferred;
+        }
+    }
+
+    let srgb_formats = [vk::Format::B8G8R8A8SRGB, vk::Format::R8G8B8A8SRGB];
+    for &srgb_format in srgb_formats.iter() {
+        let format = formats.iter().find(|format| {
+            format.format == srgb_format
+                && format.color_space == vk::ColorSpaceKHR::SRGBNonlinearKHR
+        });
+        if let Some(&format) = format {
+            return format;
+        }
+    }
+
+    return *formats.first().expect("Surface supports no formats!");
+}
+
+fn is_srgb(format: vk::Format) -> bool {
+    use vk::Format::*;
+    return matches!(
+        format,
+        R8SRGB | R8G8SRGB | R8G8B8SRGB | B8G8R8SRGB | R8G8B8A8SRGB | B8G8R8A8SRGB
+    );
+}
+
+/// Returns 1 if anisotropic filtering isn't supported, matching the features `create_device`
+/// enables.
+fn max_sampler_anisotropy(table: &InstanceTable, physical_device: *mut vk::PhysicalDevice) -> f32 {
+    let mut features: vk::PhysicalDeviceFeatures = unsafe { std::mem::zeroed() };
+    (table.get_physical_device_features)(physical_device, &mut features);
+    if features.sampler_anisotropy == false as u32 {
+        return 1.0;
+    }
 
-    return unsafe { format.assume_init() };
+    let mut properties = MaybeUninit::uninit();
+    (table.get_physical_device_properties)(physical_device, properties.as_mut_ptr());
+    let properties: vk::PhysicalDeviceProperties = unsafe { properties.assume_init() };
+    return properties.limits.max_sampler_anisotropy;
 }
 
 fn select_extent(
@@ -1880,29 +3434,156 @@ fn select_extent(
     return vk::Extent2D { width, height };
 }
 
+/// Picks the highest scoring device able to render to `surface`, unless `selection` names one by
+/// index or by part of its name.
 fn select_physical_device(
-    instance_table: &InstanceTable,
+    table: &InstanceTable,
     instance: *mut vk::Instance,
+    surface: *mut vk::SurfaceKHR,
+    selection: Option<&str>,
 ) -> Option<NonNull<vk::PhysicalDevice>> {
-    let mut count = 1;
+    let mut count = 0;
+    (table.enumerate_physical_devices)(instance, &mut count, null_mut());
+    let mut physical_devices = vec![null_mut(); count as usize];
+    (table.enumerate_physical_devices)(instance, &mut count, physical_devices.as_mut_ptr());
+
+    let candidates: Vec<(*mut vk::PhysicalDevice, String, Option<u32>)> = physical_devices
+        .iter()
+        .map(|&physical_device| {
+            let mut properties = MaybeUninit::uninit();
+            (table.get_physical_device_properties)(physical_device, properties.as_mut_ptr());
+            let properties = unsafe { properties.assume_init() };
+
+            let name = &properties.device_name;
+            let length = name.iter().position(|&c| c == 0).unwrap_or(name.len());
+            let name = String::from_utf8_lossy(&name[..length]).into_owned();
+
+            let score = score_physical_device(table, surface, physical_device, &properties);
+            (physical_device, name, score)
+        })
+        .collect();
+
+    for (i, (_, name, score)) in candidates.iter().enumerate() {
+        match score {
+            Some(score) => info!("Device {}: {} (score {})", i, name, score),
+            None => info!("Device {}: {} (unsuitable)", i, name),
+        }
+    }
+
+    let selected = match selection {
+        Some(selection) => {
+            let selected = match selection.parse::<usize>() {
+                Ok(index) => candidates.get(index),
+                Err(_) => {
+                    let selection = selection.to_lowercase();
+                    candidates
+                        .iter()
+                        .find(|(_, name, _)| name.to_lowercase().contains(&selection))
+                }
+            };
+            let selected =
+                selected.unwrap_or_else(|| panic!("No device matches \"{}\"!", selection));
+            if selected.2.is_none() {
+                panic!("Device \"{}\" can't render to the window!", selected.1);
+            }
+            selected
+        }
+        None => candidates
+            .iter()
+            .filter(|(_, _, score)| score.is_some())
+            .max_by_key(|(_, _, score)| *score)?,
+    };
+
+    info!("Using {}", selected.1);
+    return NonNull::new(selected.0);
+}
+
+/// Scores a device by its type, how its queues are laid out and its limits. Returns `None` for
+/// devices missing a queue the renderer needs.
+fn score_physical_device(
+    table: &InstanceTable,
+    surface: *mut vk::SurfaceKHR,
+    physical_device: *mut vk::PhysicalDevice,
+    properties: &vk::PhysicalDeviceProperties,
+) -> Option<u32> {
+    let [graphics, presentation, _] = select_queue_family_indices(table, surface, physical_device)?;
+
+    let mut score = match properties.device_type {
+        vk::PhysicalDeviceType::DiscreteGPU => 1000,
+        vk::PhysicalDeviceType::IntegratedGPU => 500,
+        vk::PhysicalDeviceType::VirtualGPU => 250,
+        vk::PhysicalDeviceType::CPU => 100,
+        vk::PhysicalDeviceType::Other => 0,
+    };
+
+    // Presenting from the graphics queue avoids sharing images between queue families
+    if graphics == presentation {
+        score += 100;
+    }
+    score += properties.limits.max_image_dimension_2d / 1024;
+
+    return Some(score);
+}
+
+/// Picks the first of `preferences` the surface supports, falling back to `Fifo`.
+fn select_present_mode(
+    table: &InstanceTable,
+    physical_device: *mut vk::PhysicalDevice,
+    surface: *mut vk::SurfaceKHR,
+    preferences: &[vk::PresentModeKHR],
+) -> vk::PresentModeKHR {
+    let mut count = 0;
+    (table.get_physical_device_surface_present_modes_khr)(
+        physical_device,
+        surface,
+        &mut count,
+        null_mut(),
+    );
+    let mut supported = vec![vk::PresentModeKHR::Fifo; count as usize];
+    (table.get_physical_device_surface_present_modes_khr)(
+        physical_device,
+        surface,
+        &mut count,
+        supported.as_mut_ptr(),
+    );
 
-    let mut physical_device = null_mut();
-    (instance_table.enumerate_physical_devices)(instance, &mut count, &mut physical_device);
-    return NonNull::new(physical_device);
+    return preferences
+        .iter()
+        .copied()
+        .find(|mode| supported.contains(mode))
+        .unwrap_or(vk::PresentModeKHR::Fifo);
 }
 
 fn select_queue_family_indices(
     table: &InstanceTable,
     surface: *mut vk::SurfaceKHR,
     physical_device: *mut vk::PhysicalDevice,
-) -> [u32; 3] {
+) -> Option<[u32; 3]> {
     let properties = get_queue_family_properties(&table, physical_device);
-    let graphics = find_queue(&properties, vk::QueueFlagBits::Graphics).unwrap();
+    let graphics = find_queue(&properties, vk::QueueFlagBits::Graphics)?;
     let presentation =
-        find_presentation_queue(&table, surface, physical_device, properties.len() as u32).unwrap();
-    let transfer = find_queue(&properties, vk::QueueFlagBits::Transfer).unwrap();
+        find_presentation_queue(&table, surface, physical_device, properties.len() as u32)?;
+    let transfer = find_transfer_queue(&properties, graphics);
+
+    return Some([graphics, presentation, transfer]);
+}
+
+/// Falls back to the most samples up to `requested` that the device can render colour with.
+fn select_sample_count(
+    table: &InstanceTable,
+    physical_device: *mut vk::PhysicalDevice,
+    requested: vk::SampleCountFlagBits,
+) -> vk::SampleCountFlagBits {
+    use vk::SampleCountFlagBits::*;
 
-    return [graphics, presentation, transfer];
+    let mut properties = MaybeUninit::uninit();
+    (table.get_physical_device_properties)(physical_device, properties.as_mut_ptr());
+    let properties: vk::PhysicalDeviceProperties = unsafe { properties.assume_init() };
+    let supported = properties.limits.framebuffer_color_sample_counts;
+
+    return IntoIterator::into_iter([SixtyFour, ThirtyTwo, Sixteen, Eight, Four, Two])
+        .find(|&samples| samples as u32 <= requested as u32 && supported & samples as u32 != 0)
+        .unwrap_or(One);
 }
 
 fn set_scissor_and_viewport(
@@ -1927,6 +3608,16 @@ fn set_scissor_and_viewport(
     (dt.cmd_set_scissor)(command_buffer, 0, 1, &scissor);
 }
 
+/// Matches the features `create_device` enables.
+fn supports_non_solid_fill(
+    table: &InstanceTable,
+    physical_device: *mut vk::PhysicalDevice,
+) -> bool {
+    let mut features: vk::PhysicalDeviceFeatures = unsafe { std::mem::zeroed() };
+    (table.get_physical_device_features)(physical_device, &mut features);
+    return features.fill_mode_non_solid != false as u32;
+}
+
 struct ImageMemoryBarrier {
     src_access_mask: vk::AccessFlags,
     dst_access_mask: vk::AccessFlags,
@@ -1936,6 +3627,15 @@ struct ImageMemoryBarrier {
     dst_queue_family_index: u32,
 }
 
+/// Decodes a colour channel from the sRGB transfer function into linear light.
+fn srgb_to_linear(value: f32) -> f32 {
+    if value <= 0.04045 {
+        return value / 12.92;
+    }
+
+    return ((value + 0.055) / 1.055).powf(2.4);
+}
+
 fn record_image_memory_barrier(
     device_table: &DeviceTable,
     command_buffer: *mut vk::CommandBuffer,
@@ -1943,11 +3643,34 @@ fn record_image_memory_barrier(
     dst_stage_mask: vk::PipelineStageFlags,
     barrier: ImageMemoryBarrier,
     image: *mut vk::Image,
+) {
+    record_image_mip_barrier(
+        device_table,
+        command_buffer,
+        src_stage_mask,
+        dst_stage_mask,
+        barrier,
+        image,
+        0,
+        1,
+    );
+}
+
+/// Like `record_image_memory_barrier`, for `mip_levels` levels starting at `base_mip_level`.
+fn record_image_mip_barrier(
+    device_table: &DeviceTable,
+    command_buffer: *mut vk::CommandBuffer,
+    src_stage_mask: vk::PipelineStageFlags,
+    dst_stage_mask: vk::PipelineStageFlags,
+    barrier: ImageMemoryBarrier,
+    image: *mut vk::Image,
+    base_mip_level: u32,
+    mip_levels: u32,
 ) {
     let subresource_range = vk::ImageSubresourceRange {
         aspect_mask: vk::ImageAspectFlagBits::Color as u32,
-        base_mip_level: 0,
-        mip_levels: 1,
+        base_mip_level,
+        mip_levels,
         base_array_layer: 0,
         array_layers: 1,
     };
@@ -0,0 +1,104 @@
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+/// The work the renderer did in a frame.
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct FrameStats {
+    /// From the start of `begin_scene` until the frame was submitted
+    pub(crate) cpu_time: Duration,
+    pub(crate) draw_calls: usize,
+    /// Written to the vertex buffer, including instance data
+    pub(crate) vertex_bytes: usize,
+}
+
+/// Frame times and counts shown on top of the scene, drawn with one of the renderer's fonts.
+pub(crate) struct PerformanceOverlay {
+    pub(crate) font: usize,
+    /// Seconds between the starts of consecutive frames, oldest first
+    frame_times: VecDeque<f32>,
+    last_frame: Option<Instant>,
+}
+
+impl PerformanceOverlay {
+    /// Number of frames shown in the graph
+    pub(crate) const HISTORY: usize = 120;
+
+    pub(crate) fn new(font: usize) -> Self {
+        return Self {
+            font,
+            frame_times: VecDeque::with_capacity(Self::HISTORY),
+            last_frame: None,
+        };
+    }
+
+    pub(crate) fn frame_times(&self) -> impl Iterator<Item = f32> + '_ {
+        return self.frame_times.iter().copied();
+    }
+
+    /// Averaged over the history, or zero until two frames have started.
+    pub(crate) fn frames_per_second(&self) -> f32 {
+        let total: f32 = self.frame_times.iter().sum();
+        if total <= 0.0 {
+            return 0.0;
+        }
+
+        return self.frame_times.len() as f32 / total;
+    }
+
+    /// Called as each frame starts.
+    pub(crate) fn record_frame(&mut self, now: Instant) {
+        if let Some(last_frame) = self.last_frame {
+            if Self::HISTORY <= self.frame_times.len() {
+                self.frame_times.pop_front();
+            }
+            self.frame_times
+                .push_back(now.duration_since(last_frame).as_secs_f32());
+        }
+        self.last_frame = Some(now);
+    }
+
+    pub(crate) fn text(&self, stats: &FrameStats, textures: usize, texture_bytes: u64) -> String {
+        let mib = texture_bytes as f64 / (1024.0 * 1024.0);
+        return format!(
+            "{:.0} FPS\nCPU {:.2} ms\n{} draw calls\n{:.1} KiB of vertices\n{} textures, {:.2} MiB",
+            self.frames_per_second(),
+            1000.0 * stats.cpu_time.as_secs_f64(),
+            stats.draw_calls,
+            stats.vertex_bytes as f64 / 1024.0,
+            textures,
+            mib
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn record_frames() {
+        let mut overlay = PerformanceOverlay::new(0);
+        let start = Instant::now();
+        overlay.record_frame(start);
+        assert_eq!(overlay.frames_per_second(), 0.0);
+
+        let frame = Duration::from_millis(20);
+        for i in 1..=PerformanceOverlay::HISTORY as u32 + 10 {
+            overlay.record_frame(start + frame * i);
+        }
+        assert_eq!(overlay.frame_times().count(), PerformanceOverlay::HISTORY);
+        assert!((overlay.frames_per_second() - 50.0).abs() < 0.01);
+
+        let stats = FrameStats {
+            cpu_time: Duration::from_micros(1500),
+            draw_calls: 12,
+            vertex_bytes: 3072,
+        };
+        assert_eq!(
+            overlay.text(&stats, 3, 3 * 1024 * 1024),
+            "50 FPS\nCPU 1.50 ms\n12 draw calls\n3.0 KiB of vertices\n3 textures, 3.00 MiB"
+        );
+    }
+}
@@ -0,0 +1,187 @@
+use super::loader::{DeviceTable, InstanceTable};
+use crate::ffi::vk;
+use std::{
+    ffi::{c_void, OsString},
+    mem::MaybeUninit,
+    path::PathBuf,
+    ptr::{null, null_mut, NonNull},
+};
+
+/// Length of the header every implementation starts its cache data with.
+const HEADER_SIZE: usize = 16 + vk::UUID_SIZE;
+
+/// Identifies the driver a cache was written by. Data from any other driver is discarded rather
+/// than handed to the implementation.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct CacheHeader {
+    vendor_id: u32,
+    device_id: u32,
+    uuid: [u8; vk::UUID_SIZE],
+}
+
+impl CacheHeader {
+    fn matches(&self, data: &[u8]) -> bool {
+        if data.len() < HEADER_SIZE {
+            return false;
+        }
+
+        let word = |i: usize| {
+            let mut bytes = [0; 4];
+            bytes.copy_from_slice(&data[4 * i..4 * i + 4]);
+            u32::from_ne_bytes(bytes)
+        };
+        return word(0) as usize >= HEADER_SIZE
+            && word(1) == vk::PIPELINE_CACHE_HEADER_VERSION_ONE
+            && word(2) == self.vendor_id
+            && word(3) == self.device_id
+            && data[16..HEADER_SIZE] == self.uuid;
+    }
+}
+
+/// Pipelines built with this cache skip shader compilation when a previous run already compiled
+/// them. The cache is read at startup and written back at shutdown.
+pub(crate) struct PipelineCache {
+    cache: NonNull<vk::PipelineCache>,
+    /// `None` if no cache directory could be found, in which case nothing is saved
+    path: Option<PathBuf>,
+}
+
+impl PipelineCache {
+    pub(crate) fn new(
+        instance_table: &InstanceTable,
+        device_table: &DeviceTable,
+        physical_device: *mut vk::PhysicalDevice,
+        device: *mut vk::Device,
+    ) -> Self {
+        let mut properties = MaybeUninit::uninit();
+        (instance_table.get_physical_device_properties)(physical_device, properties.as_mut_ptr());
+        let properties: vk::PhysicalDeviceProperties = unsafe { properties.assume_init() };
+        let header = CacheHeader {
+            vendor_id: properties.vendor_id,
+            device_id: properties.device_id,
+            uuid: properties.pipeline_cache_uuid,
+        };
+
+        let path = cache_path(std::env::var_os("XDG_CACHE_HOME"), std::env::var_os("HOME"));
+        let data = match path.as_ref().map(std::fs::read) {
+            Some(Ok(data)) if header.matches(&data) => {
+                debug!("Loaded {} bytes of pipeline cache", data.len());
+                data
+            }
+            Some(Ok(_)) => {
+                info!("Discarding pipeline cache written by a different driver");
+                Vec::new()
+            }
+            _ => Vec::new(),
+        };
+
+        let info = vk::PipelineCacheCreateInfo {
+            stype: vk::StructureType::PipelineCacheCreateInfo,
+            next: null(),
+            flags: 0,
+            initial_data_size: data.len(),
+            initial_data: data.as_ptr() as *const c_void,
+        };
+        let mut cache = null_mut();
+        (device_table.create_pipeline_cache)(device, &info, null(), &mut cache);
+        let cache = NonNull::new(cache).expect("Failed to create pipeline cache!");
+
+        return Self { cache, path };
+    }
+
+    pub(crate) fn as_ptr(&self) -> *mut vk::PipelineCache {
+        return self.cache.as_ptr();
+    }
+
+    /// Saves the cache, then destroys it. Failing to save only costs compile time on the next run,
+    /// so errors are logged rather than returned.
+    pub(crate) fn destroy(self, device_table: &DeviceTable, device: *mut vk::Device) {
+        if let Some(path) = &self.path {
+            let mut size = 0;
+            (device_table.get_pipeline_cache_data)(device, self.as_ptr(), &mut size, null_mut());
+            let mut data = vec![0u8; size];
+            let result = (device_table.get_pipeline_cache_data)(
+                device,
+                self.as_ptr(),
+                &mut size,
+                data.as_mut_ptr() as *mut c_void,
+            );
+
+            if result == vk::Result::Success {
+                data.truncate(size);
+                // Written to a temporary file first so a crash never leaves a truncated cache
+                let temporary = path.with_extension("tmp");
+                let written = path
+                    .parent()
+                    .map_or(Ok(()), std::fs::create_dir_all)
+                    .and_then(|_| std::fs::write(&temporary, &data))
+                    .and_then(|_| std::fs::rename(&temporary, path));
+                match written {
+                    Ok(()) => debug!("Saved {} bytes of pipeline cache", data.len()),
+                    Err(e) => warn!("Failed to save pipeline cache to {:?}: {}", path, e),
+                }
+            }
+        }
+
+        (device_table.destroy_pipeline_cache)(device, self.as_ptr(), null());
+    }
+}
+
+/// Follows the XDG base directory specification, falling back to `~/.cache`.
+fn cache_path(xdg_cache_home: Option<OsString>, home: Option<OsString>) -> Option<PathBuf> {
+    let directory = match xdg_cache_home.map(PathBuf::from) {
+        // Relative paths are invalid and should be ignored
+        Some(path) if path.is_absolute() => path,
+        _ => PathBuf::from(home?).join(".cache"),
+    };
+    return Some(directory.join("thyrox").join("pipeline_cache"));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn header_bytes(vendor_id: u32, device_id: u32, uuid: [u8; vk::UUID_SIZE]) -> Vec<u8> {
+        let mut data = Vec::new();
+        data.extend_from_slice(&(HEADER_SIZE as u32).to_ne_bytes());
+        data.extend_from_slice(&vk::PIPELINE_CACHE_HEADER_VERSION_ONE.to_ne_bytes());
+        data.extend_from_slice(&vendor_id.to_ne_bytes());
+        data.extend_from_slice(&device_id.to_ne_bytes());
+        data.extend_from_slice(&uuid);
+        data.extend_from_slice(&[0xAB; 8]);
+        return data;
+    }
+
+    #[test]
+    fn header_must_match_driver() {
+        let header = CacheHeader {
+            vendor_id: 0x10DE,
+            device_id: 0x2484,
+            uuid: [7; vk::UUID_SIZE],
+        };
+
+        assert!(header.matches(&header_bytes(0x10DE, 0x2484, [7; vk::UUID_SIZE])));
+        assert!(!header.matches(&header_bytes(0x1002, 0x2484, [7; vk::UUID_SIZE])));
+        assert!(!header.matches(&header_bytes(0x10DE, 0x2485, [7; vk::UUID_SIZE])));
+        assert!(!header.matches(&header_bytes(0x10DE, 0x2484, [8; vk::UUID_SIZE])));
+        assert!(!header.matches(&header_bytes(0x10DE, 0x2484, [7; vk::UUID_SIZE])[..20]));
+        assert!(!header.matches(&[]));
+    }
+
+    #[test]
+    fn cache_directory() {
+        let path = |xdg: Option<&str>, home: Option<&str>| {
+            cache_path(xdg.map(OsString::from), home.map(OsString::from))
+        };
+
+        assert_eq!(
+            path(Some("/tmp/cache"), Some("/home/a")),
+            Some(PathBuf::from("/tmp/cache/thyrox/pipeline_cache"))
+        );
+        assert_eq!(
+            path(Some("relative"), Some("/home/a")),
+            Some(PathBuf::from("/home/a/.cache/thyrox/pipeline_cache"))
+        );
+        assert_eq!(path(None, None), None);
+    }
+}
@@ -0,0 +1,319 @@
+use super::{
+    allocator::{Allocation, Allocator},
+    begin_render_pass, bind_graphics_pipeline, bind_sampled_image_descriptor,
+    command_buffer_begin_secondary, create_framebuffer, create_image_view, create_render_pass,
+    descriptor_set_update_sampled_image,
+    loader::{DeviceTable, InstanceTable},
+    material::{Material, PipelineContext},
+    set_scissor_and_viewport, PerFrameResources, MBI,
+};
+use crate::ffi::vk;
+use std::ptr::{null, null_mut, NonNull};
+
+/// Full-screen effects applied to the scene before it is presented.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum PostEffect {
+    /// Spreads light from bright pixels. Parameters: luminance threshold, intensity and blur
+    /// radius in pixels.
+    Bloom,
+    /// Parameters: contrast, saturation and brightness, where 1 leaves the image unchanged.
+    ColourGrading,
+    /// Darkens the edges of the screen. Parameters: intensity, the radius darkening starts at
+    /// and the width it fades in over, relative to the distance from the centre to the sides.
+    Vignette,
+    /// Darkens rows like a CRT. Parameters: intensity and the distance between lines in pixels.
+    Scanlines,
+}
+
+impl PostEffect {
+    pub const ALL: [PostEffect; 4] = [
+        PostEffect::Bloom,
+        PostEffect::ColourGrading,
+        PostEffect::Vignette,
+        PostEffect::Scanlines,
+    ];
+
+    /// Subtle versions of each effect.
+    pub fn default_parameters(self) -> [f32; 4] {
+        return match self {
+            PostEffect::Bloom => [0.6, 0.8, 2.0, 0.0],
+            PostEffect::ColourGrading => [1.1, 1.2, 1.0, 0.0],
+            PostEffect::Vignette => [0.5, 0.6, 0.8, 0.0],
+            PostEffect::Scanlines => [0.25, 3.0, 0.0, 0.0],
+        };
+    }
+
+    fn material(self) -> &'static str {
+        return match self {
+            PostEffect::Bloom => "materials/bloom.material",
+            PostEffect::ColourGrading => "materials/colour_grading.material",
+            PostEffect::Vignette => "materials/vignette.material",
+            PostEffect::Scanlines => "materials/scanlines.material",
+        };
+    }
+}
+
+/// One step of the post-processing stack, which reads the output of the step before it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PostPass {
+    pub effect: PostEffect,
+    pub enabled: bool,
+    /// Passed to the shader as push constants, see `PostEffect` for their meaning
+    pub parameters: [f32; 4],
+}
+
+impl PostPass {
+    /// A disabled pass with the default parameters.
+    pub fn new(effect: PostEffect) -> Self {
+        return Self {
+            effect,
+            enabled: false,
+            parameters: effect.default_parameters(),
+        };
+    }
+}
+
+/// A colour image rendered to by one pass and sampled by the next.
+struct Target {
+    image: NonNull<vk::Image>,
+    allocation: Allocation,
+    view: *mut vk::ImageView,
+    framebuffer: *mut vk::Framebuffer,
+}
+
+/// While any pass is enabled the scene is rendered to an intermediate target instead of the
+/// swapchain image. Each pass then samples the previous target and renders to the other, with
+/// the last rendering to the swapchain image.
+pub(crate) struct PostProcess {
+    materials: Vec<(PostEffect, Material)>,
+    /// Leaves the target ready to be sampled. Compatible with the swapchain's post-processing
+    /// render pass, so the same pipelines can be used with both.
+    render_pass: *mut vk::RenderPass,
+    /// Renders the scene with the swapchain's sample count, resolving into the first target
+    scene_render_pass: *mut vk::RenderPass,
+    scene_framebuffer: *mut vk::Framebuffer,
+    /// Created when first needed, and released when the swapchain is recreated
+    targets: Vec<Target>,
+    extent: vk::Extent2D,
+}
+
+impl PostProcess {
+    /// Effects whose material fails to load are logged and skipped while drawing.
+    pub(crate) fn new(
+        device_table: &DeviceTable,
+        device: *mut vk::Device,
+        context: &PipelineContext,
+    ) -> Self {
+        let materials = PostEffect::ALL
+            .iter()
+            .filter_map(|&effect| {
+                match Material::load(device_table, device, effect.material(), context) {
+                    Ok(material) => Some((effect, material)),
+                    Err(e) => {
+                        warn!("Failed to load the {:?} material: {}", effect, e);
+                        None
+                    }
+                }
+            })
+            .collect();
+
+        return Self {
+            materials,
+            render_pass: null_mut(),
+            scene_render_pass: null_mut(),
+            scene_framebuffer: null_mut(),
+            targets: Vec::new(),
+            extent: vk::Extent2D {
+                width: 0,
+                height: 0,
+            },
+        };
+    }
+
+    /// The enabled passes that can be drawn, in order.
+    pub(crate) fn active<'a>(&'a self, passes: &'a [PostPass]) -> Vec<(PostPass, &'a Material)> {
+        return passes
+            .iter()
+            .filter(|pass| pass.enabled)
+            .filter_map(|pass| {
+                let material = self.materials.iter().find(|(e, _)| *e == pass.effect)?;
+                Some((*pass, &material.1))
+            })
+            .collect();
+    }
+
+    pub(crate) fn materials_mut(&mut self) -> impl Iterator<Item = &mut Material> {
+        return self.materials.iter_mut().map(|(_, material)| material);
+    }
+
+    /// Creates the targets if they don't exist yet, returning the framebuffer the scene should be
+    /// rendered to. While multisampling, the scene is rendered to `multisampled` and resolved
+    /// into the first target.
+    pub(crate) fn prepare(
+        &mut self,
+        instance_table: &InstanceTable,
+        device_table: &DeviceTable,
+        allocator: &mut Allocator,
+        device: *mut vk::Device,
+        format: vk::Format,
+        extent: vk::Extent2D,
+        samples: vk::SampleCountFlagBits,
+        multisampled: Option<*mut vk::ImageView>,
+    ) -> (*mut vk::RenderPass, *mut vk::Framebuffer) {
+        if self.targets.is_empty() {
+            self.render_pass = create_render_pass(
+                device_table,
+                device,
+                format,
+                vk::SampleCountFlagBits::One,
+                vk::ImageLayout::ShaderReadOnlyOptimal,
+            );
+            self.scene_render_pass = create_render_pass(
+                device_table,
+                device,
+                format,
+                samples,
+                vk::ImageLayout::ShaderReadOnlyOptimal,
+            );
+            for _ in 0..2 {
+                let MBI { image, allocation } = MBI::create(
+                    instance_table,
+                    device_table,
+                    allocator,
+                    device,
+                    format,
+                    extent,
+                    1,
+                    vk::SampleCountFlagBits::One,
+                    vk::ImageTiling::Optimal,
+                    vk::ImageUsageFlagBits::ColorAttachment as u32
+                        | vk::ImageUsageFlagBits::Sampled as u32,
+                    vk::ImageLayout::Undefined,
+                    vk::MemoryPropertyFlagBits::DeviceLocal as u32,
+                );
+                let view = create_image_view(device_table, device, image.as_ptr(), format, 1);
+                let framebuffer =
+                    create_framebuffer(device_table, device, self.render_pass, &[view], extent);
+                self.targets.push(Target {
+                    image,
+                    allocation,
+                    view,
+                    framebuffer,
+                });
+            }
+            let attachments: Vec<_> = multisampled
+                .into_iter()
+                .chain(Some(self.targets[0].view))
+                .collect();
+            self.scene_framebuffer = create_framebuffer(
+                device_table,
+                device,
+                self.scene_render_pass,
+                &attachments,
+                extent,
+            );
+            self.extent = extent;
+        }
+
+        return (self.scene_render_pass, self.scene_framebuffer);
+    }
+
+    /// Records the passes after the scene's render pass has ended, finishing with a render pass
+    /// on `framebuffer` of the swapchain. The `overlay` secondaries are executed after the last
+    /// pass, in the same render pass.
+    pub(crate) fn record(
+        &self,
+        device_table: &DeviceTable,
+        device: *mut vk::Device,
+        resources: &mut PerFrameResources,
+        passes: &[(PostPass, &Material)],
+        sampler: *mut vk::Sampler,
+        render_pass: *mut vk::RenderPass,
+        framebuffer: *mut vk::Framebuffer,
+        overlay: &[*mut vk::CommandBuffer],
+    ) {
+        for (i, (pass, material)) in passes.iter().enumerate() {
+            let input = &self.targets[i % 2];
+            let (render_pass, framebuffer) = match passes.get(i + 1) {
+                Some(_) => (self.render_pass, self.targets[(i + 1) % 2].framebuffer),
+                None => (render_pass, framebuffer),
+            };
+
+            let index = resources.allocate_descriptors_and_secondaries(
+                device_table,
+                device,
+                material.set_layout,
+                1,
+            )[0];
+            let set = resources.descriptor_sets[index];
+            descriptor_set_update_sampled_image(device_table, device, set, input.view, sampler);
+
+            let secondary = resources.secondaries[index];
+            command_buffer_begin_secondary(
+                device_table,
+                secondary,
+                render_pass,
+                vk::CommandBufferUsageFlagBits::OneTimeSubmit as u32,
+            );
+            set_scissor_and_viewport(device_table, secondary, self.extent);
+            bind_graphics_pipeline(device_table, secondary, material.pipeline);
+            bind_sampled_image_descriptor(device_table, secondary, material.pipeline_layout, set);
+            (device_table.cmd_push_constants)(
+                secondary,
+                material.pipeline_layout,
+                vk::ShaderStageFlagBits::Fragment as u32,
+                0,
+                std::mem::size_of::<[f32; 4]>() as u32,
+                pass.parameters.as_ptr() as *const _,
+            );
+            (device_table.cmd_draw)(secondary, 3, 1, 0, 0);
+            (device_table.end_command_buffer)(secondary);
+
+            // Every pixel is overwritten, so the clear colour doesn't matter
+            begin_render_pass(
+                device_table,
+                resources.primary,
+                render_pass,
+                framebuffer,
+                self.extent,
+                [0.0, 0.0, 0.0, 1.0],
+            );
+            (device_table.cmd_execute_commands)(resources.primary, 1, &secondary);
+            if i + 1 == passes.len() && !overlay.is_empty() {
+                (device_table.cmd_execute_commands)(
+                    resources.primary,
+                    overlay.len() as u32,
+                    overlay.as_ptr(),
+                );
+            }
+            (device_table.cmd_end_render_pass)(resources.primary);
+        }
+    }
+
+    /// Destroys the targets, which must no longer be in use. They are recreated by `prepare`.
+    pub(crate) fn release(
+        &mut self,
+        device_table: &DeviceTable,
+        allocator: &mut Allocator,
+        device: *mut vk::Device,
+    ) {
+        if !self.scene_framebuffer.is_null() {
+            (device_table.destroy_framebuffer)(device, self.scene_framebuffer, null());
+            self.scene_framebuffer = null_mut();
+        }
+
+        for target in self.targets.drain(..) {
+            (device_table.destroy_framebuffer)(device, target.framebuffer, null());
+            (device_table.destroy_image_view)(device, target.view, null());
+            (device_table.destroy_image)(device, target.image.as_ptr(), null());
+            allocator.free(device_table, target.allocation);
+        }
+
+        for render_pass in [&mut self.render_pass, &mut self.scene_render_pass] {
+            if !render_pass.is_null() {
+                (device_table.destroy_render_pass)(device, *render_pass, null());
+                *render_pass = null_mut();
+            }
+        }
+    }
+}
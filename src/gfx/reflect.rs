@@ -0,0 +1,562 @@
+use crate::ffi::vk;
+use std::collections::HashMap;
+
+const MAGIC: u32 = 0x07230203;
+const HEADER_LENGTH: usize = 5;
+
+// Opcodes
+const OP_ENTRY_POINT: u32 = 15;
+const OP_TYPE_INT: u32 = 21;
+const OP_TYPE_FLOAT: u32 = 22;
+const OP_TYPE_VECTOR: u32 = 23;
+const OP_TYPE_MATRIX: u32 = 24;
+const OP_TYPE_IMAGE: u32 = 25;
+const OP_TYPE_SAMPLER: u32 = 26;
+const OP_TYPE_SAMPLED_IMAGE: u32 = 27;
+const OP_TYPE_ARRAY: u32 = 28;
+const OP_TYPE_STRUCT: u32 = 30;
+const OP_TYPE_POINTER: u32 = 32;
+const OP_CONSTANT: u32 = 43;
+const OP_VARIABLE: u32 = 59;
+const OP_DECORATE: u32 = 71;
+const OP_MEMBER_DECORATE: u32 = 72;
+
+// Decorations
+const DECORATION_BUFFER_BLOCK: u32 = 3;
+const DECORATION_ARRAY_STRIDE: u32 = 6;
+const DECORATION_MATRIX_STRIDE: u32 = 7;
+const DECORATION_BUILT_IN: u32 = 11;
+const DECORATION_LOCATION: u32 = 30;
+const DECORATION_BINDING: u32 = 33;
+const DECORATION_DESCRIPTOR_SET: u32 = 34;
+const DECORATION_OFFSET: u32 = 35;
+
+// Storage classes
+const STORAGE_UNIFORM_CONSTANT: u32 = 0;
+const STORAGE_INPUT: u32 = 1;
+const STORAGE_UNIFORM: u32 = 2;
+const STORAGE_OUTPUT: u32 = 3;
+const STORAGE_PUSH_CONSTANT: u32 = 9;
+const STORAGE_STORAGE_BUFFER: u32 = 12;
+
+// Execution models
+const EXECUTION_MODEL_VERTEX: u32 = 0;
+const EXECUTION_MODEL_FRAGMENT: u32 = 4;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) enum ReflectError {
+    /// Not a whole number of words, or missing the SPIR-V magic number
+    NotSpirV,
+    /// An instruction runs past the end of the module
+    Truncated,
+    MissingEntryPoint,
+    /// Only vertex and fragment shaders are used by materials
+    UnsupportedStage(u32),
+    /// A stage interface variable at the given location isn't a scalar or vector
+    UnsupportedInterface(u32),
+    /// The result id of a type that couldn't be resolved
+    UnknownType(u32),
+}
+
+impl std::fmt::Display for ReflectError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        return match self {
+            Self::NotSpirV => write!(f, "not a SPIR-V module"),
+            Self::Truncated => write!(f, "truncated instruction"),
+            Self::MissingEntryPoint => write!(f, "no entry point"),
+            Self::UnsupportedStage(model) => write!(f, "unsupported execution model {}", model),
+            Self::UnsupportedInterface(location) => write!(
+                f,
+                "interface variable at location {} isn't a scalar or vector",
+                location
+            ),
+            Self::UnknownType(id) => write!(f, "unknown type %{}", id),
+        };
+    }
+}
+
+impl std::error::Error for ReflectError {}
+
+/// Numeric type of the components of a variable, or of the texels an image is sampled as.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) enum ScalarKind {
+    Float,
+    Int,
+    Uint,
+}
+
+/// A scalar or vector passed between stages or read from a vertex buffer.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) struct InterfaceType {
+    pub(crate) kind: ScalarKind,
+    /// Bits per component
+    pub(crate) width: u32,
+    pub(crate) components: u32,
+}
+
+impl InterfaceType {
+    /// The vertex attribute format of a 32-bit scalar or vector.
+    pub(crate) fn format(&self) -> Option<vk::Format> {
+        use vk::Format::*;
+        let format = match (self.kind, self.width, self.components) {
+            (ScalarKind::Float, 32, 1) => R32SFLOAT,
+            (ScalarKind::Float, 32, 2) => R32G32SFLOAT,
+            (ScalarKind::Float, 32, 3) => R32G32B32SFLOAT,
+            (ScalarKind::Float, 32, 4) => R32G32B32A32SFLOAT,
+            (ScalarKind::Int, 32, 1) => R32SINT,
+            (ScalarKind::Int, 32, 2) => R32G32SINT,
+            (ScalarKind::Int, 32, 3) => R32G32B32SINT,
+            (ScalarKind::Int, 32, 4) => R32G32B32A32SINT,
+            (ScalarKind::Uint, 32, 1) => R32UINT,
+            (ScalarKind::Uint, 32, 2) => R32G32UINT,
+            (ScalarKind::Uint, 32, 3) => R32G32B32UINT,
+            (ScalarKind::Uint, 32, 4) => R32G32B32A32UINT,
+            _ => return None,
+        };
+        return Some(format);
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) struct Interface {
+    pub(crate) location: u32,
+    pub(crate) ty: InterfaceType,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) struct Binding {
+    pub(crate) set: u32,
+    pub(crate) binding: u32,
+    pub(crate) descriptor_type: vk::DescriptorType,
+    pub(crate) count: u32,
+    /// What sampled images return, as `sampler2D`, `isampler2D` and `usampler2D` differ
+    pub(crate) sampled: Option<ScalarKind>,
+}
+
+/// The resources a shader module uses, as declared in its SPIR-V.
+#[derive(Clone, Debug)]
+pub(crate) struct Reflection {
+    pub(crate) stage: vk::ShaderStageFlagBits,
+    /// Sorted by location, without built-in variables
+    pub(crate) inputs: Vec<Interface>,
+    pub(crate) outputs: Vec<Interface>,
+    /// Sorted by set, then binding
+    pub(crate) bindings: Vec<Binding>,
+    /// Bytes of push constants used, 0 if there are none
+    pub(crate) push_constant_size: u32,
+}
+
+enum Type {
+    Scalar(ScalarKind, u32),
+    Vector(u32, u32),
+    Matrix(u32, u32),
+    Image { sampled_type: u32, sampled: u32 },
+    Sampler,
+    SampledImage(u32),
+    Array(u32, u32),
+    Struct(Vec<u32>),
+    Pointer(u32),
+}
+
+#[derive(Default)]
+struct Decorations {
+    location: Option<u32>,
+    binding: Option<u32>,
+    set: Option<u32>,
+    built_in: bool,
+    buffer_block: bool,
+    array_stride: Option<u32>,
+}
+
+#[derive(Default)]
+struct MemberDecorations {
+    offset: u32,
+    matrix_stride: Option<u32>,
+    built_in: bool,
+}
+
+/// A parsed module, holding only what reflection needs.
+struct Module {
+    execution_model: Option<u32>,
+    types: HashMap<u32, Type>,
+    constants: HashMap<u32, u32>,
+    decorations: HashMap<u32, Decorations>,
+    member_decorations: HashMap<(u32, u32), MemberDecorations>,
+    /// Result type, result id and storage class of global variables
+    variables: Vec<(u32, u32, u32)>,
+}
+
+impl Module {
+    fn parse(code: &[u32]) -> Result<Self, ReflectError> {
+        if code.len() < HEADER_LENGTH || code[0] != MAGIC {
+            return Err(ReflectError::NotSpirV);
+        }
+
+        let mut module = Self {
+            execution_model: None,
+            types: HashMap::new(),
+            constants: HashMap::new(),
+            decorations: HashMap::new(),
+            member_decorations: HashMap::new(),
+            variables: Vec::new(),
+        };
+
+        let mut offset = HEADER_LENGTH;
+        while offset < code.len() {
+            let word_count = (code[offset] >> 16) as usize;
+            let opcode = code[offset] & 0xFFFF;
+            if word_count == 0 || code.len() < offset + word_count {
+                return Err(ReflectError::Truncated);
+            }
+
+            // Operands, after the opcode
+            let ops = &code[offset + 1..offset + word_count];
+            offset += word_count;
+            let operand = |i: usize| ops.get(i).copied().ok_or(ReflectError::Truncated);
+
+            match opcode {
+                OP_ENTRY_POINT if module.execution_model.is_none() => {
+                    module.execution_model = Some(operand(0)?);
+                }
+                OP_TYPE_INT => {
+                    let kind = match operand(2)? {
+                        0 => ScalarKind::Uint,
+                        _ => ScalarKind::Int,
+                    };
+                    module
+                        .types
+                        .insert(operand(0)?, Type::Scalar(kind, operand(1)?));
+                }
+                OP_TYPE_FLOAT => {
+                    let float = Type::Scalar(ScalarKind::Float, operand(1)?);
+                    module.types.insert(operand(0)?, float);
+                }
+                OP_TYPE_VECTOR => {
+                    let vector = Type::Vector(operand(1)?, operand(2)?);
+                    module.types.insert(operand(0)?, vector);
+                }
+                OP_TYPE_MATRIX => {
+                    let matrix = Type::Matrix(operand(1)?, operand(2)?);
+                    module.types.insert(operand(0)?, matrix);
+                }
+                OP_TYPE_IMAGE => {
+                    let image = Type::Image {
+                        sampled_type: operand(1)?,
+                        sampled: operand(6)?,
+                    };
+                    module.types.insert(operand(0)?, image);
+                }
+                OP_TYPE_SAMPLER => {
+                    module.types.insert(operand(0)?, Type::Sampler);
+                }
+                OP_TYPE_SAMPLED_IMAGE => {
+                    let sampled_image = Type::SampledImage(operand(1)?);
+                    module.types.insert(operand(0)?, sampled_image);
+                }
+                OP_TYPE_ARRAY => {
+                    let array = Type::Array(operand(1)?, operand(2)?);
+                    module.types.insert(operand(0)?, array);
+                }
+                OP_TYPE_STRUCT => {
+                    let members = Type::Struct(ops.get(1..).unwrap_or(&[]).to_vec());
+                    module.types.insert(operand(0)?, members);
+                }
+                OP_TYPE_POINTER => {
+                    module.types.insert(operand(0)?, Type::Pointer(operand(2)?));
+                }
+                OP_CONSTANT => {
+                    // Only the low word matters, as constants are only read for array lengths
+                    module.constants.insert(operand(1)?, operand(2)?);
+                }
+                OP_VARIABLE => {
+                    module
+                        .variables
+                        .push((operand(0)?, operand(1)?, operand(2)?));
+                }
+                OP_DECORATE => {
+                    let decorations = module.decorations.entry(operand(0)?).or_default();
+                    match operand(1)? {
+                        DECORATION_BUFFER_BLOCK => decorations.buffer_block = true,
+                        DECORATION_ARRAY_STRIDE => decorations.array_stride = Some(operand(2)?),
+                        DECORATION_BUILT_IN => decorations.built_in = true,
+                        DECORATION_LOCATION => decorations.location = Some(operand(2)?),
+                        DECORATION_BINDING => decorations.binding = Some(operand(2)?),
+                        DECORATION_DESCRIPTOR_SET => decorations.set = Some(operand(2)?),
+                        _ => {}
+                    }
+                }
+                OP_MEMBER_DECORATE => {
+                    let key = (operand(0)?, operand(1)?);
+                    let decorations = module.member_decorations.entry(key).or_default();
+                    match operand(2)? {
+                        DECORATION_OFFSET => decorations.offset = operand(3)?,
+                        DECORATION_MATRIX_STRIDE => decorations.matrix_stride = Some(operand(3)?),
+                        DECORATION_BUILT_IN => decorations.built_in = true,
+                        _ => {}
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        return Ok(module);
+    }
+
+    fn ty(&self, id: u32) -> Result<&Type, ReflectError> {
+        return self.types.get(&id).ok_or(ReflectError::UnknownType(id));
+    }
+
+    /// Whether the variable is built in, like `gl_Position`, or a block of built-ins, like
+    /// `gl_PerVertex`.
+    fn is_built_in(&self, variable: u32, pointee: u32) -> bool {
+        let decorated = |id| self.decorations.get(&id).map_or(false, |d| d.built_in);
+        let member_decorated = self
+            .member_decorations
+            .iter()
+            .any(|(&(id, _), d)| id == pointee && d.built_in);
+        return decorated(variable) || member_decorated;
+    }
+
+    fn interface_type(&self, id: u32, location: u32) -> Result<InterfaceType, ReflectError> {
+        return match self.ty(id)? {
+            &Type::Scalar(kind, width) => Ok(InterfaceType {
+                kind,
+                width,
+                components: 1,
+            }),
+            &Type::Vector(component, components) => match self.ty(component)? {
+                &Type::Scalar(kind, width) => Ok(InterfaceType {
+                    kind,
+                    width,
+                    components,
+                }),
+                _ => Err(ReflectError::UnsupportedInterface(location)),
+            },
+            _ => Err(ReflectError::UnsupportedInterface(location)),
+        };
+    }
+
+    /// Descriptor type, descriptor count and sampled component type of a resource variable.
+    fn descriptor(
+        &self,
+        id: u32,
+        storage: u32,
+    ) -> Result<(vk::DescriptorType, u32, Option<ScalarKind>), ReflectError> {
+        let sampled_kind = |image| match self.ty(image)? {
+            &Type::Image { sampled_type, .. } => match self.ty(sampled_type)? {
+                &Type::Scalar(kind, _) => Ok(Some(kind)),
+                _ => Err(ReflectError::UnknownType(sampled_type)),
+            },
+            _ => Err(ReflectError::UnknownType(image)),
+        };
+
+        let descriptor = match self.ty(id)? {
+            &Type::Array(element, length) => {
+                let (descriptor_type, count, sampled) = self.descriptor(element, storage)?;
+                let length = self.constants.get(&length).copied().unwrap_or(1);
+                (descriptor_type, count * length, sampled)
+            }
+            &Type::SampledImage(image) => (
+                vk::DescriptorType::CombinedImageSampler,
+                1,
+                sampled_kind(image)?,
+            ),
+            &Type::Image { sampled: 2, .. } => (vk::DescriptorType::StorageImage, 1, None),
+            Type::Image { .. } => (vk::DescriptorType::SamplerImage, 1, sampled_kind(id)?),
+            Type::Sampler => (vk::DescriptorType::Sampler, 1, None),
+            Type::Struct(_) => {
+                let buffer_block = self.decorations.get(&id).map_or(false, |d| d.buffer_block);
+                if storage == STORAGE_STORAGE_BUFFER || buffer_block {
+                    (vk::DescriptorType::StorageBuffer, 1, None)
+                } else {
+                    (vk::DescriptorType::UniformBuffer, 1, None)
+                }
+            }
+            _ => return Err(ReflectError::UnknownType(id)),
+        };
+        return Ok(descriptor);
+    }
+
+    /// Bytes occupied by a value of the type, following its explicit layout decorations.
+    fn size(&self, id: u32, matrix_stride: Option<u32>) -> Result<u32, ReflectError> {
+        let size = match self.ty(id)? {
+            &Type::Scalar(_, width) => width / 8,
+            &Type::Vector(component, count) => count * self.size(component, None)?,
+            &Type::Matrix(column, count) => match matrix_stride {
+                Some(stride) => count * stride,
+                None => count * self.size(column, None)?,
+            },
+            &Type::Array(element, length) => {
+                let length = self.constants.get(&length).copied().unwrap_or(1);
+                let stride = self.decorations.get(&id).and_then(|d| d.array_stride);
+                match stride {
+                    Some(stride) => length * stride,
+                    None => length * self.size(element, None)?,
+                }
+            }
+            Type::Struct(members) => {
+                let mut size = 0;
+                for (i, &member) in members.iter().enumerate() {
+                    let decorations = self.member_decorations.get(&(id, i as u32));
+                    let offset = decorations.map_or(0, |d| d.offset);
+                    let stride = decorations.and_then(|d| d.matrix_stride);
+                    size = size.max(offset + self.size(member, stride)?);
+                }
+                size
+            }
+            _ => return Err(ReflectError::UnknownType(id)),
+        };
+        return Ok(size);
+    }
+}
+
+/// Reads a SPIR-V file into words, swapping their bytes if it was written with the other
+/// endianness.
+pub(crate) fn words(bytes: &[u8]) -> Result<Vec<u32>, ReflectError> {
+    if bytes.len() % 4 != 0 {
+        return Err(ReflectError::NotSpirV);
+    }
+
+    let mut words: Vec<u32> = bytes
+        .chunks_exact(4)
+        .map(|word| u32::from_ne_bytes([word[0], word[1], word[2], word[3]]))
+        .collect();
+    match words.first() {
+        Some(&MAGIC) => {}
+        Some(&magic) if magic == MAGIC.swap_bytes() => {
+            words.iter_mut().for_each(|word| *word = word.swap_bytes());
+        }
+        _ => return Err(ReflectError::NotSpirV),
+    }
+    return Ok(words);
+}
+
+/// Finds the stage, interface variables, descriptor bindings and push constants of a module with
+/// a single entry point.
+pub(crate) fn reflect(code: &[u32]) -> Result<Reflection, ReflectError> {
+    let module = Module::parse(code)?;
+    let stage = match module.execution_model {
+        Some(EXECUTION_MODEL_VERTEX) => vk::ShaderStageFlagBits::Vertex,
+        Some(EXECUTION_MODEL_FRAGMENT) => vk::ShaderStageFlagBits::Fragment,
+        Some(model) => return Err(ReflectError::UnsupportedStage(model)),
+        None => return Err(ReflectError::MissingEntryPoint),
+    };
+
+    let mut reflection = Reflection {
+        stage,
+        inputs: Vec::new(),
+        outputs: Vec::new(),
+        bindings: Vec::new(),
+        push_constant_size: 0,
+    };
+    for &(pointer, variable, storage) in module.variables.iter() {
+        let pointee = match module.ty(pointer)? {
+            &Type::Pointer(pointee) => pointee,
+            _ => return Err(ReflectError::UnknownType(pointer)),
+        };
+        let decorations = module.decorations.get(&variable);
+
+        match storage {
+            STORAGE_INPUT | STORAGE_OUTPUT if !module.is_built_in(variable, pointee) => {
+                let location = match decorations.and_then(|d| d.location) {
+                    Some(location) => location,
+                    None => continue,
+                };
+                let interface = Interface {
+                    location,
+                    ty: module.interface_type(pointee, location)?,
+                };
+                match storage {
+                    STORAGE_INPUT => reflection.inputs.push(interface),
+                    _ => reflection.outputs.push(interface),
+                }
+            }
+            STORAGE_UNIFORM_CONSTANT | STORAGE_UNIFORM | STORAGE_STORAGE_BUFFER => {
+                let (descriptor_type, count, sampled) = module.descriptor(pointee, storage)?;
+                reflection.bindings.push(Binding {
+                    set: decorations.and_then(|d| d.set).unwrap_or(0),
+                    binding: decorations.and_then(|d| d.binding).unwrap_or(0),
+                    descriptor_type,
+                    count,
+                    sampled,
+                });
+            }
+            STORAGE_PUSH_CONSTANT => {
+                let size = module.size(pointee, None)?;
+                reflection.push_constant_size = reflection.push_constant_size.max(size);
+            }
+            _ => {}
+        }
+    }
+
+    reflection.inputs.sort_by_key(|input| input.location);
+    reflection.outputs.sort_by_key(|output| output.location);
+    reflection
+        .bindings
+        .sort_by_key(|binding| (binding.set, binding.binding));
+    return Ok(reflection);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reflect_file(bytes: &[u8]) -> Reflection {
+        return reflect(&words(bytes).unwrap()).unwrap();
+    }
+
+    fn vector(components: u32) -> InterfaceType {
+        return InterfaceType {
+            kind: ScalarKind::Float,
+            width: 32,
+            components,
+        };
+    }
+
+    #[test]
+    fn vertex_interface() {
+        let reflection = reflect_file(include_bytes!("../../shaders/text.vert.spv"));
+        let locations = |interfaces: &[Interface]| -> Vec<(u32, InterfaceType)> {
+            return interfaces.iter().map(|i| (i.location, i.ty)).collect();
+        };
+
+        assert!(matches!(reflection.stage, vk::ShaderStageFlagBits::Vertex));
+        assert_eq!(
+            locations(&reflection.inputs),
+            [(0, vector(2)), (1, vector(2)), (2, vector(4))]
+        );
+        // gl_Position is built in, so left out
+        assert_eq!(
+            locations(&reflection.outputs),
+            [(0, vector(2)), (1, vector(4))]
+        );
+        assert!(reflection.bindings.is_empty());
+        assert_eq!(reflection.push_constant_size, 0);
+    }
+
+    #[test]
+    fn fragment_resources() {
+        let reflection = reflect_file(include_bytes!("../../shaders/sdf_text.frag.spv"));
+
+        assert!(matches!(
+            reflection.stage,
+            vk::ShaderStageFlagBits::Fragment
+        ));
+        assert_eq!(reflection.bindings.len(), 1);
+        let binding = reflection.bindings[0];
+        assert_eq!((binding.set, binding.binding, binding.count), (0, 0, 1));
+        assert_eq!(binding.sampled, Some(ScalarKind::Float));
+        // Two vec4s, a vec2 and a float
+        assert_eq!(reflection.push_constant_size, 44);
+    }
+
+    #[test]
+    fn rejects_other_data() {
+        assert_eq!(words(&[1, 2, 3]), Err(ReflectError::NotSpirV));
+        assert_eq!(words(&[0; 8]), Err(ReflectError::NotSpirV));
+
+        // A header followed by an instruction claiming more words than remain
+        let truncated = [MAGIC, 0x0001_0000, 0, 1, 0, (4 << 16) | OP_TYPE_FLOAT, 1];
+        assert_eq!(reflect(&truncated).err(), Some(ReflectError::Truncated));
+    }
+}
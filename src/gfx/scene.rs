@@ -1,41 +1,282 @@
+use super::{text::DistanceFieldStyle, texture::SamplerOptions};
+use std::ops::Range;
+
+/// The pipeline a draw is recorded with. Draws on the same layer are grouped by it.
+#[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd)]
+pub(crate) enum MaterialKey {
+    /// Index into the renderer's loaded materials, or the sprite material if `None`
+    Sprite(Option<usize>),
+    InstancedSprite,
+    Primitive,
+    Text,
+    DistanceFieldText,
+}
+
 #[derive(Debug)]
-pub(crate) struct Glyph {
-    pub(crate) num_vertices: usize,
-    pub(crate) texture_index: usize,
-    pub(crate) offset: usize,
-    pub(crate) length: usize,
+pub(crate) enum DrawKind {
+    Sprite,
+    /// Drawn once per instance, with the instances' data in place of vertices
+    Instances {
+        count: usize,
+    },
+    /// Untextured triangles
+    Primitives {
+        num_vertices: usize,
+    },
+    Glyphs {
+        num_vertices: usize,
+        /// Set for fonts with distance field atlases, which are drawn with their own pipeline
+        distance_field: Option<DistanceFieldStyle>,
+    },
 }
 
 #[derive(Debug)]
-pub(crate) struct SpriteData {
-    pub(crate) index: usize,
+pub(crate) struct Draw {
+    /// Draws on higher layers cover those on lower ones
+    pub(crate) layer: i32,
+    pub(crate) material: MaterialKey,
+    /// `None` for primitives, which have no descriptors
+    pub(crate) texture_index: Option<usize>,
+    /// Overrides the sampler of the texture
+    pub(crate) sampler: Option<SamplerOptions>,
     pub(crate) offset: usize,
     pub(crate) length: usize,
+    pub(crate) kind: DrawKind,
 }
 
 #[derive(Debug, Default)]
 pub(crate) struct Scene {
-    pub(crate) sprite: Vec<SpriteData>,
-    pub(crate) glyphs: Vec<Glyph>,
+    pub(crate) clear_colour: [f32; 3],
+    pub(crate) image_index: u32,
+    pub(crate) draws: Vec<Draw>,
+    /// Drawn in order over the finished image, after post-processing
+    pub(crate) overlay: Vec<Draw>,
     pub(crate) data: Vec<u8>,
 }
 
 impl Scene {
-    pub(crate) fn insert_sprite(&mut self, sprite_data: &[f32], sprite_index: usize) {
+    pub(crate) fn insert_glyphs(
+        &mut self,
+        glyph_data: &[f32],
+        vertex_length: usize,
+        layer: i32,
+        texture_index: usize,
+        distance_field: Option<DistanceFieldStyle>,
+    ) {
+        if glyph_data.is_empty() {
+            return;
+        }
+
+        let (offset, length) = self.push_data(glyph_data);
+        let material = match distance_field {
+            Some(_) => MaterialKey::DistanceFieldText,
+            None => MaterialKey::Text,
+        };
+        let draw = Draw {
+            layer,
+            material,
+            texture_index: Some(texture_index),
+            sampler: None,
+            offset,
+            length,
+            kind: DrawKind::Glyphs {
+                num_vertices: glyph_data.len() / vertex_length,
+                distance_field,
+            },
+        };
+        self.draws.push(draw);
+    }
+
+    pub(crate) fn insert_instances(
+        &mut self,
+        instance_data: &[f32],
+        instance_length: usize,
+        layer: i32,
+        texture_index: usize,
+        sampler: Option<SamplerOptions>,
+    ) {
+        if instance_data.is_empty() {
+            return;
+        }
+
+        let (offset, length) = self.push_data(instance_data);
+        let draw = Draw {
+            layer,
+            material: MaterialKey::InstancedSprite,
+            texture_index: Some(texture_index),
+            sampler,
+            offset,
+            length,
+            kind: DrawKind::Instances {
+                count: instance_data.len() / instance_length,
+            },
+        };
+        self.draws.push(draw);
+    }
+
+    /// Primitives following others on the same layer are added to the same draw.
+    pub(crate) fn insert_primitives(
+        &mut self,
+        vertex_data: &[f32],
+        vertex_length: usize,
+        layer: i32,
+    ) {
+        if vertex_data.is_empty() {
+            return;
+        }
+
+        let end = self.data.len();
+        let (offset, length) = self.push_data(vertex_data);
+        let num_vertices = vertex_data.len() / vertex_length;
+        if let Some(draw) = self.draws.last_mut() {
+            if let DrawKind::Primitives {
+                num_vertices: previous,
+            } = &mut draw.kind
+            {
+                if draw.layer == layer && draw.offset + draw.length == end {
+                    *previous += num_vertices;
+                    draw.length += length;
+                    return;
+                }
+            }
+        }
+
+        let draw = Draw {
+            layer,
+            material: MaterialKey::Primitive,
+            texture_index: None,
+            sampler: None,
+            offset,
+            length,
+            kind: DrawKind::Primitives { num_vertices },
+        };
+        self.draws.push(draw);
+    }
+
+    pub(crate) fn insert_sprite(
+        &mut self,
+        sprite_data: &[f32],
+        layer: i32,
+        material: Option<usize>,
+        texture_index: usize,
+        sampler: Option<SamplerOptions>,
+    ) {
+        let (offset, length) = self.push_data(sprite_data);
+        let draw = Draw {
+            layer,
+            material: MaterialKey::Sprite(material),
+            texture_index: Some(texture_index),
+            sampler,
+            offset,
+            length,
+            kind: DrawKind::Sprite,
+        };
+        self.draws.push(draw);
+    }
+
+    /// Orders the draws by layer, then material, then texture. Draws that tie keep the order
+    /// they were inserted in.
+    pub(crate) fn sort(&mut self) {
+        self.draws
+            .sort_by_key(|draw| (draw.layer, draw.material, draw.texture_index));
+    }
+
+    /// Appends the raw bytes of `data` to the scene, returning their offset and length in bytes.
+    fn push_data(&mut self, data: &[f32]) -> (usize, usize) {
         let offset = self.data.len();
-        let len = sprite_data.len() * std::mem::size_of::<f32>();
+        let len = data.len() * std::mem::size_of::<f32>();
         let byte_slice = {
-            let slice =
-                unsafe { std::slice::from_raw_parts(sprite_data.as_ptr() as *const _, len) };
+            let slice = unsafe { std::slice::from_raw_parts(data.as_ptr() as *const _, len) };
             std::mem::ManuallyDrop::new(slice)
         };
 
-        let sprite_data = SpriteData {
-            index: sprite_index,
-            offset,
-            length: len,
-        };
-        self.sprite.push(sprite_data);
         self.data.extend_from_slice(&byte_slice);
+        return (offset, len);
+    }
+}
+
+/// Runs of consecutive draws with the same material, texture and sampler, which are recorded
+/// together with one descriptor set.
+pub(crate) fn batches(draws: &[Draw]) -> Vec<Range<usize>> {
+    let mut batches: Vec<Range<usize>> = Vec::new();
+    for (i, draw) in draws.iter().enumerate() {
+        if let Some(batch) = batches.last_mut() {
+            let first = &draws[batch.start];
+            if first.material == draw.material
+                && first.texture_index == draw.texture_index
+                && first.sampler == draw.sampler
+            {
+                batch.end = i + 1;
+                continue;
+            }
+        }
+        batches.push(i..i + 1);
+    }
+
+    return batches;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sort_draws() {
+        let mut scene = Scene::default();
+        scene.insert_glyphs(&[0.0; 4], 4, 1, 2, None);
+        scene.insert_sprite(&[1.0], 0, Some(0), 3, None);
+        scene.insert_sprite(&[2.0], 1, None, 1, None);
+        scene.insert_sprite(&[3.0], 0, None, 5, None);
+        scene.insert_sprite(&[4.0], 0, None, 4, None);
+        scene.insert_sprite(&[5.0], 0, None, 4, None);
+        scene.insert_instances(&[6.0; 4], 2, 0, 0, None);
+        scene.sort();
+
+        let offsets: Vec<_> = scene.draws.iter().map(|draw| draw.offset / 4).collect();
+        assert_eq!(offsets, [7, 8, 6, 4, 9, 5, 0]);
+    }
+
+    #[test]
+    fn batch_sprites() {
+        // More sprites than the descriptor sets in a pool, alternating between two textures
+        let mut scene = Scene::default();
+        for i in 0..100 {
+            scene.insert_sprite(&[i as f32], 0, None, i % 2, None);
+        }
+        scene.insert_sprite(&[0.0], 0, Some(0), 1, None);
+        scene.insert_glyphs(&[0.0; 4], 4, 0, 1, None);
+        scene.insert_sprite(&[0.0], 1, None, 1, None);
+        scene.sort();
+
+        let nearest = SamplerOptions {
+            filter: crate::ffi::vk::Filter::Nearest,
+            ..SamplerOptions::default()
+        };
+        scene.insert_sprite(&[0.0], 1, None, 1, Some(nearest));
+
+        assert_eq!(
+            batches(&scene.draws),
+            [0..50, 50..100, 100..101, 101..102, 102..103, 103..104]
+        );
+    }
+
+    #[test]
+    fn merge_primitives() {
+        let mut scene = Scene::default();
+        scene.insert_primitives(&[0.0; 4], 2, 0);
+        scene.insert_primitives(&[0.0; 6], 2, 0);
+        scene.insert_primitives(&[0.0; 2], 2, 1);
+        scene.insert_sprite(&[0.0], 1, None, 0, None);
+        scene.insert_primitives(&[0.0; 2], 2, 1);
+
+        let vertices: Vec<_> = scene
+            .draws
+            .iter()
+            .filter_map(|draw| match draw.kind {
+                DrawKind::Primitives { num_vertices } => Some((draw.offset / 4, num_vertices)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(vertices, [(0, 5), (10, 1), (13, 1)]);
     }
 }
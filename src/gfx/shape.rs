@@ -0,0 +1,269 @@
+use crate::{
+    ffi::vk,
+    math::{Vector2, Vector4},
+};
+
+/// Number of `f32`s making up a single primitive vertex: position and colour.
+pub(crate) const PRIMITIVE_VERTEX_LENGTH: usize = 6;
+
+/// Miters longer than this many half thicknesses are cut off, so sharp corners don't spike.
+const MITER_LIMIT: f32 = 4.0;
+
+/// How lines and shapes are drawn.
+#[derive(Clone, Copy, Debug)]
+pub struct ShapeStyle {
+    pub colour: Vector4,
+    /// Shapes are outlined if not filled. Lines are never filled.
+    pub filled: bool,
+    /// Of lines and outlines, in pixels
+    pub thickness: f32,
+    /// See `Transform::layer`
+    pub layer: i32,
+}
+
+impl ShapeStyle {
+    pub fn filled(colour: Vector4) -> Self {
+        return Self {
+            colour,
+            filled: true,
+            thickness: 1.0,
+            layer: 0,
+        };
+    }
+
+    pub fn outlined(colour: Vector4, thickness: f32) -> Self {
+        return Self {
+            colour,
+            filled: false,
+            thickness,
+            layer: 0,
+        };
+    }
+}
+
+/// Builds the triangles of untextured shapes. Points are given in normalized device
+/// coordinates, with the y-axis pointing up, but lines are placed in pixels so that their
+/// thickness is the same in every direction.
+pub(crate) struct ShapeBuilder {
+    /// Half the size of the render target in pixels
+    half_extent: (f32, f32),
+    pub(crate) data: Vec<f32>,
+}
+
+impl ShapeBuilder {
+    pub(crate) fn new(extent: vk::Extent2D) -> Self {
+        return Self {
+            half_extent: (0.5 * extent.width as f32, 0.5 * extent.height as f32),
+            data: Vec::new(),
+        };
+    }
+
+    /// Points around an ellipse that looks like a circle of `radius` pixels, with enough of them
+    /// that its edge looks smooth.
+    pub(crate) fn circle_points(&self, centre: Vector2, radius: f32) -> Vec<Vector2> {
+        let count = ((radius.max(0.0).sqrt() * 4.0) as usize).max(12).min(128);
+        return (0..count)
+            .map(|i| {
+                let angle = std::f32::consts::PI * 2.0 * i as f32 / count as f32;
+                let (sin, cos) = angle.sin_cos();
+                Vector2 {
+                    x: centre.x + radius * cos / self.half_extent.0,
+                    y: centre.y + radius * sin / self.half_extent.1,
+                }
+            })
+            .collect();
+    }
+
+    /// A quad `thickness` pixels wide, centred on the line from `start` to `end`.
+    pub(crate) fn line(&mut self, start: Vector2, end: Vector2, thickness: f32, colour: Vector4) {
+        let (start, end) = (self.to_pixels(start), self.to_pixels(end));
+        let normal = match normalize((start.1 - end.1, end.0 - start.0)) {
+            Some(normal) => scale(normal, 0.5 * thickness),
+            None => return,
+        };
+
+        let corners = [
+            add(start, normal),
+            sub(start, normal),
+            add(end, normal),
+            sub(end, normal),
+        ];
+        self.quad(corners, colour);
+    }
+
+    /// Fills convex polygons, or outlines any polygon with mitred corners. The polygon is closed
+    /// from its last point back to its first.
+    pub(crate) fn polygon(&mut self, points: &[Vector2], style: &ShapeStyle) {
+        if points.len() < 3 {
+            return;
+        }
+
+        let points: Vec<_> = points.iter().map(|&point| self.to_pixels(point)).collect();
+        if style.filled {
+            for i in 1..points.len() - 1 {
+                let triangle = [points[0], points[i], points[i + 1]];
+                for &point in triangle.iter() {
+                    self.vertex(point, style.colour);
+                }
+            }
+            return;
+        }
+
+        // Each corner is offset inside and outside by half the thickness along its miter
+        let half = 0.5 * style.thickness;
+        let count = points.len();
+        let offsets: Vec<_> = (0..count)
+            .map(|i| {
+                let previous = points[(i + count - 1) % count];
+                let next = points[(i + 1) % count];
+                miter(previous, points[i], next, half)
+            })
+            .collect();
+
+        for i in 0..count {
+            let j = (i + 1) % count;
+            let corners = [
+                add(points[i], offsets[i]),
+                sub(points[i], offsets[i]),
+                add(points[j], offsets[j]),
+                sub(points[j], offsets[j]),
+            ];
+            self.quad(corners, style.colour);
+        }
+    }
+
+    /// Two triangles, with the corners of each side of the quad in order.
+    fn quad(&mut self, corners: [(f32, f32); 4], colour: Vector4) {
+        for &i in [0, 1, 2, 1, 3, 2].iter() {
+            self.vertex(corners[i], colour);
+        }
+    }
+
+    fn to_pixels(&self, point: Vector2) -> (f32, f32) {
+        return (point.x * self.half_extent.0, point.y * self.half_extent.1);
+    }
+
+    /// Converts back from pixels into clip space, where the y-axis points down.
+    fn vertex(&mut self, point: (f32, f32), colour: Vector4) {
+        let colour: [f32; 4] = colour.into();
+        self.data.push(point.0 / self.half_extent.0);
+        self.data.push(-point.1 / self.half_extent.1);
+        self.data.extend_from_slice(&colour);
+    }
+}
+
+fn add(a: (f32, f32), b: (f32, f32)) -> (f32, f32) {
+    return (a.0 + b.0, a.1 + b.1);
+}
+
+/// Offset from `point` to the outside of the corner, for a line `half` as thick either side.
+fn miter(previous: (f32, f32), point: (f32, f32), next: (f32, f32), half: f32) -> (f32, f32) {
+    let normal = |from: (f32, f32), to: (f32, f32)| normalize((from.1 - to.1, to.0 - from.0));
+    let (before, after) = match (normal(previous, point), normal(point, next)) {
+        (Some(before), Some(after)) => (before, after),
+        (Some(normal), None) | (None, Some(normal)) => return scale(normal, half),
+        (None, None) => return (0.0, 0.0),
+    };
+
+    // Lines that double back have no miter, so fall back to the normal of the first
+    let direction = match normalize(add(before, after)) {
+        Some(direction) => direction,
+        None => return scale(before, half),
+    };
+    let cos = direction.0 * before.0 + direction.1 * before.1;
+    return scale(direction, (half / cos).min(MITER_LIMIT * half));
+}
+
+fn normalize(v: (f32, f32)) -> Option<(f32, f32)> {
+    let length = (v.0 * v.0 + v.1 * v.1).sqrt();
+    if length <= f32::EPSILON {
+        return None;
+    }
+
+    return Some((v.0 / length, v.1 / length));
+}
+
+fn scale(v: (f32, f32), factor: f32) -> (f32, f32) {
+    return (v.0 * factor, v.1 * factor);
+}
+
+fn sub(a: (f32, f32), b: (f32, f32)) -> (f32, f32) {
+    return (a.0 - b.0, a.1 - b.1);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WHITE: Vector4 = Vector4 {
+        x: 1.0,
+        y: 1.0,
+        z: 1.0,
+        w: 1.0,
+    };
+
+    fn positions(builder: &ShapeBuilder) -> Vec<(f32, f32)> {
+        return builder
+            .data
+            .chunks_exact(PRIMITIVE_VERTEX_LENGTH)
+            .map(|vertex| (vertex[0], vertex[1]))
+            .collect();
+    }
+
+    #[test]
+    fn line_thickness_is_in_pixels() {
+        let mut builder = ShapeBuilder::new(vk::Extent2D {
+            width: 200,
+            height: 100,
+        });
+        let start = Vector2 { x: -0.5, y: 0.0 };
+        let end = Vector2 { x: 0.5, y: 0.0 };
+        builder.line(start, end, 10.0, WHITE);
+
+        // 5 pixels either side is 0.1 of the half height, flipped into clip space
+        let expected = [
+            (-0.5, -0.1),
+            (-0.5, 0.1),
+            (0.5, -0.1),
+            (-0.5, 0.1),
+            (0.5, 0.1),
+            (0.5, -0.1),
+        ];
+        assert_eq!(positions(&builder), expected);
+
+        builder.line(start, start, 10.0, WHITE);
+        assert_eq!(builder.data.len(), 6 * PRIMITIVE_VERTEX_LENGTH);
+    }
+
+    #[test]
+    fn polygons() {
+        let extent = vk::Extent2D {
+            width: 100,
+            height: 100,
+        };
+        let square = [
+            Vector2 { x: -0.5, y: 0.5 },
+            Vector2 { x: 0.5, y: 0.5 },
+            Vector2 { x: 0.5, y: -0.5 },
+            Vector2 { x: -0.5, y: -0.5 },
+        ];
+
+        let mut builder = ShapeBuilder::new(extent);
+        builder.polygon(&square, &ShapeStyle::filled(WHITE));
+        assert_eq!(positions(&builder).len(), 2 * 3);
+
+        // Mitred corners sit on the diagonals, half the thickness from each edge
+        let mut builder = ShapeBuilder::new(extent);
+        builder.polygon(&square, &ShapeStyle::outlined(WHITE, 10.0));
+        let positions = positions(&builder);
+        assert_eq!(positions.len(), 4 * 6);
+        let (x, y) = positions[0];
+        assert!((x - -0.6).abs() < 1e-6 && (y - -0.6).abs() < 1e-6);
+        let (x, y) = positions[1];
+        assert!((x - -0.4).abs() < 1e-6 && (y - -0.4).abs() < 1e-6);
+
+        let mut builder = ShapeBuilder::new(extent);
+        builder.polygon(&square[..2], &ShapeStyle::filled(WHITE));
+        assert!(builder.data.is_empty());
+    }
+}
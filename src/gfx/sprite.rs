@@ -1,55 +1,114 @@
-use crate::{ffi::vk, math::Vector2};
+use crate::{
+    ffi::vk,
+    math::{Rectangle, Vector2, Vector4},
+};
+
+/// Number of `f32`s making up a single sprite vertex: position, texture coordinates and tint.
+pub(crate) const SPRITE_VERTEX_LENGTH: usize = 8;
+/// Number of `f32`s making up a single instance of an instanced sprite: the clip space position
+/// of its top-left corner, the axes along its top and left edges, its texture coordinates at the
+/// top-left and bottom-right corners, and tint.
+pub(crate) const SPRITE_INSTANCE_LENGTH: usize = 14;
+/// Corners of the two triangles making up every sprite, relative to its size.
+pub(crate) const UNIT_QUAD: [f32; 12] = [
+    0.0, 1.0, // Bottom-left vertex
+    0.0, 0.0, // Top-left vertex
+    1.0, 0.0, // Top-right vertex
+    0.0, 1.0, // Bottom-left vertex
+    1.0, 0.0, // Top-right vertex
+    1.0, 1.0, // Bottom-right vertex
+];
+
+/// Placement of a sprite. The sprite is scaled and rotated about `origin`, which is then moved to
+/// `position`.
+#[derive(Clone, Copy, Debug)]
+pub struct Transform {
+    /// In normalized device coordinates, with the y-axis pointing up
+    pub position: Vector2,
+    /// Counter-clockwise, in radians
+    pub rotation: f32,
+    /// Multiplier applied to the size of the source rectangle in pixels
+    pub scale: Vector2,
+    /// Relative to the sprite, from (0, 0) at the top-left corner to (1, 1) at the bottom-right
+    pub origin: Vector2,
+    /// Sprites and text on higher layers are drawn over those on lower ones, whatever order they
+    /// were drawn in
+    pub layer: i32,
+}
+
+impl Transform {
+    /// A sprite centred on `position`, drawn at its native size.
+    pub fn new(position: Vector2) -> Self {
+        return Self {
+            position,
+            rotation: 0.0,
+            scale: Vector2 { x: 1.0, y: 1.0 },
+            origin: Vector2 { x: 0.5, y: 0.5 },
+            layer: 0,
+        };
+    }
+}
+
+/// One copy of a sprite drawn by `Renderer::draw_instances`.
+#[derive(Clone, Copy, Debug)]
+pub struct SpriteInstance {
+    pub transform: Transform,
+    pub tint: Vector4,
+    /// A rectangle of the texture in pixels, or the whole texture if `None`
+    pub source: Option<Rectangle>,
+}
 
 pub(crate) struct Sprite {
     pub(crate) texture_index: usize,
     pub(crate) width: u32,
     pub(crate) height: u32,
+    /// Index into the renderer's loaded materials, drawn with the sprite material if `None`
+    pub(crate) material: Option<usize>,
 }
 
 impl Sprite {
+    /// Generates two triangles covering `source`, a rectangle of the texture in pixels, or the
+    /// whole texture if no rectangle is given.
     pub(crate) fn generate_vertex_data(
         &self,
-        position: Vector2,
+        transform: Transform,
+        tint: Vector4,
+        source: Option<Rectangle>,
+        extent: vk::Extent2D,
+    ) -> [f32; 6 * SPRITE_VERTEX_LENGTH] {
+        let quad = self.quad(transform, source, extent);
+        let tint: [f32; 4] = tint.into();
+        let mut data = [0.0; 6 * SPRITE_VERTEX_LENGTH];
+        for (chunk, corner) in data
+            .chunks_exact_mut(SPRITE_VERTEX_LENGTH)
+            .zip(UNIT_QUAD.chunks_exact(2))
+        {
+            let (u, v) = (corner[0], corner[1]);
+            chunk[0] = quad.origin[0] + u * quad.axes[0] + v * quad.axes[2];
+            chunk[1] = quad.origin[1] + u * quad.axes[1] + v * quad.axes[3];
+            chunk[2] = quad.source[0] + u * (quad.source[2] - quad.source[0]);
+            chunk[3] = quad.source[1] + v * (quad.source[3] - quad.source[1]);
+            chunk[4..8].copy_from_slice(&tint);
+        }
+        return data;
+    }
+
+    /// The data of a single instance, for drawing with the instanced sprite material. Takes the
+    /// same arguments as `generate_vertex_data`.
+    pub(crate) fn generate_instance_data(
+        &self,
+        transform: Transform,
+        tint: Vector4,
+        source: Option<Rectangle>,
         extent: vk::Extent2D,
-    ) -> [f32; 24] {
-        let (width, height) = self.pixels_to_ndc(extent);
-
-        let mut data = [0.0; 24];
-        // Bottom-left vertex
-        data[0] = position.x - width;
-        data[1] = -position.y + height;
-        data[2] = 0.0;
-        data[3] = 1.0;
-
-        // Top-left vertex
-        data[4] = position.x - width;
-        data[5] = -position.y - height;
-        data[6] = 0.0;
-        data[7] = 0.0;
-
-        // Top-right vertex
-        data[8] = position.x + width;
-        data[9] = -position.y - height;
-        data[10] = 1.0;
-        data[11] = 0.0;
-
-        // Bottom-left vertex
-        data[12] = position.x - width;
-        data[13] = -position.y + height;
-        data[14] = 0.0;
-        data[15] = 1.0;
-
-        // Top-right vertex
-        data[16] = position.x + width;
-        data[17] = -position.y - height;
-        data[18] = 1.0;
-        data[19] = 0.0;
-
-        // Bottom-right vertex
-        data[20] = position.x + width;
-        data[21] = -position.y + height;
-        data[22] = 1.0;
-        data[23] = 1.0;
+    ) -> [f32; SPRITE_INSTANCE_LENGTH] {
+        let quad = self.quad(transform, source, extent);
+        let mut data = [0.0; SPRITE_INSTANCE_LENGTH];
+        data[0..2].copy_from_slice(&quad.origin);
+        data[2..6].copy_from_slice(&quad.axes);
+        data[6..10].copy_from_slice(&quad.source);
+        let tint: [f32; 4] = tint.into();
+        data[10..14].copy_from_slice(&tint);
         return data;
     }
 
@@ -59,4 +118,57 @@ impl Sprite {
             self.height as f32 / extent.height as f32,
         );
     }
+
+    fn quad(&self, transform: Transform, source: Option<Rectangle>, extent: vk::Extent2D) -> Quad {
+        let source = source.unwrap_or(Rectangle {
+            left_top: Vector2 { x: 0.0, y: 0.0 },
+            right_bottom: Vector2 {
+                x: self.width as f32,
+                y: self.height as f32,
+            },
+        });
+        let width = (source.right_bottom.x - source.left_top.x) * transform.scale.x;
+        let height = (source.right_bottom.y - source.left_top.y) * transform.scale.y;
+        let (sin, cos) = transform.rotation.sin_cos();
+
+        // Corners are placed in pixels around the origin, so rotation keeps the aspect ratio
+        let corner = |u: f32, v: f32| {
+            let x = (u - transform.origin.x) * width;
+            let y = (transform.origin.y - v) * height;
+            let (x, y) = (x * cos - y * sin, x * sin + y * cos);
+
+            return [
+                transform.position.x + 2.0 * x / extent.width as f32,
+                -(transform.position.y + 2.0 * y / extent.height as f32),
+            ];
+        };
+
+        let origin = corner(0.0, 0.0);
+        let right = corner(1.0, 0.0);
+        let bottom = corner(0.0, 1.0);
+        return Quad {
+            origin,
+            axes: [
+                right[0] - origin[0],
+                right[1] - origin[1],
+                bottom[0] - origin[0],
+                bottom[1] - origin[1],
+            ],
+            source: [
+                source.left_top.x / self.width as f32,
+                source.left_top.y / self.height as f32,
+                source.right_bottom.x / self.width as f32,
+                source.right_bottom.y / self.height as f32,
+            ],
+        };
+    }
+}
+
+/// A sprite placed in clip space. Its corners are at `origin`, plus the first axis for the
+/// right-hand corners, plus the second axis for the bottom corners.
+struct Quad {
+    origin: [f32; 2],
+    axes: [f32; 4],
+    /// Texture coordinates of the top-left and bottom-right corners
+    source: [f32; 4],
 }
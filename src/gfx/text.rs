@@ -0,0 +1,156 @@
+use crate::{
+    ffi::vk,
+    font::{self, GlyphCache, LayoutOptions, TextAlignment, TextLayout, SDF_SPREAD},
+    math::{Vector2, Vector4},
+};
+
+/// Number of `f32`s making up a single text vertex: position, texture coordinates and colour.
+/// Texture coordinates are in atlas pixels, the text shader normalizes them.
+pub(crate) const TEXT_VERTEX_LENGTH: usize = 8;
+
+/// Outline drawn around distance field text. `width` is in pixels at the size the font was loaded
+/// at, and can't exceed the spread of the distance field.
+#[derive(Clone, Copy, Debug)]
+pub struct Outline {
+    pub width: f32,
+    pub colour: Vector4,
+}
+
+/// Drop shadow drawn behind distance field text. `offset` is in pixels at the size the font was
+/// loaded at, with the y-axis pointing up. Offsets beyond the spread of the distance field are
+/// clipped.
+#[derive(Clone, Copy, Debug)]
+pub struct Shadow {
+    pub offset: Vector2,
+    pub colour: Vector4,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct TextStyle {
+    pub colour: Vector4,
+    /// Multiplier applied to the size the font was loaded at. Only distance field fonts stay
+    /// sharp when scaled.
+    pub scale: f32,
+    /// Ignored for coverage fonts
+    pub outline: Option<Outline>,
+    /// Ignored for coverage fonts
+    pub shadow: Option<Shadow>,
+    /// See `Transform::layer`
+    pub layer: i32,
+}
+
+impl TextStyle {
+    pub fn new(colour: Vector4) -> Self {
+        return Self {
+            colour,
+            scale: 1.0,
+            outline: None,
+            shadow: None,
+            layer: 0,
+        };
+    }
+}
+
+/// Push constants of the distance field text shader. Distances are in the normalized units
+/// stored in the atlas, offsets in atlas pixels.
+#[derive(Clone, Copy, Debug)]
+#[repr(C)]
+pub(crate) struct DistanceFieldStyle {
+    outline_colour: [f32; 4],
+    shadow_colour: [f32; 4],
+    shadow_offset: [f32; 2],
+    outline_width: f32,
+    _padding: f32,
+}
+
+impl DistanceFieldStyle {
+    pub(crate) fn new(style: &TextStyle) -> Self {
+        let transparent = [0.0; 4];
+        let (outline_colour, outline_width) = match style.outline {
+            Some(outline) => {
+                let width = outline.width.clamp(0.0, SDF_SPREAD as f32);
+                (outline.colour.into(), 0.5 * width / SDF_SPREAD as f32)
+            }
+            None => (transparent, 0.0),
+        };
+        let (shadow_colour, shadow_offset) = match style.shadow {
+            Some(shadow) => (shadow.colour.into(), [shadow.offset.x, -shadow.offset.y]),
+            None => (transparent, [0.0; 2]),
+        };
+
+        return Self {
+            outline_colour,
+            shadow_colour,
+            shadow_offset,
+            outline_width,
+            _padding: 0.0,
+        };
+    }
+}
+
+pub(crate) struct Font {
+    pub(crate) texture_index: usize,
+    pub(crate) cache: GlyphCache,
+}
+
+impl Font {
+    pub(crate) fn layout(&mut self, text: &str, options: LayoutOptions) -> TextLayout {
+        return font::layout(&mut self.cache, text, options);
+    }
+
+    /// Generates two triangles per visible glyph of `layout`, scaled by `scale`. `position` is in
+    /// normalized device coordinates with the y-axis pointing up, and marks the vertical centre of
+    /// the block. Horizontally, it marks the left edge, centre or right edge of the block, based
+    /// on `alignment`.
+    pub(crate) fn generate_vertex_data(
+        layout: &TextLayout,
+        position: Vector2,
+        colour: Vector4,
+        scale: f32,
+        alignment: TextAlignment,
+        extent: vk::Extent2D,
+    ) -> Vec<f32> {
+        let to_ndc_x = |pixels: i32| 2.0 * scale * pixels as f32 / extent.width as f32;
+        let to_ndc_y = |pixels: i32| 2.0 * scale * pixels as f32 / extent.height as f32;
+
+        let block_width = to_ndc_x(layout.width);
+        let block_left = match alignment {
+            TextAlignment::Left => position.x,
+            TextAlignment::Centre => position.x - 0.5 * block_width,
+            TextAlignment::Right => position.x - block_width,
+        };
+        let block_top = -(position.y + 0.5 * to_ndc_y(layout.height));
+
+        let colour: [f32; 4] = colour.into();
+        let mut data = Vec::with_capacity(6 * TEXT_VERTEX_LENGTH * layout.glyphs.len());
+        for positioned in layout.glyphs.iter() {
+            let glyph = positioned.glyph;
+            if glyph.width == 0 || glyph.height == 0 {
+                continue;
+            }
+
+            let left = block_left + to_ndc_x(positioned.x);
+            let right = left + to_ndc_x(glyph.width as i32);
+            let top = block_top + to_ndc_y(positioned.y);
+            let bottom = top + to_ndc_y(glyph.height as i32);
+
+            let (u0, v0) = (glyph.x as f32, glyph.y as f32);
+            let (u1, v1) = (u0 + glyph.width as f32, v0 + glyph.height as f32);
+
+            let vertices = [
+                [left, bottom, u0, v1],  // Bottom-left vertex
+                [left, top, u0, v0],     // Top-left vertex
+                [right, top, u1, v0],    // Top-right vertex
+                [left, bottom, u0, v1],  // Bottom-left vertex
+                [right, top, u1, v0],    // Top-right vertex
+                [right, bottom, u1, v1], // Bottom-right vertex
+            ];
+            for vertex in vertices.iter() {
+                data.extend_from_slice(vertex);
+                data.extend_from_slice(&colour);
+            }
+        }
+
+        return data;
+    }
+}
@@ -0,0 +1,115 @@
+use super::{create_sampler, loader::DeviceTable};
+use crate::ffi::vk;
+use std::ptr::{null, NonNull};
+
+pub use crate::ffi::vk::{Filter, SamplerAddressMode as AddressMode};
+
+/// How a texture is filtered and addressed when sampled.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SamplerOptions {
+    /// `Nearest` keeps pixel art crisp, `Linear` suits smooth artwork
+    pub filter: Filter,
+    pub address_mode: AddressMode,
+    /// Maximum anisotropy, where 1 disables anisotropic filtering. Limited to what the device
+    /// supports.
+    pub anisotropy: f32,
+}
+
+impl Default for SamplerOptions {
+    fn default() -> Self {
+        return Self {
+            filter: Filter::Linear,
+            address_mode: AddressMode::ClampToEdge,
+            anisotropy: 1.0,
+        };
+    }
+}
+
+impl SamplerOptions {
+    /// Unfiltered sampling, for pixel art.
+    pub fn pixel_art() -> Self {
+        return Self {
+            filter: Filter::Nearest,
+            ..Self::default()
+        };
+    }
+}
+
+/// Settings a texture is loaded with.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TextureOptions {
+    /// Generates a full mip chain at upload time, so the texture stays clean when drawn smaller
+    pub mipmaps: bool,
+    /// Used unless a draw asks for a different sampler
+    pub sampler: SamplerOptions,
+}
+
+impl Default for TextureOptions {
+    fn default() -> Self {
+        return Self {
+            mipmaps: true,
+            sampler: SamplerOptions::default(),
+        };
+    }
+}
+
+/// Creates samplers on first use and shares them between every texture and draw using the same
+/// options.
+pub(crate) struct SamplerCache {
+    /// 1 if the device doesn't support anisotropic filtering
+    max_anisotropy: f32,
+    samplers: Vec<(SamplerOptions, NonNull<vk::Sampler>)>,
+}
+
+impl SamplerCache {
+    pub(crate) fn new(max_anisotropy: f32) -> Self {
+        return Self {
+            max_anisotropy,
+            samplers: Vec::new(),
+        };
+    }
+
+    pub(crate) fn destroy(&mut self, table: &DeviceTable, device: *mut vk::Device) {
+        for (_, sampler) in self.samplers.drain(..) {
+            (table.destroy_sampler)(device, sampler.as_ptr(), null());
+        }
+    }
+
+    pub(crate) fn get(
+        &mut self,
+        table: &DeviceTable,
+        device: *mut vk::Device,
+        options: SamplerOptions,
+    ) -> *mut vk::Sampler {
+        let options = SamplerOptions {
+            anisotropy: options.anisotropy.max(1.0).min(self.max_anisotropy),
+            ..options
+        };
+        if let Some((_, sampler)) = self.samplers.iter().find(|(o, _)| *o == options) {
+            return sampler.as_ptr();
+        }
+
+        let sampler = create_sampler(table, device, options).expect("Failed to create sampler!");
+        self.samplers.push((options, sampler));
+        return sampler.as_ptr();
+    }
+}
+
+/// Number of levels in a full mip chain, down to a single texel.
+pub(crate) fn mip_levels(width: u32, height: u32) -> u32 {
+    return 32 - width.max(height).max(1).leading_zeros();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn full_mip_chain() {
+        assert_eq!(mip_levels(1, 1), 1);
+        assert_eq!(mip_levels(256, 256), 9);
+        // Odd sizes round down at every level, so 300 has as many levels as 256
+        assert_eq!(mip_levels(300, 20), 9);
+        assert_eq!(mip_levels(20, 513), 10);
+    }
+}
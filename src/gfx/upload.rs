@@ -0,0 +1,638 @@
+use super::{
+    allocate_command_buffer, command_buffer_begin_primary, command_buffer_end_and_submit,
+    create_command_pool, create_fence, create_semaphore, fence_destroy,
+    loader::{DeviceTable, InstanceTable},
+    record_image_memory_barrier, record_image_mip_barrier, Allocator, Device, ImageMemoryBarrier,
+    GRAPHICS, MBB, TRANSFER,
+};
+use crate::ffi::vk;
+use std::{collections::VecDeque, ptr::null};
+
+/// Bytes of host visible memory reused for staging uploads. Larger uploads get a staging buffer
+/// of their own.
+const STAGING_RING_SIZE: vk::DeviceSize = 8 * 1024 * 1024;
+
+/// Offsets into the ring must suit `vkCmdCopyBufferToImage` for any texel size.
+const STAGING_ALIGNMENT: vk::DeviceSize = 16;
+
+/// Uploads recorded but not yet submitted.
+struct Recording {
+    transfer: *mut vk::CommandBuffer,
+    /// Acquires ownership on the graphics queue, when it belongs to a different family
+    acquire: Option<*mut vk::CommandBuffer>,
+    ring_bytes: vk::DeviceSize,
+    dedicated: Vec<MBB>,
+}
+
+struct Batch {
+    fence: *mut vk::Fence,
+    semaphore: Option<*mut vk::Semaphore>,
+    transfer: *mut vk::CommandBuffer,
+    acquire: Option<*mut vk::CommandBuffer>,
+    ring_end: vk::DeviceSize,
+    ring_bytes: vk::DeviceSize,
+    dedicated: Vec<MBB>,
+}
+
+/// Batches texture uploads onto the transfer queue. Uploads are recorded as they are requested,
+/// submitted together once per frame, and retired by `poll` once the GPU has finished them.
+pub(crate) struct UploadQueue {
+    staging: MBB,
+    ring: StagingRing,
+    transfer_pool: *mut vk::CommandPool,
+    /// Only needed when transfer and graphics queues belong to different families
+    graphics_pool: Option<*mut vk::CommandPool>,
+    recording: Option<Recording>,
+    in_flight: VecDeque<Batch>,
+}
+
+impl UploadQueue {
+    pub(crate) fn new(
+        instance_table: &InstanceTable,
+        device_table: &DeviceTable,
+        allocator: &mut Allocator,
+        device: &Device,
+    ) -> Self {
+        let staging = MBB::create(
+            instance_table,
+            device_table,
+            allocator,
+            **device,
+            STAGING_RING_SIZE,
+            vk::BufferUsageFlagBits::TransferSource as u32,
+            vk::MemoryPropertyFlagBits::HostCoherent as u32
+                | vk::MemoryPropertyFlagBits::HostVisible as u32,
+        );
+
+        let [graphics_family, transfer_family] = [
+            device.queue_family_indices[GRAPHICS],
+            device.queue_family_indices[TRANSFER],
+        ];
+        let transfer_pool = create_command_pool(device_table, **device, 0, transfer_family);
+        let graphics_pool = if graphics_family != transfer_family {
+            Some(create_command_pool(
+                device_table,
+                **device,
+                0,
+                graphics_family,
+            ))
+        } else {
+            None
+        };
+
+        return Self {
+            staging,
+            ring: StagingRing::new(STAGING_RING_SIZE),
+            transfer_pool,
+            graphics_pool,
+            recording: None,
+            in_flight: VecDeque::new(),
+        };
+    }
+
+    /// Releases everything after waiting for all outstanding uploads.
+    pub(crate) fn destroy(
+        mut self,
+        device_table: &DeviceTable,
+        device: &Device,
+        allocator: &mut Allocator,
+    ) {
+        self.finish(device_table, device, allocator);
+
+        (device_table.destroy_command_pool)(**device, self.transfer_pool, null());
+        if let Some(pool) = self.graphics_pool {
+            (device_table.destroy_command_pool)(**device, pool, null());
+        }
+        self.staging.destroy(device_table, **device, allocator);
+    }
+
+    /// Submits recorded uploads and blocks until every upload has completed.
+    pub(crate) fn finish(
+        &mut self,
+        device_table: &DeviceTable,
+        device: &Device,
+        allocator: &mut Allocator,
+    ) {
+        self.submit(device_table, device);
+        while !self.in_flight.is_empty() {
+            self.retire_oldest(device_table, device, allocator, true);
+        }
+    }
+
+    pub(crate) fn is_idle(&self) -> bool {
+        return self.recording.is_none() && self.in_flight.is_empty();
+    }
+
+    /// Retires finished batches without blocking.
+    pub(crate) fn poll(
+        &mut self,
+        device_table: &DeviceTable,
+        device: &Device,
+        allocator: &mut Allocator,
+    ) {
+        while self.retire_oldest(device_table, device, allocator, false) {}
+    }
+
+    /// Submits the uploads recorded since the last submission as a single batch.
+    pub(crate) fn submit(&mut self, device_table: &DeviceTable, device: &Device) {
+        let recording = match self.recording.take() {
+            Some(recording) => recording,
+            None => return,
+        };
+
+        let fence = create_fence(device_table, **device, 0);
+        let semaphore = match recording.acquire {
+            Some(acquire) => {
+                // The graphics queue waits for the release before acquiring the images
+                let semaphore = create_semaphore(device_table, **device, 0);
+                command_buffer_end_and_submit(
+                    device_table,
+                    recording.transfer,
+                    device.queues.transfer,
+                    None,
+                    0,
+                    Some(semaphore),
+                    None,
+                );
+                command_buffer_end_and_submit(
+                    device_table,
+                    acquire,
+                    device.queues.graphics,
+                    Some(semaphore),
+                    vk::PipelineStageFlagBits::AllCommands as u32,
+                    None,
+                    Some(fence),
+                );
+                Some(semaphore)
+            }
+            None => {
+                command_buffer_end_and_submit(
+                    device_table,
+                    recording.transfer,
+                    device.queues.transfer,
+                    None,
+                    0,
+                    None,
+                    Some(fence),
+                );
+                None
+            }
+        };
+
+        self.in_flight.push_back(Batch {
+            fence,
+            semaphore,
+            transfer: recording.transfer,
+            acquire: recording.acquire,
+            ring_end: self.ring.head,
+            ring_bytes: recording.ring_bytes,
+            dedicated: recording.dedicated,
+        });
+    }
+
+    /// Records a copy of tightly packed `pixels` into the first mip level of `image`, leaving it
+    /// in `ShaderReadOnlyOptimal` layout and owned by the graphics queue family.
+    pub(crate) fn upload_image(
+        &mut self,
+        instance_table: &InstanceTable,
+        device_table: &DeviceTable,
+        allocator: &mut Allocator,
+        device: &Device,
+        image: *mut vk::Image,
+        extent: vk::Extent2D,
+        mip_levels: u32,
+        pixels: &[u8],
+    ) {
+        let size = pixels.len() as vk::DeviceSize;
+        let (buffer, offset, ring_bytes) = if STAGING_RING_SIZE < size {
+            let dedicated = MBB::create(
+                instance_table,
+                device_table,
+                allocator,
+                **device,
+                size,
+                vk::BufferUsageFlagBits::TransferSource as u32,
+                vk::MemoryPropertyFlagBits::HostCoherent as u32
+                    | vk::MemoryPropertyFlagBits::HostVisible as u32,
+            );
+            dedicated.fill(pixels.as_ptr());
+            let buffer = dedicated.buffer;
+            self.recording(device_table, device)
+                .dedicated
+                .push(dedicated);
+            (buffer, 0, 0)
+        } else {
+            let (offset, ring_bytes) = loop {
+                let used = self.ring.used;
+                if let Some(offset) = self.ring.allocate(size, STAGING_ALIGNMENT) {
+                    break (offset, self.ring.used - used);
+                }
+
+                // The ring is full of uploads still in flight, so wait for the oldest
+                self.submit(device_table, device);
+                let retired = self.retire_oldest(device_table, device, allocator, true);
+                debug_assert!(retired, "Staging ring is full without uploads in flight!");
+            };
+            self.staging.write_region(offset, size, pixels.as_ptr());
+            (self.staging.buffer, offset, ring_bytes)
+        };
+
+        let transfer_family = device.queue_family_indices[TRANSFER];
+        let graphics_family = device.queue_family_indices[GRAPHICS];
+        let recording = self.recording(device_table, device);
+        recording.ring_bytes += ring_bytes;
+
+        let barrier = ImageMemoryBarrier {
+            src_access_mask: 0,
+            dst_access_mask: vk::AccessFlagBits::TransferWrite as u32,
+            old_layout: vk::ImageLayout::Undefined,
+            new_layout: vk::ImageLayout::TransferDestinationOptimal,
+            src_queue_family_index: vk::QUEUE_FAMILY_IGNORED,
+            dst_queue_family_index: vk::QUEUE_FAMILY_IGNORED,
+        };
+        record_image_mip_barrier(
+            device_table,
+            recording.transfer,
+            vk::PipelineStageFlagBits::TopOfPipe as u32,
+            vk::PipelineStageFlagBits::Transfer as u32,
+            barrier,
+            image,
+            0,
+            mip_levels,
+        );
+
+        let region = vk::BufferImageCopy {
+            buffer_offset: offset,
+            buffer_row_length: extent.width,
+            buffer_image_height: extent.height,
+            image_subresource: vk::ImageSubresourceLayers {
+                aspect_mask: vk::ImageAspectFlagBits::Color as u32,
+                mip_level: 0,
+                base_array_layer: 0,
+                layer_count: 1,
+            },
+            image_offset: (0, 0, 0).into(),
+            image_extent: (extent.width, extent.height, 1).into(),
+        };
+        (device_table.cmd_copy_buffer_to_image)(
+            recording.transfer,
+            buffer,
+            image,
+            vk::ImageLayout::TransferDestinationOptimal,
+            1,
+            &region,
+        );
+
+        // Blits need a graphics queue, so when generating mip levels the image is handed over to
+        // the graphics family still in the transfer layout
+        let generate_mipmaps = 1 < mip_levels;
+        let (layout, dst_access_mask, dst_stage_mask) = if generate_mipmaps {
+            (
+                vk::ImageLayout::TransferDestinationOptimal,
+                vk::AccessFlagBits::TransferRead as u32 | vk::AccessFlagBits::TransferWrite as u32,
+                vk::PipelineStageFlagBits::Transfer as u32,
+            )
+        } else {
+            (
+                vk::ImageLayout::ShaderReadOnlyOptimal,
+                vk::AccessFlagBits::ShaderRead as u32,
+                vk::PipelineStageFlagBits::FragmentShader as u32,
+            )
+        };
+
+        let graphics = match recording.acquire {
+            Some(acquire) => {
+                // Release on the transfer queue and acquire on the graphics queue, with matching
+                // layouts and families in both barriers
+                let release = ImageMemoryBarrier {
+                    src_access_mask: vk::AccessFlagBits::TransferWrite as u32,
+                    dst_access_mask: 0,
+                    old_layout: vk::ImageLayout::TransferDestinationOptimal,
+                    new_layout: layout,
+                    src_queue_family_index: transfer_family,
+                    dst_queue_family_index: graphics_family,
+                };
+                record_image_mip_barrier(
+                    device_table,
+                    recording.transfer,
+                    vk::PipelineStageFlagBits::Transfer as u32,
+                    vk::PipelineStageFlagBits::BottomOfPipe as u32,
+                    release,
+                    image,
+                    0,
+                    mip_levels,
+                );
+
+                let acquire_barrier = ImageMemoryBarrier {
+                    src_access_mask: 0,
+                    dst_access_mask,
+                    old_layout: vk::ImageLayout::TransferDestinationOptimal,
+                    new_layout: layout,
+                    src_queue_family_index: transfer_family,
+                    dst_queue_family_index: graphics_family,
+                };
+                record_image_mip_barrier(
+                    device_table,
+                    acquire,
+                    vk::PipelineStageFlagBits::AllCommands as u32,
+                    dst_stage_mask,
+                    acquire_barrier,
+                    image,
+                    0,
+                    mip_levels,
+                );
+                acquire
+            }
+            None => {
+                // The transfer queue is in the graphics family, and the first blit waits on the
+                // copy itself
+                if !generate_mipmaps {
+                    let barrier = ImageMemoryBarrier {
+                        src_access_mask: vk::AccessFlagBits::TransferWrite as u32,
+                        dst_access_mask,
+                        old_layout: vk::ImageLayout::TransferDestinationOptimal,
+                        new_layout: layout,
+                        src_queue_family_index: vk::QUEUE_FAMILY_IGNORED,
+                        dst_queue_family_index: vk::QUEUE_FAMILY_IGNORED,
+                    };
+                    record_image_memory_barrier(
+                        device_table,
+                        recording.transfer,
+                        vk::PipelineStageFlagBits::Transfer as u32,
+                        dst_stage_mask,
+                        barrier,
+                        image,
+                    );
+                }
+                recording.transfer
+            }
+        };
+
+        if generate_mipmaps {
+            record_mip_chain(device_table, graphics, image, extent, mip_levels);
+        }
+    }
+
+    /// Returns the batch being recorded, beginning a new one if needed.
+    fn recording(&mut self, device_table: &DeviceTable, device: &Device) -> &mut Recording {
+        let transfer_pool = self.transfer_pool;
+        let graphics_pool = self.graphics_pool;
+        return self.recording.get_or_insert_with(|| {
+            let begin = |pool| {
+                let command_buffer = allocate_command_buffer(
+                    device_table,
+                    **device,
+                    pool,
+                    vk::CommandBufferLevel::Primary,
+                );
+                command_buffer_begin_primary(
+                    device_table,
+                    command_buffer,
+                    vk::CommandBufferUsageFlagBits::OneTimeSubmit as u32,
+                );
+                command_buffer
+            };
+
+            Recording {
+                transfer: begin(transfer_pool),
+                acquire: graphics_pool.map(begin),
+                ring_bytes: 0,
+                dedicated: Vec::new(),
+            }
+        });
+    }
+
+    /// Returns whether a batch was retired. Batches finish in submission order, so only the
+    /// oldest needs checking.
+    fn retire_oldest(
+        &mut self,
+        device_table: &DeviceTable,
+        device: &Device,
+        allocator: &mut Allocator,
+        wait: bool,
+    ) -> bool {
+        let fence = match self.in_flight.front() {
+            Some(batch) => batch.fence,
+            None => return false,
+        };
+        if wait {
+            (device_table.wait_for_fences)(**device, 1, &fence, true as u32, u64::MAX);
+        } else if (device_table.get_fence_status)(**device, fence) != vk::Result::Success {
+            return false;
+        }
+
+        let batch = self.in_flight.pop_front().unwrap();
+        fence_destroy(device_table, **device, batch.fence);
+        if let Some(semaphore) = batch.semaphore {
+            (device_table.destroy_semaphore)(**device, semaphore, null());
+        }
+        (device_table.free_command_buffers)(**device, self.transfer_pool, 1, &batch.transfer);
+        if let (Some(acquire), Some(pool)) = (batch.acquire, self.graphics_pool) {
+            (device_table.free_command_buffers)(**device, pool, 1, &acquire);
+        }
+        for staging in batch.dedicated {
+            staging.destroy(device_table, **device, allocator);
+        }
+
+        self.ring.release(batch.ring_end, batch.ring_bytes);
+        return true;
+    }
+}
+
+/// Fills levels 1 and up by blitting down from the level above, leaving every level ready for
+/// sampling. Every level starts in the transfer destination layout with level 0 written.
+fn record_mip_chain(
+    device_table: &DeviceTable,
+    command_buffer: *mut vk::CommandBuffer,
+    image: *mut vk::Image,
+    extent: vk::Extent2D,
+    mip_levels: u32,
+) {
+    let level_extent = |level: u32| {
+        let width = (extent.width >> level).max(1) as i32;
+        let height = (extent.height >> level).max(1) as i32;
+        vk::Offset3D::from((width, height, 1))
+    };
+    let subresource = |level| vk::ImageSubresourceLayers {
+        aspect_mask: vk::ImageAspectFlagBits::Color as u32,
+        mip_level: level,
+        base_array_layer: 0,
+        layer_count: 1,
+    };
+
+    for level in 1..mip_levels {
+        let source = level - 1;
+        let to_source = ImageMemoryBarrier {
+            src_access_mask: vk::AccessFlagBits::TransferWrite as u32,
+            dst_access_mask: vk::AccessFlagBits::TransferRead as u32,
+            old_layout: vk::ImageLayout::TransferDestinationOptimal,
+            new_layout: vk::ImageLayout::TransferSourceOptimal,
+            src_queue_family_index: vk::QUEUE_FAMILY_IGNORED,
+            dst_queue_family_index: vk::QUEUE_FAMILY_IGNORED,
+        };
+        record_image_mip_barrier(
+            device_table,
+            command_buffer,
+            vk::PipelineStageFlagBits::Transfer as u32,
+            vk::PipelineStageFlagBits::Transfer as u32,
+            to_source,
+            image,
+            source,
+            1,
+        );
+
+        let blit = vk::ImageBlit {
+            src_subresource: subresource(source),
+            src_offsets: [(0, 0, 0).into(), level_extent(source)],
+            dst_subresource: subresource(level),
+            dst_offsets: [(0, 0, 0).into(), level_extent(level)],
+        };
+        (device_table.cmd_blit_image)(
+            command_buffer,
+            image,
+            vk::ImageLayout::TransferSourceOptimal,
+            image,
+            vk::ImageLayout::TransferDestinationOptimal,
+            1,
+            &blit,
+            vk::Filter::Linear,
+        );
+
+        let to_shader = ImageMemoryBarrier {
+            src_access_mask: vk::AccessFlagBits::TransferRead as u32,
+            dst_access_mask: vk::AccessFlagBits::ShaderRead as u32,
+            old_layout: vk::ImageLayout::TransferSourceOptimal,
+            new_layout: vk::ImageLayout::ShaderReadOnlyOptimal,
+            src_queue_family_index: vk::QUEUE_FAMILY_IGNORED,
+            dst_queue_family_index: vk::QUEUE_FAMILY_IGNORED,
+        };
+        record_image_mip_barrier(
+            device_table,
+            command_buffer,
+            vk::PipelineStageFlagBits::Transfer as u32,
+            vk::PipelineStageFlagBits::FragmentShader as u32,
+            to_shader,
+            image,
+            source,
+            1,
+        );
+    }
+
+    // The last level is only ever written
+    let last = ImageMemoryBarrier {
+        src_access_mask: vk::AccessFlagBits::TransferWrite as u32,
+        dst_access_mask: vk::AccessFlagBits::ShaderRead as u32,
+        old_layout: vk::ImageLayout::TransferDestinationOptimal,
+        new_layout: vk::ImageLayout::ShaderReadOnlyOptimal,
+        src_queue_family_index: vk::QUEUE_FAMILY_IGNORED,
+        dst_queue_family_index: vk::QUEUE_FAMILY_IGNORED,
+    };
+    record_image_mip_barrier(
+        device_table,
+        command_buffer,
+        vk::PipelineStageFlagBits::Transfer as u32,
+        vk::PipelineStageFlagBits::FragmentShader as u32,
+        last,
+        image,
+        mip_levels - 1,
+        1,
+    );
+}
+
+/// Hands out space in a circular buffer. Space is released in the order it was handed out, by
+/// moving the tail up to where the head was when the released uploads were submitted.
+struct StagingRing {
+    size: vk::DeviceSize,
+    head: vk::DeviceSize,
+    tail: vk::DeviceSize,
+    /// Bytes between tail and head, including space skipped when wrapping around
+    used: vk::DeviceSize,
+}
+
+impl StagingRing {
+    fn new(size: vk::DeviceSize) -> Self {
+        return Self {
+            size,
+            head: 0,
+            tail: 0,
+            used: 0,
+        };
+    }
+
+    fn allocate(
+        &mut self,
+        size: vk::DeviceSize,
+        alignment: vk::DeviceSize,
+    ) -> Option<vk::DeviceSize> {
+        if self.used == 0 {
+            self.head = 0;
+            self.tail = 0;
+        } else if self.used == self.size {
+            return None;
+        }
+
+        let offset = (self.head + alignment - 1) / alignment * alignment;
+        let end = offset + size;
+        if self.tail <= self.head {
+            // Free space runs to the end of the ring and continues from the start up to the tail
+            if end <= self.size {
+                self.used += end - self.head;
+                self.head = end;
+                return Some(offset);
+            }
+            if size <= self.tail {
+                self.used += self.size - self.head + size;
+                self.head = size;
+                return Some(0);
+            }
+        } else if end <= self.tail {
+            self.used += end - self.head;
+            self.head = end;
+            return Some(offset);
+        }
+
+        return None;
+    }
+
+    fn release(&mut self, end: vk::DeviceSize, bytes: vk::DeviceSize) {
+        self.tail = end;
+        self.used -= bytes;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wraps_around() {
+        let mut ring = StagingRing::new(100);
+        assert_eq!(ring.allocate(40, 1), Some(0));
+        let first = (ring.head, ring.used);
+        assert_eq!(ring.allocate(40, 1), Some(40));
+        assert_eq!(ring.allocate(30, 1), None);
+
+        // Once the first batch retires the allocation wraps, skipping the last 20 bytes
+        ring.release(first.0, first.1);
+        assert_eq!(ring.allocate(30, 1), Some(0));
+        assert_eq!(ring.used, 90);
+        assert_eq!(ring.allocate(20, 1), None);
+        assert_eq!(ring.allocate(10, 1), Some(30));
+    }
+
+    #[test]
+    fn respects_alignment() {
+        let mut ring = StagingRing::new(64);
+        assert_eq!(ring.allocate(3, 16), Some(0));
+        assert_eq!(ring.allocate(3, 16), Some(16));
+        assert_eq!(ring.used, 19);
+    }
+
+    #[test]
+    fn resets_when_empty() {
+        let mut ring = StagingRing::new(64);
+        assert_eq!(ring.allocate(60, 1), Some(0));
+        ring.release(ring.head, ring.used);
+        assert_eq!(ring.allocate(64, 1), Some(0));
+    }
+}
@@ -8,6 +8,30 @@ macro_rules! cstr {
     }};
 }
 
+macro_rules! error {
+    ( $($arg:tt)+ ) => {
+        crate::log::write(crate::log::Level::Error, format_args!($($arg)+))
+    };
+}
+
+macro_rules! warn {
+    ( $($arg:tt)+ ) => {
+        crate::log::write(crate::log::Level::Warn, format_args!($($arg)+))
+    };
+}
+
+macro_rules! info {
+    ( $($arg:tt)+ ) => {
+        crate::log::write(crate::log::Level::Info, format_args!($($arg)+))
+    };
+}
+
+macro_rules! debug {
+    ( $($arg:tt)+ ) => {
+        crate::log::write(crate::log::Level::Debug, format_args!($($arg)+))
+    };
+}
+
 mod input {
     #![allow(dead_code)]
     #[derive(Copy, Clone, Eq, PartialEq)]
@@ -15,8 +39,16 @@ mod input {
         ArrowLeft,
         ArrowRight,
         A,
+        C,
         D,
         Enter,
+        F3,
+        V,
+        W,
+        One,
+        Two,
+        Three,
+        Four,
         Unknown,
     }
 
@@ -30,6 +62,59 @@ mod input {
     }
 }
 
+mod log {
+    use std::{
+        fmt,
+        sync::atomic::{AtomicUsize, Ordering},
+    };
+
+    /// Message severities, most severe first.
+    #[derive(Copy, Clone, Debug, Eq, Ord, PartialEq, PartialOrd)]
+    pub enum Level {
+        Error,
+        Warn,
+        Info,
+        Debug,
+    }
+
+    static MAX_LEVEL: AtomicUsize = AtomicUsize::new(Level::Info as usize);
+
+    /// Reads the most verbose level to print from `THYROX_LOG`, one of `error`, `warn`, `info`
+    /// or `debug`.
+    pub fn init() {
+        let level = match std::env::var("THYROX_LOG").as_deref() {
+            Ok("error") => Level::Error,
+            Ok("warn") => Level::Warn,
+            Ok("debug") => Level::Debug,
+            _ => Level::Info,
+        };
+        MAX_LEVEL.store(level as usize, Ordering::Relaxed);
+    }
+
+    pub fn max_level() -> Level {
+        return match MAX_LEVEL.load(Ordering::Relaxed) {
+            0 => Level::Error,
+            1 => Level::Warn,
+            2 => Level::Info,
+            _ => Level::Debug,
+        };
+    }
+
+    pub fn write(level: Level, args: fmt::Arguments) {
+        if max_level() < level {
+            return;
+        }
+
+        let label = match level {
+            Level::Error => "error",
+            Level::Warn => "warn",
+            Level::Info => "info",
+            Level::Debug => "debug",
+        };
+        eprintln!("[{}] {}", label, args);
+    }
+}
+
 mod math {
     use std::ops::{Add, AddAssign, Neg};
 
@@ -130,20 +215,92 @@ mod math {
     }
 }
 
+mod assets;
 mod ffi;
 mod font;
 mod gfx;
 mod window;
 
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
-use gfx::Renderer;
+use gfx::{
+    GlyphFormat, Outline, PostEffect, Renderer, RendererConfig, SampleCount, SamplerOptions,
+    Shadow, ShapeStyle, SpriteInstance, TextAlignment, TextStyle, TextureOptions, Transform,
+};
 use input::{Event, Key};
-use math::Vector2;
+use math::{Rectangle, Vector2, Vector4};
 use window::Window;
 
+/// The rectangle centred on `position`, in normalized device coordinates.
+fn bounds(position: Vector2, half_width: f32, half_height: f32) -> Rectangle {
+    return Rectangle {
+        left_top: Vector2 {
+            x: position.x - half_width,
+            y: position.y + half_height,
+        },
+        right_bottom: Vector2 {
+            x: position.x + half_width,
+            y: position.y - half_height,
+        },
+    };
+}
+
+/// Outlines the collision boxes, and shows where the ball is heading.
+fn draw_debug_shapes(renderer: &mut Renderer, ball: &Ball, paddles: [&Paddle; 2]) {
+    let green = Vector4 {
+        x: 0.2,
+        y: 1.0,
+        z: 0.2,
+        w: 1.0,
+    };
+    let yellow = Vector4 {
+        x: 1.0,
+        y: 0.9,
+        z: 0.2,
+        w: 1.0,
+    };
+    let debug_layer = 10;
+
+    let outline = ShapeStyle {
+        layer: debug_layer,
+        ..ShapeStyle::outlined(green, 1.0)
+    };
+    renderer.draw_rect(ball.bounds(), outline);
+    for paddle in paddles.iter() {
+        renderer.draw_rect(paddle.bounds(), outline);
+    }
+
+    let trajectory = ShapeStyle {
+        layer: debug_layer,
+        ..ShapeStyle::outlined(yellow, 1.0)
+    };
+    let points = ball.trajectory(60);
+    for segment in points.windows(2) {
+        renderer.draw_line(segment[0], segment[1], trajectory);
+    }
+    if let Some(&end) = points.last() {
+        let marker = ShapeStyle {
+            layer: debug_layer,
+            ..ShapeStyle::filled(yellow)
+        };
+        renderer.draw_circle(end, 4.0, marker);
+    }
+
+    // Exaggerated, as the ball only moves a few pixels a frame
+    let velocity_end = Vector2 {
+        x: ball.position.x + 10.0 * ball.velocity.x,
+        y: ball.position.y + 10.0 * ball.velocity.y,
+    };
+    let velocity = ShapeStyle {
+        layer: debug_layer,
+        ..ShapeStyle::outlined(green, 2.0)
+    };
+    renderer.draw_line(ball.position, velocity_end, velocity);
+}
+
 fn read_png<P: AsRef<std::path::Path>>(path: P) -> (u32, u32, Box<[u8]>) {
-    let decoder = png::Decoder::new(std::fs::File::open(path).unwrap());
+    let data = assets::read(path).expect("Failed to read PNG!");
+    let decoder = png::Decoder::new(&*data);
     let (info, mut reader) = decoder.read_info().unwrap();
     let mut buffer = vec![0; info.buffer_size()].into_boxed_slice();
     reader.next_frame(&mut buffer).unwrap();
@@ -191,7 +348,8 @@ struct Ball {
 impl Ball {
     fn centered(renderer: &mut Renderer, velocity: Vector2) -> Self {
         let position = Vector2 { x: 0.0, y: 0.0 };
-        let handle = renderer.create_sprite_from_path("textures/ball.png");
+        let handle =
+            renderer.create_sprite_from_path("textures/ball.png", TextureOptions::default());
         let (half_width, half_height) = renderer.sprite_half_dimensions(handle);
         let ball = Self {
             position,
@@ -203,6 +361,10 @@ impl Ball {
         return ball;
     }
 
+    fn bounds(&self) -> Rectangle {
+        return bounds(self.position, self.half_width, self.half_height);
+    }
+
     fn simulate(&mut self, top_paddle: &Paddle, bottom_paddle: &Paddle) -> bool {
         self.position += self.velocity;
 
@@ -262,6 +424,92 @@ impl Ball {
 
         return false;
     }
+
+    /// Positions of the ball over the next `steps` frames, bouncing off the sides of the screen
+    /// but ignoring the paddles.
+    fn trajectory(&self, steps: usize) -> Vec<Vector2> {
+        let mut position = self.position;
+        let mut velocity = self.velocity;
+        let mut points = vec![position];
+        for _ in 0..steps {
+            position += velocity;
+            if 1.0 - self.half_width <= position.x.abs() {
+                velocity.x = -velocity.x;
+            }
+            points.push(position);
+        }
+        return points;
+    }
+}
+
+/// Spins copies of the ball around the screen, drawn with a single instanced draw, and logs the
+/// frame rate every second.
+struct Benchmark {
+    instances: Vec<SpriteInstance>,
+    frames: u32,
+    started: Instant,
+    last_report: Instant,
+}
+
+impl Benchmark {
+    const INSTANCES: usize = 100_000;
+
+    fn new() -> Self {
+        let now = Instant::now();
+        let white = Vector4 {
+            x: 1.0,
+            y: 1.0,
+            z: 1.0,
+            w: 1.0,
+        };
+        let instance = SpriteInstance {
+            transform: Transform {
+                scale: Vector2 { x: 0.25, y: 0.25 },
+                ..Transform::new(Vector2 { x: 0.0, y: 0.0 })
+            },
+            tint: white,
+            source: None,
+        };
+        return Self {
+            instances: vec![instance; Self::INSTANCES],
+            frames: 0,
+            started: now,
+            last_report: now,
+        };
+    }
+
+    /// Moves the instances along a spiral that turns over time, and draws them behind the game.
+    fn draw(&mut self, renderer: &mut Renderer, sprite: usize) {
+        let time = self.started.elapsed().as_secs_f32();
+        let count = self.instances.len() as f32;
+        for (i, instance) in self.instances.iter_mut().enumerate() {
+            // Spread evenly over the disc by the golden angle
+            let radius = (i as f32 / count).sqrt();
+            let angle = i as f32 * 2.399_963 + time * (1.0 - radius);
+            instance.transform.position = Vector2 {
+                x: radius * angle.cos(),
+                y: radius * angle.sin(),
+            };
+            instance.transform.rotation = angle;
+            instance.tint.x = radius;
+            instance.tint.z = 1.0 - radius;
+        }
+        renderer.draw_instances(sprite, -2, &self.instances);
+
+        self.frames += 1;
+        let elapsed = self.last_report.elapsed();
+        if Duration::from_secs(1) <= elapsed {
+            let seconds = elapsed.as_secs_f64();
+            info!(
+                "{} sprites: {:.1} frames per second, {:.2} ms per frame",
+                self.instances.len(),
+                self.frames as f64 / seconds,
+                1000.0 * seconds / self.frames as f64
+            );
+            self.frames = 0;
+            self.last_report = Instant::now();
+        }
+    }
 }
 
 struct Paddle {
@@ -272,6 +520,10 @@ struct Paddle {
 }
 
 impl Paddle {
+    fn bounds(&self) -> Rectangle {
+        return bounds(self.position, self.half_width, self.half_height);
+    }
+
     fn bottom(handle: usize, (half_width, half_height): (f32, f32)) -> Self {
         let position = Vector2 {
             x: 0.0,
@@ -326,15 +578,80 @@ enum GameState {
 use slotmap;
 
 fn main() {
-    let font_path = cstr!("/usr/share/fonts/TTF/Comfortaa-Light.ttf");
+    log::init();
+    let font_path = "fonts/DejaVuSans.ttf";
     let mut window = Window::new("Pong!", "rose", 800, 600);
-    let mut renderer = Renderer::init(&window);
-
-    // renderer.draw_text_centered("Usman", Vector2 { x: -1.0, y: 0.0 });
+    let mut config = RendererConfig::default();
+    let mut benchmark = None;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--benchmark" => benchmark = Some(Benchmark::new()),
+            "--device" => config.device = args.next(),
+            "--hot-reload" => config.hot_reload = true,
+            "--msaa" => match args.next().as_deref() {
+                Some("1") => config.samples = SampleCount::One,
+                Some("2") => config.samples = SampleCount::Two,
+                Some("4") => config.samples = SampleCount::Four,
+                Some("8") => config.samples = SampleCount::Eight,
+                _ => warn!("--msaa expects 1, 2, 4 or 8 samples"),
+            },
+            "--post" => {
+                for pass in &mut config.post_passes {
+                    pass.enabled = true;
+                }
+            }
+            "--validation" => config.validation = true,
+            "--wireframe" => config.wireframe = true,
+            _ => warn!("Unknown argument {}", arg),
+        }
+    }
+    let mut renderer = Renderer::init(&window, config);
+    info!("Rendering with {:?} samples per pixel", renderer.samples());
+    let font = renderer.load_font(font_path, 12, GlyphFormat::Coverage);
+    let title_font = renderer.load_font(font_path, 12, GlyphFormat::DistanceField);
+    let text_colour = Vector4 {
+        x: 1.0,
+        y: 1.0,
+        z: 1.0,
+        w: 1.0,
+    };
+    // Both paddles share a texture, the top one is told apart by its tint
+    let top_paddle_tint = Vector4 {
+        x: 1.0,
+        y: 0.6,
+        z: 0.6,
+        w: 1.0,
+    };
+    let title_style = TextStyle {
+        scale: 1.5,
+        outline: Some(Outline {
+            width: 1.5,
+            colour: Vector4 {
+                x: 0.2,
+                y: 0.1,
+                z: 0.3,
+                w: 1.0,
+            },
+        }),
+        shadow: Some(Shadow {
+            offset: Vector2 { x: 2.0, y: -2.0 },
+            colour: Vector4 {
+                x: 0.0,
+                y: 0.0,
+                z: 0.0,
+                w: 0.5,
+            },
+        }),
+        // Behind the paddles and ball, although it's drawn after them
+        layer: -1,
+        ..TextStyle::new(text_colour)
+    };
 
     let delta = 3.0E-2;
     let (mut bottom_paddle, mut top_paddle) = {
-        let handle = renderer.create_sprite_from_path("textures/paddle.png");
+        let handle =
+            renderer.create_sprite_from_path("textures/paddle.png", TextureOptions::default());
         let half_dimensions = renderer.sprite_half_dimensions(handle);
         let bottom = Paddle::bottom(handle, half_dimensions);
         let top = Paddle::top(handle, half_dimensions);
@@ -347,10 +664,49 @@ fn main() {
             y: 9.0E-3,
         },
     );
+    match renderer.load_material("materials/glow.material") {
+        Ok(glow) => renderer.set_sprite_material(ball.handle, Some(glow)),
+        Err(e) => warn!("Failed to load the ball's glow material: {}", e),
+    }
+    info!("GPU memory after loading: {}", renderer.memory_stats());
 
     let mut state = GameState::Neutral;
+    let mut debug_shapes = false;
     while !window.exiting {
         while let Some(event) = window.poll_event() {
+            match event {
+                Event::KeyPress(Key::C) => {
+                    debug_shapes = !debug_shapes;
+                    info!("Debug shapes {}", if debug_shapes { "on" } else { "off" });
+                }
+                Event::KeyPress(Key::F3) => {
+                    let shown = renderer.performance_overlay();
+                    renderer.set_performance_overlay(if shown { None } else { Some(font) });
+                }
+                Event::KeyPress(Key::V) => {
+                    renderer.set_vsync(!renderer.vsync());
+                    info!("Vsync {}", if renderer.vsync() { "on" } else { "off" });
+                }
+                Event::KeyPress(Key::W) => {
+                    let wireframe = renderer.set_wireframe(!renderer.wireframe());
+                    info!("Wireframe overlay {}", if wireframe { "on" } else { "off" });
+                }
+                Event::KeyPress(key @ (Key::One | Key::Two | Key::Three | Key::Four)) => {
+                    let effect = match key {
+                        Key::One => PostEffect::Bloom,
+                        Key::Two => PostEffect::ColourGrading,
+                        Key::Three => PostEffect::Vignette,
+                        _ => PostEffect::Scanlines,
+                    };
+                    if let Some(pass) = renderer.post_pass(effect) {
+                        pass.enabled = !pass.enabled;
+                        info!("{:?} {}", effect, if pass.enabled { "on" } else { "off" });
+                    }
+                }
+                Event::WindowResize(width, height) => renderer.resize(width as u32, height as u32),
+                _ => {}
+            }
+
             match state {
                 GameState::Neutral => {
                     if let Event::KeyPress(Key::Enter) = event {
@@ -372,13 +728,62 @@ fn main() {
         }
 
         if let Some(index) = renderer.begin_scene(0.7, 0.4, 0.8) {
+            if let Some(benchmark) = &mut benchmark {
+                benchmark.draw(&mut renderer, ball.handle);
+            }
             renderer.draw(bottom_paddle.handle, bottom_paddle.position);
             renderer.draw(ball.handle, ball.position);
-            renderer.draw(top_paddle.handle, top_paddle.position);
+            // The top paddle is drawn unfiltered, to compare with the smooth bottom paddle
+            renderer.draw_sprite_with_sampler(
+                top_paddle.handle,
+                Transform::new(top_paddle.position),
+                top_paddle_tint,
+                None,
+                Some(SamplerOptions::pixel_art()),
+            );
+            if let GameState::Neutral = state {
+                // Keep the bottom of the title clear of the ball
+                let title = if renderer.uploads_finished() {
+                    "Pong\nPress Enter to start"
+                } else {
+                    "Pong\nLoading..."
+                };
+                let size = renderer.measure_text(title_font, title, title_style.scale, None, 1.2);
+                renderer.draw_paragraph(
+                    title_font,
+                    title,
+                    Vector2 {
+                        x: 0.0,
+                        y: 0.1 + 0.5 * size.y,
+                    },
+                    title_style,
+                    TextAlignment::Centre,
+                    None,
+                    1.2,
+                );
+                renderer.draw_text(
+                    font,
+                    "A / D",
+                    Vector2 { x: -0.95, y: 0.75 },
+                    text_colour,
+                    TextAlignment::Left,
+                );
+                renderer.draw_text(
+                    font,
+                    "Left / Right",
+                    Vector2 { x: 0.95, y: -0.75 },
+                    text_colour,
+                    TextAlignment::Right,
+                );
+            }
+            if debug_shapes {
+                draw_debug_shapes(&mut renderer, &ball, [&top_paddle, &bottom_paddle]);
+            }
             renderer.end_scene();
             renderer.present(index);
         } else {
-            renderer.resize(&window);
+            // Minimized, or the swapchain is being recreated. Don't spin while waiting.
+            std::thread::sleep(Duration::from_millis(10));
         }
 
         if let GameState::SetActive = state {
@@ -159,13 +159,21 @@ impl Window {
                     unsafe { ((*key_press).response_type, (*key_press).key_code) };
 
                 let key = match key_code {
+                    10 => Key::One,
+                    11 => Key::Two,
+                    12 => Key::Three,
+                    13 => Key::Four,
+                    25 => Key::W,
                     36 => Key::Enter,
                     38 => Key::A,
                     40 => Key::D,
+                    54 => Key::C,
+                    55 => Key::V,
+                    69 => Key::F3,
                     113 => Key::ArrowLeft,
                     114 => Key::ArrowRight,
                     _ => {
-                        println!("Unknown key code {}", key_code);
+                        debug!("Unknown key code {}", key_code);
                         Key::Unknown
                     }
                 };